@@ -9,6 +9,7 @@ use static_buffer::StaticBuffer;
 use syn::{parse_macro_input, DeriveInput};
 
 pub(crate) mod error;
+pub(crate) mod shader_uniform;
 pub(crate) mod static_buffer;
 pub(crate) mod types;
 pub(crate) mod util;
@@ -23,6 +24,12 @@ pub fn vertex_data(input: TokenStream) -> TokenStream {
   vertex_data::process(input)
 }
 
+#[proc_macro_derive(ShaderUniform, attributes(layout, padding))]
+pub fn shader_uniform(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  shader_uniform::process(input)
+}
+
 #[proc_macro]
 pub fn static_buffer(input: TokenStream) -> TokenStream {
   let mut result = TokenStream::new();
@@ -0,0 +1,354 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Attribute, Data, DeriveInput, Fields, Type};
+
+use crate::{types::*, util::lit_expr_to_usize};
+
+#[cfg(feature = "glam-types")]
+use crate::util::PathCaptureExpr;
+
+fn round_up(align: usize, value: usize) -> usize {
+  value.div_ceil(align) * align
+}
+
+/// Memory layout rules of WGSL address spaces.
+///
+/// `uniform` address space follows std140 rules (array strides and struct
+/// alignment are rounded up to 16 bytes) while `storage` address space
+/// follows std430 rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutRules {
+  Std140,
+  Std430,
+}
+
+impl LayoutRules {
+  fn parse(a: &Attribute) -> syn::Result<Self> {
+    let rules: syn::Ident = a.parse_args().map_err(|_| {
+      syn::Error::new_spanned(
+        a,
+        "layout value must be either 'std140' or 'std430'",
+      )
+    })?;
+
+    match rules.to_string().as_str() {
+      "std140" | "uniform" => Ok(LayoutRules::Std140),
+      "std430" | "storage" => Ok(LayoutRules::Std430),
+      other => Err(syn::Error::new_spanned(
+        &rules,
+        format!("unknown layout rules: {}", other),
+      )),
+    }
+  }
+
+  fn struct_alignment(self, align: usize) -> usize {
+    match self {
+      LayoutRules::Std140 => round_up(16, align),
+      LayoutRules::Std430 => align,
+    }
+  }
+}
+
+/// Host-shareable WGSL types a field can map onto.
+#[derive(Debug, Clone)]
+pub enum WgslType {
+  /// `f32`, `i32` or `u32`.
+  Scalar,
+  /// `vecN<T>` with 32-bit scalar components.
+  Vector(usize),
+  /// `matCxR<f32>`.
+  #[cfg_attr(not(feature = "glam-types"), allow(dead_code))]
+  Matrix { columns: usize, rows: usize },
+  /// `array<T, N>`.
+  Array(Box<WgslType>, usize),
+}
+
+impl WgslType {
+  fn parse_type(ty: &Type) -> syn::Result<Self> {
+    let error = |message: &str| Err(syn::Error::new_spanned(ty, message));
+    match ty {
+      Type::Path(tp) => {
+        if let Ok(primitive) = PrimitiveType::try_from(tp.clone()) {
+          return match primitive {
+            PrimitiveType::F32 | PrimitiveType::I32 | PrimitiveType::U32 => {
+              Ok(WgslType::Scalar)
+            }
+            PrimitiveType::Bool => {
+              error("bool is not host-shareable, use u32 instead")
+            }
+            _ => error("WGSL only supports 32-bit scalars"),
+          };
+        }
+        #[cfg(feature = "glam-types")]
+        {
+          if let Ok(Some(name)) = PathCaptureExpr::parse("glam::?")
+            .unwrap()
+            .match_on(&tp.path)
+          {
+            match name.to_lowercase().as_str() {
+              "vec2" | "ivec2" | "uvec2" => return Ok(WgslType::Vector(2)),
+              "vec3" | "ivec3" | "uvec3" => return Ok(WgslType::Vector(3)),
+              "vec4" | "ivec4" | "uvec4" => return Ok(WgslType::Vector(4)),
+              "mat2" => {
+                return Ok(WgslType::Matrix {
+                  columns: 2,
+                  rows: 2,
+                })
+              }
+              "mat3a" => {
+                return Ok(WgslType::Matrix {
+                  columns: 3,
+                  rows: 3,
+                })
+              }
+              "mat4" => {
+                return Ok(WgslType::Matrix {
+                  columns: 4,
+                  rows: 4,
+                })
+              }
+              "mat3" => {
+                return error(
+                  "glam::Mat3 columns aren't padded, use glam::Mat3A",
+                )
+              }
+              _ => {}
+            }
+          }
+        }
+        error("unhandled type (enable types through features)")
+      }
+      Type::Array(arr) => match lit_expr_to_usize(&arr.len) {
+        Some(len) => {
+          Ok(WgslType::Array(Box::new(Self::parse_type(&arr.elem)?), len))
+        }
+        None => Err(syn::Error::new_spanned(
+          &arr.len,
+          "unable to parse array length (must be a literal)",
+        )),
+      },
+      Type::Group(syn::TypeGroup { elem, .. }) => Self::parse_type(elem),
+      _ => error("unhandled type"),
+    }
+  }
+
+  pub fn alignment(&self, rules: LayoutRules) -> usize {
+    match self {
+      WgslType::Scalar => 4,
+      WgslType::Vector(2) => 8,
+      WgslType::Vector(_) => 16,
+      WgslType::Matrix { rows, .. } => WgslType::Vector(*rows).alignment(rules),
+      WgslType::Array(elem, _) => rules.struct_alignment(elem.alignment(rules)),
+    }
+  }
+
+  pub fn size(&self, rules: LayoutRules) -> usize {
+    match self {
+      WgslType::Scalar => 4,
+      WgslType::Vector(n) => 4 * n,
+      WgslType::Matrix { columns, rows } => {
+        let column = WgslType::Vector(*rows);
+        columns * round_up(column.alignment(rules), column.size(rules))
+      }
+      WgslType::Array(_, len) => len * self.stride(rules),
+    }
+  }
+
+  /// Distance between array elements, only meaningful for arrays.
+  pub fn stride(&self, rules: LayoutRules) -> usize {
+    match self {
+      WgslType::Array(elem, _) => {
+        round_up(self.alignment(rules), elem.size(rules))
+      }
+      _ => self.size(rules),
+    }
+  }
+
+  /// Rust always packs `[T; N]` elements, so array strides have to match
+  /// element sizes.
+  fn is_host_compatible(&self, rules: LayoutRules) -> bool {
+    match self {
+      WgslType::Array(elem, _) => {
+        self.stride(rules) == elem.size(rules) && elem.is_host_compatible(rules)
+      }
+      _ => true,
+    }
+  }
+}
+
+pub struct UniformField {
+  member: syn::Member,
+  offset: usize,
+}
+
+pub struct CodeGenerator {
+  target: syn::Ident,
+  rules: LayoutRules,
+  fields: Vec<UniformField>,
+  /// End of the last (non-padding) field.
+  end: usize,
+  alignment: usize,
+}
+
+impl CodeGenerator {
+  fn new(target: syn::Ident) -> Self {
+    CodeGenerator {
+      target,
+      rules: LayoutRules::Std140,
+      fields: Vec::with_capacity(16),
+      end: 0,
+      alignment: 4,
+    }
+  }
+
+  pub fn with_attribute(&mut self, a: Attribute) -> syn::Result<()> {
+    if a.path.is_ident("layout") {
+      self.rules = LayoutRules::parse(&a)?;
+    }
+    Ok(())
+  }
+
+  pub fn with_field(&mut self, index: usize, f: syn::Field) -> syn::Result<()> {
+    // explicit padding only has to keep following fields in place
+    if f.attrs.iter().any(|a| a.path.is_ident("padding")) {
+      return Ok(());
+    }
+
+    let member = match f.ident {
+      Some(ident) => syn::Member::Named(ident),
+      None => syn::Member::Unnamed(index.into()),
+    };
+
+    let ty = WgslType::parse_type(&f.ty)?;
+    if !ty.is_host_compatible(self.rules) {
+      return Err(syn::Error::new_spanned(
+        &f.ty,
+        format!(
+          "array '{}' requires a stride of {} bytes, use an element type of \
+           that size",
+          member_to_string(&member),
+          ty.stride(self.rules)
+        ),
+      ));
+    }
+
+    let align = ty.alignment(self.rules);
+    let offset = round_up(align, self.end);
+
+    self.end = offset + ty.size(self.rules);
+    self.alignment = self.alignment.max(align);
+    self.fields.push(UniformField { member, offset });
+    Ok(())
+  }
+
+  fn alignment(&self) -> usize {
+    self.rules.struct_alignment(self.alignment)
+  }
+
+  fn size(&self) -> usize {
+    round_up(self.alignment(), self.end)
+  }
+
+  pub fn generate_token_stream(&self) -> TokenStream {
+    let target = self.target.clone();
+    let size = self.size();
+    let alignment = self.alignment();
+
+    let offset_checks: Vec<TokenStream2> = self
+      .fields
+      .iter()
+      .map(|f| {
+        let member = &f.member;
+        let offset = f.offset;
+        let message = format!(
+          "field '{}' must be placed at offset {} (insert padding before it)",
+          member_to_string(member),
+          offset
+        );
+        quote! {
+          assert!(::core::mem::offset_of!(#target, #member) == #offset, #message);
+        }
+      })
+      .collect();
+
+    let size_message = format!(
+      "'{}' must be {} bytes large (insert padding at the end)",
+      target, size
+    );
+    quote! {
+      impl crate::render::uniform::ShaderUniform for #target {
+        const SIZE: usize = #size;
+        const ALIGNMENT: usize = #alignment;
+      }
+
+      const _: () = {
+        #(#offset_checks)*
+        assert!(::core::mem::size_of::<#target>() == #size, #size_message);
+      };
+    }
+    .into()
+  }
+}
+
+fn member_to_string(member: &syn::Member) -> String {
+  match member {
+    syn::Member::Named(ident) => ident.to_string(),
+    syn::Member::Unnamed(index) => index.index.to_string(),
+  }
+}
+
+pub fn process(input: DeriveInput) -> TokenStream {
+  match generate(input) {
+    Ok(tokens) => tokens,
+    Err(err) => err.to_compile_error().into(),
+  }
+}
+
+fn generate(input: DeriveInput) -> syn::Result<TokenStream> {
+  if !input.generics.params.is_empty() {
+    return Err(syn::Error::new_spanned(
+      &input.generics,
+      "generic structs can't derive ShaderUniform",
+    ));
+  }
+
+  let mut gen = CodeGenerator::new(input.ident.clone());
+
+  for attribute in input.attrs {
+    gen.with_attribute(attribute)?;
+  }
+
+  let data = match input.data {
+    Data::Struct(data) => data,
+    Data::Enum(_) => {
+      return Err(syn::Error::new_spanned(
+        &input.ident,
+        "enum can't derive ShaderUniform",
+      ))
+    }
+    Data::Union(_) => {
+      return Err(syn::Error::new_spanned(
+        &input.ident,
+        "union can't derive ShaderUniform",
+      ))
+    }
+  };
+
+  let fields = match data.fields {
+    Fields::Named(fields) => fields.named,
+    Fields::Unnamed(fields) => fields.unnamed,
+    Fields::Unit => {
+      return Err(syn::Error::new_spanned(
+        &input.ident,
+        "can't derive ShaderUniform for unit structs",
+      ))
+    }
+  };
+
+  for (i, field) in fields.into_iter().enumerate() {
+    gen.with_field(i, field)?;
+  }
+
+  Ok(gen.generate_token_stream())
+}
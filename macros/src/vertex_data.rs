@@ -182,6 +182,49 @@ impl VertexFormat {
     }
   }
 
+  /// Type of the matching WGSL vertex shader input.
+  ///
+  /// Normalized and 16-bit float formats are converted to `f32` by the GPU.
+  /// 64-bit formats require `wgpu::Features::VERTEX_ATTRIBUTE_64BIT`.
+  fn wgsl_type(self) -> &'static str {
+    match self {
+      VertexFormat::Uint8x2
+      | VertexFormat::Uint16x2
+      | VertexFormat::Uint32x2 => "vec2<u32>",
+      VertexFormat::Uint8x4
+      | VertexFormat::Uint16x4
+      | VertexFormat::Uint32x4 => "vec4<u32>",
+      VertexFormat::Sint8x2
+      | VertexFormat::Sint16x2
+      | VertexFormat::Sint32x2 => "vec2<i32>",
+      VertexFormat::Sint8x4
+      | VertexFormat::Sint16x4
+      | VertexFormat::Sint32x4 => "vec4<i32>",
+      VertexFormat::Unorm8x2
+      | VertexFormat::Snorm8x2
+      | VertexFormat::Unorm16x2
+      | VertexFormat::Snorm16x2
+      | VertexFormat::Float16x2
+      | VertexFormat::Float32x2 => "vec2<f32>",
+      VertexFormat::Unorm8x4
+      | VertexFormat::Snorm8x4
+      | VertexFormat::Unorm16x4
+      | VertexFormat::Snorm16x4
+      | VertexFormat::Float16x4
+      | VertexFormat::Float32x4 => "vec4<f32>",
+      VertexFormat::Float32 => "f32",
+      VertexFormat::Float32x3 => "vec3<f32>",
+      VertexFormat::Uint32 => "u32",
+      VertexFormat::Uint32x3 => "vec3<u32>",
+      VertexFormat::Sint32 => "i32",
+      VertexFormat::Sint32x3 => "vec3<i32>",
+      VertexFormat::Float64 => "f64",
+      VertexFormat::Float64x2 => "vec2<f64>",
+      VertexFormat::Float64x3 => "vec3<f64>",
+      VertexFormat::Float64x4 => "vec4<f64>",
+    }
+  }
+
   fn to_path(self) -> TokenStream2 {
     let path: syn::Path =
       syn::parse_str(&format!("wgpu::VertexFormat::{}", self.to_string()))
@@ -207,6 +250,7 @@ impl TryFrom<syn::Path> for VertexFormat {
 }
 
 pub struct VertexInfo {
+  name: String,
   format: VertexFormat,
   offset: usize,
   shader_location: usize,
@@ -235,7 +279,8 @@ impl CodeGenerator {
 
     match seg.to_string().as_str() {
       "array_stride" => {
-        let stride = syn::parse::<syn::LitInt>(a.tokens.into())
+        let stride: syn::LitInt = a
+          .parse_args()
           .expect("array_stride value must be an integer");
         self.array_stride = Some(
           stride
@@ -244,18 +289,23 @@ impl CodeGenerator {
         );
       }
       "step_mode" => {
-        let path = syn::parse::<syn::Path>(a.tokens.into()).expect(
+        let path: syn::Path = a.parse_args().expect(
           "step_mode value must be a path to a wgpu::VertexStepMode variant",
         );
 
-        if PathCaptureExpr::parse("wgpu::VertexStepMode::?")
+        // allows both 'Instance' and 'wgpu::VertexStepMode::Instance'
+        match PathCaptureExpr::parse("wgpu::VertexStepMode::?")
           .unwrap()
           .match_on(&path)
-          .is_ok()
         {
-          self.step_mode = path.clone();
-        } else {
-          panic!("step_mode value must be a path to a wgpu::VertexStepMode variant ('VertexStepMode::Vertex' or 'VertexStepMode::Instance')")
+          Ok(Some(variant)) if variant == "Vertex" || variant == "Instance" => {
+            self.step_mode = syn::parse_str(&format!(
+              "wgpu::VertexStepMode::{}",
+              variant
+            ))
+            .unwrap();
+          }
+          _ => panic!("step_mode value must be a path to a wgpu::VertexStepMode variant ('VertexStepMode::Vertex' or 'VertexStepMode::Instance')"),
         }
       }
       _ => {}
@@ -273,6 +323,11 @@ impl CodeGenerator {
   }
 
   pub fn with_field(&mut self, f: syn::Field) {
+    let name = match &f.ident {
+      Some(ident) => ident.to_string(),
+      None => format!("field_{}", self.fields.len()),
+    };
+
     // infer from type first
     let mut format = VertexFormat::parse_type(f.ty);
     let mut offset = self
@@ -331,6 +386,7 @@ impl CodeGenerator {
     }
 
     self.fields.push(VertexInfo {
+      name,
       format,
       offset,
       shader_location,
@@ -346,14 +402,18 @@ impl CodeGenerator {
 
     let mut total_size = 0;
 
+    let mut wgsl_struct = format!("struct {} {{\n", target);
+
     for f in &self.fields {
       let offset = f.offset;
       let shader_location = f.shader_location;
       let format = if f.normalized {
-        f.format.to_norm().expect("can't normalize type").to_path()
+        f.format.to_norm().expect("can't normalize type")
       } else {
-        f.format.to_path()
+        f.format
       };
+      let wgsl_type = format.wgsl_type();
+      let format = format.to_path();
 
       layout_fields.push(quote! {wgpu::VertexAttribute {
         offset: #offset as wgpu::BufferAddress,
@@ -362,11 +422,17 @@ impl CodeGenerator {
       }});
 
       total_size += f.format.size();
+
+      wgsl_struct += &format!(
+        "    @location({}) {}: {},\n",
+        shader_location, f.name, wgsl_type
+      );
     }
+    wgsl_struct += "};\n";
 
     let array_stride = match self.array_stride {
       Some(stride) => {
-        quote! {#stride}
+        quote! {#stride as wgpu::BufferAddress}
       }
       None => {
         quote! {std::mem::size_of::<#target>() as wgpu::BufferAddress}
@@ -382,6 +448,7 @@ impl CodeGenerator {
           step_mode: #step_mode,
           attributes: Self::ATTRIBUTES
         };
+        const WGSL_STRUCT: &'static str = #wgsl_struct;
       }
     }.into()
   }
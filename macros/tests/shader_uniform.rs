@@ -0,0 +1,106 @@
+use macros::ShaderUniform;
+
+// generated impls name the trait by its path in the main crate
+mod render {
+  pub mod uniform {
+    pub trait ShaderUniform {
+      const SIZE: usize;
+      const ALIGNMENT: usize;
+    }
+  }
+}
+
+#[repr(C)]
+#[derive(ShaderUniform)]
+struct Scalars {
+  exposure: f32,
+  frame: u32,
+  offset: i32,
+  #[padding]
+  _pad: u32,
+}
+
+#[repr(C)]
+#[derive(ShaderUniform)]
+#[layout(std430)]
+struct Weights {
+  count: u32,
+  weights: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(ShaderUniform)]
+#[layout(std430)]
+struct Nested {
+  kernel: [[f32; 3]; 3],
+  scale: f32,
+}
+
+#[test]
+fn scalars() {
+  use render::uniform::ShaderUniform;
+
+  assert_eq!(Scalars::SIZE, 16);
+  assert_eq!(Scalars::ALIGNMENT, 16);
+}
+
+#[test]
+fn arrays() {
+  use render::uniform::ShaderUniform;
+
+  assert_eq!(Weights::SIZE, 16);
+  assert_eq!(Weights::ALIGNMENT, 4);
+
+  assert_eq!(Nested::SIZE, 40);
+  assert_eq!(Nested::ALIGNMENT, 4);
+}
+
+#[cfg(feature = "glam-types")]
+mod glam_types {
+  use macros::ShaderUniform;
+
+  use crate::render::uniform::ShaderUniform;
+
+  #[repr(C)]
+  #[derive(ShaderUniform)]
+  struct Camera {
+    view_proj: glam::Mat4,
+    position: glam::Vec3,
+    time: f32,
+  }
+
+  #[repr(C)]
+  #[derive(ShaderUniform)]
+  struct Light {
+    direction: glam::Vec3,
+    #[padding]
+    _pad: u32,
+    color: [glam::Vec4; 2],
+    intensity: f32,
+    #[padding]
+    _tail: [u32; 3],
+  }
+
+  #[repr(C)]
+  #[derive(ShaderUniform)]
+  #[layout(std430)]
+  struct Particles {
+    weights: [f32; 4],
+    offset: glam::Vec2,
+  }
+
+  #[test]
+  fn std140() {
+    assert_eq!(Camera::SIZE, 80);
+    assert_eq!(Camera::ALIGNMENT, 16);
+
+    assert_eq!(Light::SIZE, 64);
+    assert_eq!(Light::ALIGNMENT, 16);
+  }
+
+  #[test]
+  fn std430() {
+    assert_eq!(Particles::SIZE, 24);
+    assert_eq!(Particles::ALIGNMENT, 8);
+  }
+}
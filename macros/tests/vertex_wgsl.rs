@@ -0,0 +1,70 @@
+use macros::VertexData;
+
+trait VertexData<'l> {
+  const SIZE: usize;
+  const ATTRIBUTES: &'l [wgpu::VertexAttribute];
+  const LAYOUT: wgpu::VertexBufferLayout<'l>;
+  const WGSL_STRUCT: &'l str;
+}
+
+#[repr(C)]
+#[derive(VertexData)]
+struct Vertex {
+  position: [f32; 3],
+  uv: [f32; 2],
+  light: [u8; 4],
+  block: [u32; 1],
+}
+
+#[repr(C)]
+#[derive(VertexData)]
+struct Packed([f32; 4], [i16; 2]);
+
+#[test]
+fn named_fields() {
+  assert_eq!(
+    Vertex::WGSL_STRUCT,
+    "struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) light: vec4<u32>,
+    @location(3) block: u32,
+};
+"
+  );
+}
+
+#[test]
+fn unnamed_fields() {
+  assert_eq!(
+    Packed::WGSL_STRUCT,
+    "struct Packed {
+    @location(0) field_0: vec4<f32>,
+    @location(1) field_1: vec2<i32>,
+};
+"
+  );
+}
+
+#[repr(C)]
+#[derive(VertexData)]
+#[step_mode(wgpu::VertexStepMode::Instance)]
+struct Instance {
+  offset: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(VertexData)]
+#[step_mode(Instance)]
+#[array_stride(32)]
+struct ShortInstance {
+  offset: [f32; 4],
+}
+
+#[test]
+fn instance_step_mode() {
+  assert_eq!(Vertex::LAYOUT.step_mode, wgpu::VertexStepMode::Vertex);
+  assert_eq!(Instance::LAYOUT.step_mode, wgpu::VertexStepMode::Instance);
+  assert_eq!(ShortInstance::LAYOUT.step_mode, wgpu::VertexStepMode::Instance);
+  assert_eq!(ShortInstance::LAYOUT.array_stride, 32);
+}
@@ -1,7 +1,100 @@
+use std::collections::HashMap;
+
+use bevy_ecs::system::Resource;
 use glam::Vec4;
 
+use crate::content::light::MAX_LIGHT;
+
+/// How block faces are blended with what's behind them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RenderLayer {
+    #[default]
+    Opaque,
+    /// Fully transparent where alpha is below a half, like leaves.
+    Cutout,
+    /// Blended with what's behind, like glass or water.
+    Translucent,
+}
+
+#[derive(Debug, Clone)]
 pub struct Block {
-    color: Vec4,
+    pub color: Vec4,
+    pub layer: RenderLayer,
+    /// Block light level the block emits, at most [`MAX_LIGHT`].
+    pub emission: u8,
+    /// Opaque blocks stop light from passing through them.
+    pub opaque: bool,
+}
+
+impl Block {
+    pub fn new(color: Vec4) -> Self {
+        Block {
+            color,
+            layer: RenderLayer::Opaque,
+            emission: 0,
+            opaque: true,
+        }
+    }
+
+    pub fn with_emission(mut self, emission: u8) -> Self {
+        self.emission = emission.min(MAX_LIGHT);
+        self
+    }
+
+    pub fn with_layer(mut self, layer: RenderLayer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn transparent(mut self) -> Self {
+        self.opaque = false;
+        self
+    }
 }
 
 pub type BlockID = u32;
+
+/// Empty space, chunks are filled with it by default.
+pub const AIR: BlockID = 0;
+
+/// Definitions of blocks by their ID.
+///
+/// Blocks without a definition are treated as opaque and unlit.
+#[derive(Debug, Clone, Resource)]
+pub struct BlockRegistry {
+    blocks: HashMap<BlockID, Block>,
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        let mut blocks = HashMap::new();
+        blocks.insert(AIR, Block::new(Vec4::ZERO).transparent());
+        BlockRegistry { blocks }
+    }
+}
+
+impl BlockRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, id: BlockID, block: Block) {
+        self.blocks.insert(id, block);
+    }
+
+    pub fn get(&self, id: BlockID) -> Option<&Block> {
+        self.blocks.get(&id)
+    }
+
+    pub fn layer(&self, id: BlockID) -> RenderLayer {
+        self.get(id).map(|it| it.layer).unwrap_or_default()
+    }
+
+    pub fn emission(&self, id: BlockID) -> u8 {
+        self.get(id).map(|it| it.emission).unwrap_or(0)
+    }
+
+    pub fn is_opaque(&self, id: BlockID) -> bool {
+        self.get(id).map(|it| it.opaque).unwrap_or(true)
+    }
+}
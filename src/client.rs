@@ -0,0 +1,47 @@
+use glam::Vec4;
+
+use crate::{
+    block::{Block, BlockRegistry},
+    render::{chunk::ChunkPipelines, config::StateConfig, post::HDR_FORMAT, RendererState},
+    state::loading::GROUND,
+    ui::{Theme, UiInput},
+};
+
+/// Everything game states are run with.
+pub struct Client {
+    pub renderer: RendererState,
+    /// Mouse state of the current frame, fed from window events.
+    pub input: UiInput,
+    pub theme: Theme,
+    pub blocks: BlockRegistry,
+    /// Pipelines chunk meshes of the world are drawn with.
+    pub chunk_pipelines: ChunkPipelines,
+}
+
+impl Client {
+    pub fn new(mut renderer: RendererState) -> Self {
+        let mut blocks = BlockRegistry::new();
+        blocks.register(GROUND, Block::new(Vec4::new(0.35, 0.55, 0.25, 1.0)));
+        let chunk_pipelines = ChunkPipelines::register(&mut renderer.renderer, HDR_FORMAT);
+        Client {
+            renderer,
+            input: UiInput::new(),
+            theme: Theme::default(),
+            blocks,
+            chunk_pipelines,
+        }
+    }
+
+    /// Switches to `config` and saves it for the next start.
+    pub fn set_config(&mut self, config: StateConfig) {
+        self.renderer.set_config(config);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(err) = self
+            .renderer
+            .config()
+            .save(crate::render::config::CONFIG_PATH)
+        {
+            tracing::warn!("unable to save settings: {}", err);
+        }
+    }
+}
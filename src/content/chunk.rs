@@ -1,6 +1,6 @@
 use std::default;
 
-use crate::block::BlockID;
+use crate::block::{BlockID, AIR};
 
 use super::world::CHUNK_SIZE;
 
@@ -16,3 +16,17 @@ impl Default for ArrayChunk {
         }
     }
 }
+
+impl ArrayChunk {
+    pub fn get(&self, x: usize, y: usize, z: usize) -> BlockID {
+        self.blocks[x][y][z]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: BlockID) {
+        self.blocks[x][y][z] = block;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.iter().flatten().flatten().all(|it| *it == AIR)
+    }
+}
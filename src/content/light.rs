@@ -0,0 +1,421 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::block::{BlockID, BlockRegistry};
+
+use super::world::{BlockCoord, BlockPos, ChunkCoord, ChunkMap, CHUNK_SIZE};
+
+/// Brightest light level, sunlight has it in open sky.
+pub const MAX_LIGHT: u8 = 15;
+
+const DIRECTIONS: [[BlockPos; 3]; 6] = [
+    [-1, 0, 0],
+    [1, 0, 0],
+    [0, -1, 0],
+    [0, 1, 0],
+    [0, 0, -1],
+    [0, 0, 1],
+];
+const DOWN: [BlockPos; 3] = [0, -1, 0];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Light {
+    /// Light coming from the sky.
+    pub sky: u8,
+    /// Light emitted by blocks.
+    pub block: u8,
+}
+
+impl Light {
+    /// Light of unloaded space, which is treated as open sky.
+    pub const OPEN_SKY: Light = Light {
+        sky: MAX_LIGHT,
+        block: 0,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Channel {
+    Sky,
+    Block,
+}
+
+/// Light levels of chunk blocks, packed into a nibble per channel.
+#[derive(Debug, Clone)]
+pub struct ChunkLight {
+    levels: Vec<u8>,
+}
+
+impl Default for ChunkLight {
+    fn default() -> Self {
+        ChunkLight {
+            levels: vec![0; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE],
+        }
+    }
+}
+
+impl ChunkLight {
+    fn index([x, y, z]: [usize; 3]) -> usize {
+        (x * CHUNK_SIZE + y) * CHUNK_SIZE + z
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Light {
+        let level = self.levels[Self::index([x, y, z])];
+        Light {
+            sky: level >> 4,
+            block: level & 0xF,
+        }
+    }
+
+    fn level(&self, local: [usize; 3], channel: Channel) -> u8 {
+        let light = self.get(local[0], local[1], local[2]);
+        match channel {
+            Channel::Sky => light.sky,
+            Channel::Block => light.block,
+        }
+    }
+
+    fn set_level(&mut self, local: [usize; 3], channel: Channel, level: u8) {
+        let packed = &mut self.levels[Self::index(local)];
+        *packed = match channel {
+            Channel::Sky => (*packed & 0xF) | (level << 4),
+            Channel::Block => (*packed & 0xF0) | level,
+        };
+    }
+}
+
+/// Sky and block light of loaded chunks.
+///
+/// Light spreads with a flood fill, losing a level with every block it passes
+/// through. Sunlight at [`MAX_LIGHT`] travels down without losing any, so
+/// everything under open sky is fully lit. Space above the highest loaded
+/// chunk of a column is assumed to be open sky.
+#[derive(Debug, Clone, Default)]
+pub struct LightMap {
+    chunks: HashMap<ChunkCoord, ChunkLight>,
+}
+
+impl LightMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn chunk(&self, coord: ChunkCoord) -> Option<&ChunkLight> {
+        self.chunks.get(&coord)
+    }
+
+    pub fn get(&self, pos: BlockCoord) -> Light {
+        let [x, y, z] = pos.local();
+        match self.chunk(pos.chunk()) {
+            Some(light) => light.get(x, y, z),
+            None => Light::OPEN_SKY,
+        }
+    }
+
+    /// Lights a chunk that was just added to `chunks` and spreads light
+    /// between it and its neighbors.
+    ///
+    /// Returns chunks whose light changed.
+    pub fn insert_chunk(
+        &mut self,
+        coord: ChunkCoord,
+        chunks: &ChunkMap,
+        blocks: &BlockRegistry,
+    ) -> HashSet<ChunkCoord> {
+        self.chunks.insert(coord, ChunkLight::default());
+        let mut flood = Flood::new(&mut self.chunks, chunks, blocks);
+        flood.changed.insert(coord);
+
+        let mut sky = VecDeque::new();
+        let mut block = VecDeque::new();
+        if let Some(chunk) = chunks.get(&coord) {
+            for x in 0..CHUNK_SIZE {
+                for y in 0..CHUNK_SIZE {
+                    for z in 0..CHUNK_SIZE {
+                        let pos = coord.block([x, y, z]);
+                        let emission = blocks.emission(chunk.get(x, y, z));
+                        if emission > 0 {
+                            flood.set_level(pos, Channel::Block, emission);
+                            block.push_back(pos);
+                        }
+                        if y == CHUNK_SIZE - 1 && flood.is_sky_source(pos) {
+                            flood.set_level(pos, Channel::Sky, MAX_LIGHT);
+                            sky.push_back(pos);
+                        }
+                    }
+                }
+            }
+        }
+
+        // pull in light from neighbors, light from the chunk above also
+        // keeps shining down at full strength
+        for offset in DIRECTIONS {
+            let neighbor = coord.offset(offset[0] as i32, offset[1] as i32, offset[2] as i32);
+            if !flood.light.contains_key(&neighbor) {
+                continue;
+            }
+            for pos in border(neighbor, offset.map(|it| -it)) {
+                sky.push_back(pos);
+                block.push_back(pos);
+            }
+        }
+
+        flood.spread(Channel::Sky, sky);
+        flood.spread(Channel::Block, block);
+
+        // columns below were lit as if they were under open sky
+        let below = coord.offset(0, -1, 0);
+        if flood.light.contains_key(&below) {
+            let mut removed = VecDeque::new();
+            for pos in border(below, [0, 1, 0]) {
+                let above = pos.offset(0, 1, 0);
+                if flood.level(pos, Channel::Sky) == Some(MAX_LIGHT)
+                    && flood.level(above, Channel::Sky) != Some(MAX_LIGHT)
+                {
+                    flood.set_level(pos, Channel::Sky, 0);
+                    removed.push_back((pos, MAX_LIGHT));
+                }
+            }
+            let relight = flood.unspread(Channel::Sky, removed);
+            flood.spread(Channel::Sky, relight);
+        }
+
+        flood.changed
+    }
+
+    /// Drops light of a chunk that was just removed from `chunks` along with
+    /// light it spread into its neighbors.
+    ///
+    /// Returns chunks whose light changed.
+    pub fn remove_chunk(
+        &mut self,
+        coord: ChunkCoord,
+        chunks: &ChunkMap,
+        blocks: &BlockRegistry,
+    ) -> HashSet<ChunkCoord> {
+        let light = match self.chunks.remove(&coord) {
+            Some(it) => it,
+            None => return HashSet::new(),
+        };
+
+        let mut flood = Flood::new(&mut self.chunks, chunks, blocks);
+        for channel in [Channel::Sky, Channel::Block] {
+            let removed = DIRECTIONS
+                .into_iter()
+                .flat_map(|direction| border(coord, direction))
+                .map(|pos| (pos, light.level(pos.local(), channel)))
+                .collect();
+            let mut relight = flood.unspread(channel, removed);
+
+            // the chunk below is under open sky now
+            if channel == Channel::Sky {
+                for pos in border(coord.offset(0, -1, 0), [0, 1, 0]) {
+                    if flood.is_sky_source(pos) {
+                        flood.set_level(pos, channel, MAX_LIGHT);
+                        relight.push_back(pos);
+                    }
+                }
+            }
+
+            flood.spread(channel, relight);
+        }
+
+        flood.changed
+    }
+
+    /// Updates light after the block at `pos` changed in `chunks`.
+    ///
+    /// Returns chunks whose light changed.
+    pub fn update_block(
+        &mut self,
+        pos: BlockCoord,
+        chunks: &ChunkMap,
+        blocks: &BlockRegistry,
+    ) -> HashSet<ChunkCoord> {
+        let mut flood = Flood::new(&mut self.chunks, chunks, blocks);
+        let block = match flood.block(pos) {
+            Some(it) => it,
+            None => return flood.changed,
+        };
+
+        for channel in [Channel::Sky, Channel::Block] {
+            // darken everything lit through the block, then let light flow
+            // back in from whatever still has it
+            let level = flood.level(pos, channel).unwrap_or(0);
+            flood.set_level(pos, channel, 0);
+            let mut relight = flood.unspread(channel, VecDeque::from([(pos, level)]));
+
+            if !blocks.is_opaque(block) {
+                for offset in DIRECTIONS {
+                    relight.push_back(pos.offset(offset[0], offset[1], offset[2]));
+                }
+            }
+            let source = flood.source(pos, channel);
+            if source > 0 {
+                flood.set_level(pos, channel, source);
+                relight.push_back(pos);
+            }
+
+            flood.spread(channel, relight);
+        }
+
+        flood.changed
+    }
+}
+
+/// Blocks of a chunk on its side facing `direction`.
+fn border(coord: ChunkCoord, direction: [BlockPos; 3]) -> impl Iterator<Item = BlockCoord> {
+    let axis = direction.iter().position(|it| *it != 0).unwrap_or(0);
+    let layer = if direction[axis] > 0 {
+        CHUNK_SIZE - 1
+    } else {
+        0
+    };
+    (0..CHUNK_SIZE).flat_map(move |a| {
+        (0..CHUNK_SIZE).map(move |b| {
+            let mut local = [0; 3];
+            local[axis] = layer;
+            local[(axis + 1) % 3] = a;
+            local[(axis + 2) % 3] = b;
+            coord.block(local)
+        })
+    })
+}
+
+/// State of a single light update.
+struct Flood<'a> {
+    light: &'a mut HashMap<ChunkCoord, ChunkLight>,
+    chunks: &'a ChunkMap,
+    blocks: &'a BlockRegistry,
+    changed: HashSet<ChunkCoord>,
+}
+
+impl<'a> Flood<'a> {
+    fn new(
+        light: &'a mut HashMap<ChunkCoord, ChunkLight>,
+        chunks: &'a ChunkMap,
+        blocks: &'a BlockRegistry,
+    ) -> Self {
+        Flood {
+            light,
+            chunks,
+            blocks,
+            changed: HashSet::new(),
+        }
+    }
+
+    fn block(&self, pos: BlockCoord) -> Option<BlockID> {
+        let [x, y, z] = pos.local();
+        self.chunks
+            .get(&pos.chunk())
+            .map(|chunk| chunk.get(x, y, z))
+    }
+
+    /// Whether light can enter the block, unloaded blocks are never lit.
+    fn is_transparent(&self, pos: BlockCoord) -> bool {
+        self.light.contains_key(&pos.chunk())
+            && self
+                .block(pos)
+                .map(|it| !self.blocks.is_opaque(it))
+                .unwrap_or(false)
+    }
+
+    /// Whether the block is directly under open sky.
+    fn is_sky_source(&self, pos: BlockCoord) -> bool {
+        let above = pos.offset(0, 1, 0);
+        self.is_transparent(pos) && !self.light.contains_key(&above.chunk())
+    }
+
+    /// Level of light the block is lit with regardless of its surroundings.
+    fn source(&self, pos: BlockCoord, channel: Channel) -> u8 {
+        match channel {
+            Channel::Sky if self.is_sky_source(pos) => MAX_LIGHT,
+            Channel::Sky => 0,
+            Channel::Block => self
+                .block(pos)
+                .map(|it| self.blocks.emission(it))
+                .unwrap_or(0),
+        }
+    }
+
+    fn level(&self, pos: BlockCoord, channel: Channel) -> Option<u8> {
+        self.light
+            .get(&pos.chunk())
+            .map(|light| light.level(pos.local(), channel))
+    }
+
+    fn set_level(&mut self, pos: BlockCoord, channel: Channel, level: u8) {
+        if let Some(light) = self.light.get_mut(&pos.chunk()) {
+            light.set_level(pos.local(), channel, level);
+            self.changed.insert(pos.chunk());
+        }
+    }
+
+    /// Level light at `level` reaches a neighbor in `direction` with.
+    fn falloff(channel: Channel, direction: [BlockPos; 3], level: u8) -> u8 {
+        if channel == Channel::Sky && direction == DOWN && level == MAX_LIGHT {
+            MAX_LIGHT
+        } else {
+            level.saturating_sub(1)
+        }
+    }
+
+    /// Spreads light from already lit blocks in `queue`.
+    fn spread(&mut self, channel: Channel, mut queue: VecDeque<BlockCoord>) {
+        while let Some(pos) = queue.pop_front() {
+            let level = match self.level(pos, channel) {
+                Some(it) if it > 0 => it,
+                _ => continue,
+            };
+
+            for direction in DIRECTIONS {
+                let next = pos.offset(direction[0], direction[1], direction[2]);
+                let next_level = Self::falloff(channel, direction, level);
+                if self.is_transparent(next)
+                    && self.level(next, channel).unwrap_or(MAX_LIGHT) < next_level
+                {
+                    self.set_level(next, channel, next_level);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Darkens blocks lit by the already darkened blocks in `queue`, which
+    /// hold the level they had.
+    ///
+    /// Returns blocks lit from elsewhere that need to spread their light again.
+    fn unspread(
+        &mut self,
+        channel: Channel,
+        mut queue: VecDeque<(BlockCoord, u8)>,
+    ) -> VecDeque<BlockCoord> {
+        let mut relight = VecDeque::new();
+        while let Some((pos, level)) = queue.pop_front() {
+            for direction in DIRECTIONS {
+                let next = pos.offset(direction[0], direction[1], direction[2]);
+                let next_level = match self.level(next, channel) {
+                    Some(it) if it > 0 => it,
+                    _ => continue,
+                };
+
+                let lit_by_pos = next_level < level
+                    || (next_level == MAX_LIGHT
+                        && Self::falloff(channel, direction, level) == MAX_LIGHT);
+                if !lit_by_pos {
+                    relight.push_back(next);
+                    continue;
+                }
+
+                self.set_level(next, channel, 0);
+                queue.push_back((next, next_level));
+                // sources keep their own light
+                let source = self.source(next, channel);
+                if source > 0 {
+                    self.set_level(next, channel, source);
+                    relight.push_back(next);
+                }
+            }
+        }
+        relight
+    }
+}
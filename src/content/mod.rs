@@ -1,9 +1,5 @@
 pub mod chunk;
+pub mod light;
 pub mod sdf;
 pub mod world;
 pub mod resouces;
-
-pub enum GameState {
-    Menu(),
-    World(),
-}
@@ -12,7 +12,10 @@ use byteorder::LE;
 use enum_kinds::EnumKind;
 use serde::{Deserialize, Serialize};
 
-use crate::{error::ResourceError, render::texture::TextureResource};
+use crate::{
+    error::ResourceError,
+    render::{shader::ShaderResource, texture::TextureResource},
+};
 
 pub trait Storable: Sized {
     /// Saves resource into a byte sink
@@ -29,6 +32,7 @@ pub trait Storable: Sized {
 )]
 pub enum AnyResource {
     Texture(TextureResource),
+    Shader(ShaderResource),
 }
 
 impl AnyResource {
@@ -43,6 +47,12 @@ impl From<TextureResource> for AnyResource {
     }
 }
 
+impl From<ShaderResource> for AnyResource {
+    fn from(s: ShaderResource) -> Self {
+        AnyResource::Shader(s)
+    }
+}
+
 impl Storable for AnyResource {
     fn save<W: Write>(&self, out: &mut W) -> Result<(), ResourceError> {
         use byteorder::WriteBytesExt;
@@ -50,6 +60,7 @@ impl Storable for AnyResource {
         out.write_u8(self.tag());
         match self {
             AnyResource::Texture(t) => t.save(out),
+            AnyResource::Shader(s) => s.save(out),
         }
     }
 
@@ -62,6 +73,7 @@ impl Storable for AnyResource {
 
         Ok(match kind {
             ResourceKind::Texture => AnyResource::Texture(TextureResource::load(source)?),
+            ResourceKind::Shader => AnyResource::Shader(ShaderResource::load(source)?),
         })
     }
 }
@@ -90,6 +102,11 @@ impl<R: Storable> ResourceBank<R> {
         Ok(())
     }
 
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&R> {
+        let i = *self.paths.get(path.as_ref())?;
+        self.resources.get(i)
+    }
+
     pub fn is_loaded(&self) -> bool {
         match self {
             ResourceBank::Path(_) => false,
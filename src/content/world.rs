@@ -1,11 +1,22 @@
-use std::{mem::MaybeUninit, ops::Range};
+use std::{
+    collections::{HashMap, HashSet},
+    mem::MaybeUninit,
+    ops::Range,
+};
 
-use bevy_ecs::world::World as BevyECS;
+use bevy_ecs::{
+    prelude::*,
+    schedule::IntoSystemDescriptor,
+    world::{Mut, World as BevyECS},
+};
 use bytemuck::{Pod, Zeroable};
 
-use crate::math::{Point, AABB};
+use crate::{
+    block::{BlockID, BlockRegistry},
+    math::{Point, AABB},
+};
 
-use super::chunk::ArrayChunk;
+use super::{chunk::ArrayChunk, light::LightMap};
 
 pub type BlockPos = i64;
 pub type ChunkPos = i32;
@@ -13,7 +24,7 @@ pub type ChunkPos = i32;
 pub const CHUNK_SIZE: usize = 32;
 pub const CHUNK_DISTANCE: usize = 10;
 
-#[derive(Debug, Clone, Copy, Hash, Pod, Zeroable)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Pod, Zeroable)]
 #[repr(C)]
 pub struct BlockCoord {
     x: BlockPos,
@@ -21,13 +32,44 @@ pub struct BlockCoord {
     z: BlockPos,
 }
 
+impl BlockCoord {
+    pub fn new(x: BlockPos, y: BlockPos, z: BlockPos) -> Self {
+        BlockCoord { x, y, z }
+    }
+
+    /// Block `offset` blocks away from this one.
+    pub fn offset(&self, x: BlockPos, y: BlockPos, z: BlockPos) -> Self {
+        BlockCoord::new(self.x + x, self.y + y, self.z + z)
+    }
+
+    /// Chunk containing the block.
+    pub fn chunk(&self) -> ChunkCoord {
+        let size = CHUNK_SIZE as BlockPos;
+        ChunkCoord::new(
+            self.x.div_euclid(size) as ChunkPos,
+            self.y.div_euclid(size) as ChunkPos,
+            self.z.div_euclid(size) as ChunkPos,
+        )
+    }
+
+    /// Position of the block within its chunk.
+    pub fn local(&self) -> [usize; 3] {
+        let size = CHUNK_SIZE as BlockPos;
+        self.to_array().map(|it| it.rem_euclid(size) as usize)
+    }
+}
+
 impl Point<BlockPos, 3> for BlockCoord {
     fn to_array(&self) -> [BlockPos; 3] {
         [self.x, self.y, self.z]
     }
+
+    fn from_array([x, y, z]: [BlockPos; 3]) -> Self {
+        BlockCoord { x, y, z }
+    }
 }
 
-#[derive(Debug, Clone, Copy, Hash, Pod, Zeroable)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Pod, Zeroable)]
 #[repr(C)]
 pub struct ChunkCoord {
     x: ChunkPos,
@@ -35,25 +77,170 @@ pub struct ChunkCoord {
     z: ChunkPos,
 }
 
+impl ChunkCoord {
+    pub fn new(x: ChunkPos, y: ChunkPos, z: ChunkPos) -> Self {
+        ChunkCoord { x, y, z }
+    }
+
+    /// Coordinate of the chunk block with the lowest coordinates.
+    pub fn origin(&self) -> BlockCoord {
+        let size = CHUNK_SIZE as BlockPos;
+        BlockCoord::new(
+            self.x as BlockPos * size,
+            self.y as BlockPos * size,
+            self.z as BlockPos * size,
+        )
+    }
+
+    /// Chunk `offset` chunks away from this one.
+    pub fn offset(&self, x: ChunkPos, y: ChunkPos, z: ChunkPos) -> Self {
+        ChunkCoord::new(self.x + x, self.y + y, self.z + z)
+    }
+
+    /// Coordinate of a block within the chunk.
+    pub fn block(&self, [x, y, z]: [usize; 3]) -> BlockCoord {
+        self.origin().offset(x as BlockPos, y as BlockPos, z as BlockPos)
+    }
+}
+
 impl Point<ChunkPos, 3> for ChunkCoord {
     fn to_array(&self) -> [ChunkPos; 3] {
         [self.x, self.y, self.z]
     }
+
+    fn from_array([x, y, z]: [ChunkPos; 3]) -> Self {
+        ChunkCoord { x, y, z }
+    }
 }
 
+pub type ChunkMap = HashMap<ChunkCoord, ArrayChunk>;
+
+#[derive(Resource)]
 pub struct Terrain {
     requested_chunks: Vec<ChunkCoord>,
-    loaded_chunks: ArrayChunk,
+    loaded_chunks: ChunkMap,
+    light: LightMap,
 }
 
 impl Terrain {
     pub fn new() -> Self {
         Terrain {
             requested_chunks: vec![],
-            loaded_chunks: ArrayChunk::default(),
+            loaded_chunks: ChunkMap::new(),
+            light: LightMap::new(),
         }
     }
 
+    pub fn chunk(&self, coord: ChunkCoord) -> Option<&ArrayChunk> {
+        self.loaded_chunks.get(&coord)
+    }
+
+    pub fn light(&self) -> &LightMap {
+        &self.light
+    }
+
+    pub fn block(&self, pos: BlockCoord) -> Option<BlockID> {
+        let [x, y, z] = pos.local();
+        self.chunk(pos.chunk()).map(|chunk| chunk.get(x, y, z))
+    }
+
+    /// Loads a chunk and lights it, returns chunks whose light changed.
+    pub fn insert_chunk(
+        &mut self,
+        coord: ChunkCoord,
+        chunk: ArrayChunk,
+        blocks: &BlockRegistry,
+    ) -> HashSet<ChunkCoord> {
+        self.loaded_chunks.insert(coord, chunk);
+        self.light.insert_chunk(coord, &self.loaded_chunks, blocks)
+    }
+
+    /// Unloads a chunk, returns it along with chunks whose light changed.
+    pub fn remove_chunk(
+        &mut self,
+        coord: ChunkCoord,
+        blocks: &BlockRegistry,
+    ) -> Option<(ArrayChunk, HashSet<ChunkCoord>)> {
+        let chunk = self.loaded_chunks.remove(&coord)?;
+        let changed = self.light.remove_chunk(coord, &self.loaded_chunks, blocks);
+        Some((chunk, changed))
+    }
+
+    /// Changes a block in a loaded chunk and updates light around it.
+    ///
+    /// Returns chunks whose blocks or light changed, empty if the chunk isn't
+    /// loaded.
+    pub fn set_block(
+        &mut self,
+        pos: BlockCoord,
+        block: BlockID,
+        blocks: &BlockRegistry,
+    ) -> HashSet<ChunkCoord> {
+        let [x, y, z] = pos.local();
+        match self.loaded_chunks.get_mut(&pos.chunk()) {
+            Some(chunk) => chunk.set(x, y, z, block),
+            None => return HashSet::new(),
+        }
+
+        let mut changed = self.light.update_block(pos, &self.loaded_chunks, blocks);
+        changed.insert(pos.chunk());
+        changed
+    }
+
+    /// Coordinates of every loaded chunk.
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = ChunkCoord> + '_ {
+        self.loaded_chunks.keys().copied()
+    }
+
+    /// Requests chunks that aren't loaded within `distance` chunks of
+    /// `center` horizontally and `height` chunks vertically, replacing earlier
+    /// requests. Closer chunks are streamed first.
+    pub fn request_around(&mut self, center: ChunkCoord, distance: ChunkPos, height: ChunkPos) {
+        let horizontal = -distance..=distance;
+        let mut requested: Vec<ChunkCoord> = horizontal
+            .clone()
+            .flat_map(|x| horizontal.clone().map(move |z| (x, z)))
+            .filter(|(x, z)| x * x + z * z <= distance * distance)
+            .flat_map(|(x, z)| (-height..=height).map(move |y| center.offset(x, y, z)))
+            .filter(|it| !self.loaded_chunks.contains_key(it))
+            .collect();
+        // streamed from the back
+        requested.sort_by_key(|it| {
+            let [x, y, z] = it.to_array();
+            let [cx, cy, cz] = center.to_array();
+            std::cmp::Reverse((x - cx).pow(2) + (y - cy).pow(2) + (z - cz).pow(2))
+        });
+        self.requested_chunks = requested;
+    }
+
+    /// Number of requested chunks that aren't loaded yet.
+    pub fn pending_chunks(&self) -> usize {
+        self.requested_chunks.len()
+    }
+
+    /// Loads up to `budget` requested chunks with blocks created by
+    /// `generate`, closest first.
+    ///
+    /// Returns chunks whose blocks or light changed.
+    pub fn stream(
+        &mut self,
+        budget: usize,
+        blocks: &BlockRegistry,
+        mut generate: impl FnMut(ChunkCoord) -> ArrayChunk,
+    ) -> HashSet<ChunkCoord> {
+        let mut changed = HashSet::new();
+        for _ in 0..budget {
+            let coord = match self.requested_chunks.pop() {
+                Some(it) => it,
+                None => break,
+            };
+            if !self.loaded_chunks.contains_key(&coord) {
+                changed.extend(self.insert_chunk(coord, generate(coord), blocks));
+            }
+        }
+        changed
+    }
+
     pub fn slice<'a>(&'a self, selection: AABB<BlockPos, BlockCoord>) -> TerrainSlice<'a> {
         TerrainSlice {
             of: self,
@@ -67,13 +254,184 @@ pub struct TerrainSlice<'a> {
     selection: AABB<BlockPos, BlockCoord>,
 }
 
+/// Stages the game world runs every tick, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, StageLabel)]
+pub enum WorldStage {
+    /// Turns player input into what entities should do.
+    Input,
+    /// Gameplay logic.
+    Simulation,
+    /// Moves entities and resolves their collisions.
+    Physics,
+    /// Loads requested chunks into the [`Terrain`].
+    ChunkStreaming,
+    /// Copies what the renderer needs out of the world.
+    RenderExtraction,
+}
+
+impl WorldStage {
+    pub const ALL: [WorldStage; 5] = [
+        WorldStage::Input,
+        WorldStage::Simulation,
+        WorldStage::Physics,
+        WorldStage::ChunkStreaming,
+        WorldStage::RenderExtraction,
+    ];
+}
+
+/// Tick the world is simulating.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Resource)]
+pub struct Tick {
+    /// Seconds the tick advances the world by.
+    pub dt: f32,
+    /// Ticks simulated before this one.
+    pub count: u64,
+}
+
+/// Chunks whose blocks or light changed, they're taken by the renderer to be
+/// meshed again.
+#[derive(Debug, Default, Resource)]
+pub struct ChangedChunks(pub HashSet<ChunkCoord>);
+
+/// Creates blocks of chunks streamed into the [`Terrain`].
+#[derive(Resource)]
+pub struct ChunkGenerator {
+    /// Chunks streamed in every tick, more make loading faster but ticks
+    /// longer.
+    pub budget: usize,
+    generate: Box<dyn Fn(ChunkCoord) -> ArrayChunk + Send + Sync>,
+}
+
+impl ChunkGenerator {
+    pub fn new(
+        budget: usize,
+        generate: impl Fn(ChunkCoord) -> ArrayChunk + Send + Sync + 'static,
+    ) -> Self {
+        ChunkGenerator {
+            budget,
+            generate: Box::new(generate),
+        }
+    }
+}
+
+fn stream_chunks(
+    mut terrain: ResMut<Terrain>,
+    generator: Option<Res<ChunkGenerator>>,
+    blocks: Res<BlockRegistry>,
+    mut changed: ResMut<ChangedChunks>,
+) {
+    let generator = match generator {
+        Some(it) if terrain.pending_chunks() > 0 => it,
+        _ => return,
+    };
+    let streamed = terrain.stream(generator.budget, &blocks, |coord| {
+        (generator.generate)(coord)
+    });
+    changed.0.extend(streamed);
+}
+
+/// Gameplay feature adding its components, resources and systems to a
+/// [`World`].
+pub trait Plugin {
+    fn build(&self, world: &mut World);
+}
+
+impl<F: Fn(&mut World)> Plugin for F {
+    fn build(&self, world: &mut World) {
+        self(world)
+    }
+}
+
+/// Entities and terrain of the game, simulated by running every
+/// [`WorldStage`] each tick.
+///
+/// The [`Terrain`] and [`BlockRegistry`] are resources systems can access.
 pub struct World {
     ecs: BevyECS,
-    terrain: Terrain,
+    schedule: Schedule,
 }
 
 impl World {
-    fn new() {
-        let ecs = BevyECS::default();
+    pub fn new(terrain: Terrain, blocks: BlockRegistry) -> Self {
+        let mut ecs = BevyECS::new();
+        ecs.insert_resource(terrain);
+        ecs.insert_resource(blocks);
+        ecs.init_resource::<Tick>();
+        ecs.init_resource::<ChangedChunks>();
+
+        let mut schedule = Schedule::default();
+        for stage in WorldStage::ALL {
+            schedule.add_stage(stage, SystemStage::parallel());
+        }
+        let mut world = World { ecs, schedule };
+        world.add_system(WorldStage::ChunkStreaming, stream_chunks);
+        world
+    }
+
+    pub fn add_plugin(&mut self, plugin: impl Plugin) -> &mut Self {
+        plugin.build(self);
+        self
+    }
+
+    pub fn add_system<Params>(
+        &mut self,
+        stage: WorldStage,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self {
+        self.schedule.add_system_to_stage(stage, system);
+        self
+    }
+
+    /// Adds `resource`, replacing one of the same type.
+    pub fn insert_resource(&mut self, resource: impl Resource) -> &mut Self {
+        self.ecs.insert_resource(resource);
+        self
+    }
+
+    /// Registers a component type ahead of entities using it.
+    pub fn register_component<T: Component>(&mut self) -> &mut Self {
+        self.ecs.init_component::<T>();
+        self
+    }
+
+    pub fn spawn(&mut self, bundle: impl Bundle) -> Entity {
+        self.ecs.spawn(bundle).id()
+    }
+
+    pub fn ecs(&self) -> &BevyECS {
+        &self.ecs
+    }
+
+    pub fn ecs_mut(&mut self) -> &mut BevyECS {
+        &mut self.ecs
+    }
+
+    pub fn terrain(&self) -> &Terrain {
+        self.ecs.resource()
+    }
+
+    pub fn terrain_mut(&mut self) -> Mut<'_, Terrain> {
+        self.ecs.resource_mut()
+    }
+
+    pub fn blocks(&self) -> &BlockRegistry {
+        self.ecs.resource()
+    }
+
+    pub fn tick(&self) -> Tick {
+        *self.ecs.resource()
+    }
+
+    /// Runs every stage once, advancing the world by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        self.ecs.resource_mut::<Tick>().dt = dt;
+        self.schedule.run(&mut self.ecs);
+        self.ecs.resource_mut::<Tick>().count += 1;
+        self.ecs.clear_trackers();
+    }
+
+    /// Chunks whose blocks or light changed since the last call.
+    pub fn take_changed_chunks(&mut self) -> HashSet<ChunkCoord> {
+        std::mem::take(&mut self.ecs.resource_mut::<ChangedChunks>().0)
     }
 }
@@ -1,11 +1,15 @@
 use thiserror::Error;
 
+use crate::render::shader::{preprocessor::SourceLocation, validation::Diagnostic};
+
 #[derive(Debug, Error)]
 pub enum FormatError {
     #[error("invalid resource kind: {0}")]
     InvalidResourceKind(u8),
     #[error("invalid texture format: {0}")]
     InvalidTextureFormat(u8),
+    #[error("text isn't valid UTF-8")]
+    InvalidEncoding,
 }
 
 #[derive(Debug, Error)]
@@ -23,3 +27,78 @@ pub enum ResourceError {
     #[error(transparent)]
     BincodeDecode(#[from] bincode::error::DecodeError),
 }
+
+#[derive(Debug, Error)]
+pub enum PreprocessError {
+    #[error("{0}: unknown directive '#{1}'")]
+    UnknownDirective(SourceLocation, String),
+    #[error("{0}: malformed directive: {1}")]
+    MalformedDirective(SourceLocation, String),
+    #[error("{0}: included file '{1}' not found")]
+    IncludeNotFound(SourceLocation, String),
+    #[error("{0}: '{1}' is already being included")]
+    IncludeCycle(SourceLocation, String),
+    #[error("{0}: #{1} without a matching #ifdef")]
+    UnmatchedDirective(SourceLocation, String),
+    #[error("{0}: #ifdef is never closed with #endif")]
+    UnterminatedConditional(SourceLocation),
+}
+
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error("unable to read shader '{0}': {1}")]
+    Io(std::path::PathBuf, std::io::Error),
+    #[error(transparent)]
+    Preprocess(#[from] PreprocessError),
+    #[error("unable to parse shader: {0}")]
+    Parse(Diagnostic),
+    #[error("invalid shader: {0}")]
+    Validation(Diagnostic),
+
+    #[error("shader has no {1:?} entry point called '{0}'")]
+    MissingEntryPoint(String, naga::ShaderStage),
+    #[error("no vertex buffer provides shader input @location({0})")]
+    MissingVertexAttribute(u32),
+    #[error("shader input @location({location}) is {expected} but vertex buffer provides {provided:?}")]
+    VertexFormatMismatch {
+        location: u32,
+        expected: String,
+        provided: wgpu::VertexFormat,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error("no suitable graphics adapter found")]
+    NoAdapter,
+    #[error(transparent)]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+    #[error(transparent)]
+    BufferMap(#[from] wgpu::BufferAsyncError),
+    #[error("buffer mapping was aborted")]
+    MapAborted,
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+    #[error("LUT image must be a strip of size² by size pixels, got {0}x{1}")]
+    InvalidLut(u32, u32),
+}
+
+#[derive(Debug, Error)]
+pub enum GraphError {
+    #[error("pass '{0}' uses unknown attachment '{1}'")]
+    UnknownAttachment(&'static str, &'static str),
+    #[error("pass '{0}' reads attachment '{1}' no pass writes")]
+    NeverWritten(&'static str, &'static str),
+    #[error("pass '{0}' depends on itself")]
+    Cycle(&'static str),
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid settings: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("unable to write settings: {0}")]
+    Serialize(#[from] toml::ser::Error),
+}
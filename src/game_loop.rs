@@ -0,0 +1,181 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+/// Source of time the [`GameLoop`] measures frames with.
+pub trait Clock {
+    /// Time since an arbitrary point before the first call.
+    fn now(&self) -> Duration;
+}
+
+/// Clock following real time.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock::default()
+    }
+}
+
+impl Clock for SystemClock {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    // std::time::Instant isn't available in browsers
+    #[cfg(target_arch = "wasm32")]
+    fn now(&self) -> Duration {
+        let millis = web_sys::window()
+            .and_then(|it| it.performance())
+            .map_or(0.0, |it| it.now());
+        Duration::from_secs_f64(millis / 1000.0)
+    }
+}
+
+/// Clock that only moves when it's told to, copies share the same time so
+/// loops can be driven from tests.
+#[derive(Debug, Default, Clone)]
+pub struct ManualClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopSettings {
+    /// Simulation ticks per second.
+    pub tick_rate: u32,
+    /// Most ticks simulated before a single frame. Time the simulation falls
+    /// behind by beyond it is dropped, so slow ticks can't make every
+    /// following frame simulate even more of them.
+    pub max_ticks_per_frame: u32,
+}
+
+impl Default for LoopSettings {
+    fn default() -> Self {
+        LoopSettings {
+            tick_rate: 60,
+            max_ticks_per_frame: 8,
+        }
+    }
+}
+
+/// Simulation ticks due before a frame is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTicks {
+    pub ticks: u32,
+    /// How far the frame is from the last tick towards the next one, from 0
+    /// to 1. Rendering interpolates between the last two ticks with it.
+    pub alpha: f32,
+    /// Time the simulation fell behind by that was skipped.
+    pub dropped: Duration,
+}
+
+/// Runs the simulation at a fixed rate independent of the frame rate.
+///
+/// Time between frames is accumulated and spent on whole ticks, what's left
+/// is carried over to the next frame.
+#[derive(Debug)]
+pub struct GameLoop<C: Clock = SystemClock> {
+    clock: C,
+    pub settings: LoopSettings,
+    last_frame: Option<Duration>,
+    accumulator: Duration,
+    ticks: u64,
+}
+
+impl<C: Clock> GameLoop<C> {
+    pub fn new(clock: C, settings: LoopSettings) -> Self {
+        GameLoop {
+            clock,
+            settings,
+            last_frame: None,
+            accumulator: Duration::ZERO,
+            ticks: 0,
+        }
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Simulated time every tick advances by.
+    pub fn tick_length(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.settings.tick_rate.max(1) as f64)
+    }
+
+    /// Ticks simulated since the loop started.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Measures time since the previous frame and returns the ticks due
+    /// before this one is drawn. Nothing is due on the first frame.
+    pub fn advance(&mut self) -> FrameTicks {
+        let now = self.clock.now();
+        let elapsed = self
+            .last_frame
+            .map_or(Duration::ZERO, |it| now.saturating_sub(it));
+        self.last_frame = Some(now);
+        self.accumulator += elapsed;
+
+        let tick = self.tick_length();
+        let mut ticks = 0;
+        while self.accumulator >= tick && ticks < self.settings.max_ticks_per_frame {
+            self.accumulator -= tick;
+            ticks += 1;
+        }
+        let mut dropped = Duration::ZERO;
+        if self.accumulator >= tick {
+            let remainder = self.accumulator.as_nanos() % tick.as_nanos();
+            let remainder = Duration::from_nanos(remainder as u64);
+            dropped = self.accumulator - remainder;
+            self.accumulator = remainder;
+            tracing::debug!("simulation is {:?} behind, skipping it", dropped);
+        }
+        self.ticks += ticks as u64;
+
+        FrameTicks {
+            ticks,
+            alpha: self.accumulator.as_secs_f32() / tick.as_secs_f32(),
+            dropped,
+        }
+    }
+
+    /// Calls `tick` with the tick length for every tick due before this
+    /// frame, returns the interpolation alpha of the frame.
+    pub fn run(&mut self, mut tick: impl FnMut(Duration)) -> f32 {
+        let frame = self.advance();
+        let length = self.tick_length();
+        for _ in 0..frame.ticks {
+            tick(length);
+        }
+        frame.alpha
+    }
+}
@@ -1,7 +1,10 @@
 use crate::{
     block::BlockID,
-    content::world::{BlockCoord, BlockPos, TerrainSlice},
-    math::AABB,
+    content::{
+        chunk::ArrayChunk,
+        world::{BlockCoord, BlockPos, ChunkCoord, TerrainSlice, CHUNK_SIZE},
+    },
+    math::{Point, AABB},
 };
 
 pub enum Change {
@@ -16,3 +19,20 @@ pub struct GenerationResult {
 pub trait Generator {}
 
 pub fn pass<'w>(world_slice: TerrainSlice<'w>, working_area: AABB<BlockPos, BlockCoord>) {}
+
+/// Chunk of a flat world, blocks below `height` are `block` and the rest is
+/// air.
+pub fn flat(coord: ChunkCoord, height: BlockPos, block: BlockID) -> ArrayChunk {
+    let mut chunk = ArrayChunk::default();
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            if coord.block([x, y, 0]).to_array()[1] >= height {
+                break;
+            }
+            for z in 0..CHUNK_SIZE {
+                chunk.set(x, y, z, block);
+            }
+        }
+    }
+    chunk
+}
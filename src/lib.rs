@@ -1,15 +1,22 @@
 pub mod block;
+pub mod client;
 pub mod content;
 pub mod error;
+pub mod game_loop;
 pub mod gen;
 pub mod math;
 pub mod render;
+pub mod state;
+pub mod ui;
 pub mod util;
 
 #[macro_use]
 extern crate macros;
 
-use render::RendererState;
+use client::Client;
+use game_loop::{GameLoop, LoopSettings, SystemClock};
+use render::{config::StateConfig, RendererState};
+use state::{menu::MainMenu, StateStack};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
@@ -35,7 +42,15 @@ pub async fn start() {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let mut state = RendererState::new(&window).await;
+    #[cfg(not(target_arch = "wasm32"))]
+    let config = StateConfig::load_or_default(render::config::CONFIG_PATH);
+    #[cfg(target_arch = "wasm32")]
+    let config = StateConfig::default();
+    let mut client = Client::new(RendererState::new(&window, config).await);
+    let mut states = StateStack::with_state(MainMenu::new(), &mut client);
+    let mut game_loop = GameLoop::new(SystemClock::new(), LoopSettings::default());
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut limiter = render::config::FrameLimiter::default();
 
     event_loop.run(move |event, _, control_flow| {
         match event {
@@ -43,30 +58,32 @@ pub async fn start() {
                 ref event,
                 window_id,
             } if window_id == window.id() => {
-                if !state.input(event) {
-                    // UPDATED!
+                client.input.handle_event(event);
+                if !states.input(&mut client, event) && !client.renderer.input(event) {
                     match event {
-                        WindowEvent::CloseRequested
-                        | WindowEvent::KeyboardInput {
-                            input:
-                                KeyboardInput {
-                                    state: ElementState::Pressed,
-                                    virtual_keycode: Some(VirtualKeyCode::Escape),
-                                    ..
-                                },
-                            ..
-                        } => *control_flow = ControlFlow::Exit,
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
                         WindowEvent::Resized(physical_size) => {
-                            state.resize(*physical_size);
+                            client.renderer.resize(*physical_size);
                         }
                         WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                            state.resize(**new_inner_size);
+                            client.renderer.resize(**new_inner_size);
                         }
                         _ => {}
                     }
                 }
             }
             Event::RedrawRequested(window_id) if window_id == window.id() => {
+                let alpha = game_loop.run(|dt| {
+                    states.update(&mut client, dt.as_secs_f32());
+                    // a click is handled by a single tick
+                    client.input.end_frame();
+                });
+                if states.is_empty() {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                states.render(&mut client, alpha);
+                let state = &mut client.renderer;
                 state.update();
                 match state.render() {
                     Ok(_) => {}
@@ -80,8 +97,21 @@ pub async fn start() {
             }
             Event::MainEventsCleared => {
                 // RedrawRequested will only trigger once, unless we manually
-                // request it.
-                window.request_redraw();
+                // request it. Browsers pace frames on their own.
+                #[cfg(not(target_arch = "wasm32"))]
+                let wait = limiter.wait(
+                    client.renderer.config().frame_cap,
+                    std::time::Instant::now(),
+                );
+                #[cfg(target_arch = "wasm32")]
+                let wait = None;
+                match wait {
+                    Some(deadline) => *control_flow = ControlFlow::WaitUntil(deadline),
+                    None => {
+                        *control_flow = ControlFlow::Poll;
+                        window.request_redraw();
+                    }
+                }
             }
             _ => {}
         }
@@ -1,33 +1,95 @@
 use std::{fmt::Debug, marker::PhantomData};
 
+use glam::{Mat4, Vec2, Vec3, Vec4};
 use num::Num;
 
 pub trait Point<U: Num, const Dim: usize> {
     fn to_array(&self) -> [U; Dim];
+    fn from_array(array: [U; Dim]) -> Self;
 }
 
-impl<U: Num + Clone + Debug, A: AsRef<[U; Dim]>, const Dim: usize> Point<U, Dim> for A {
+impl<U: Num + Clone + Debug, A: AsRef<[U; Dim]> + From<[U; Dim]>, const Dim: usize> Point<U, Dim>
+    for A
+{
     fn to_array(&self) -> [U; Dim] {
         self.as_ref().to_vec().try_into().unwrap()
     }
+
+    fn from_array(array: [U; Dim]) -> Self {
+        A::from(array)
+    }
+}
+
+fn min<U: PartialOrd>(a: U, b: U) -> U {
+    if b < a {
+        b
+    } else {
+        a
+    }
+}
+
+fn max<U: PartialOrd>(a: U, b: U) -> U {
+    if b > a {
+        b
+    } else {
+        a
+    }
 }
 
+/// Axis-aligned bounding box spanning from `start` to `end` (inclusive).
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AABB<U: Num, P: Point<U, 3>> {
     start: P,
     end: P,
     _unit: PhantomData<U>,
 }
 
-impl<U: Num, P: Point<U, 3>> AABB<U, P> {
-    fn new(start: P, end: P) -> Self {
+impl<U: Num + PartialOrd + Copy, P: Point<U, 3>> AABB<U, P> {
+    /// Creates a box between two opposite corners, given in any order.
+    pub fn new(a: P, b: P) -> Self {
+        let a = a.to_array();
+        let b = b.to_array();
+
         AABB {
-            start,
-            end,
-            _unit: PhantomData::default(),
+            start: P::from_array([min(a[0], b[0]), min(a[1], b[1]), min(a[2], b[2])]),
+            end: P::from_array([max(a[0], b[0]), max(a[1], b[1]), max(a[2], b[2])]),
+            _unit: PhantomData,
         }
     }
 
-    fn dimensions(&self) -> [U; 3] {
+    /// Smallest box containing all `points`, `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = P>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?.to_array();
+
+        let (start, end) = points.fold((first, first), |(start, end), point| {
+            let p = point.to_array();
+            (
+                [
+                    min(start[0], p[0]),
+                    min(start[1], p[1]),
+                    min(start[2], p[2]),
+                ],
+                [max(end[0], p[0]), max(end[1], p[1]), max(end[2], p[2])],
+            )
+        });
+
+        Some(AABB {
+            start: P::from_array(start),
+            end: P::from_array(end),
+            _unit: PhantomData,
+        })
+    }
+
+    pub fn start(&self) -> &P {
+        &self.start
+    }
+
+    pub fn end(&self) -> &P {
+        &self.end
+    }
+
+    pub fn dimensions(&self) -> [U; 3] {
         let [start_x, start_y, start_z] = self.start.to_array();
         let [end_x, end_y, end_z] = self.end.to_array();
         let x = end_x - start_x;
@@ -35,4 +97,315 @@ impl<U: Num, P: Point<U, 3>> AABB<U, P> {
         let z = end_z - start_z;
         [x, y, z]
     }
+
+    pub fn center(&self) -> P {
+        let two = U::one() + U::one();
+        let start = self.start.to_array();
+        let end = self.end.to_array();
+        P::from_array([
+            (start[0] + end[0]) / two,
+            (start[1] + end[1]) / two,
+            (start[2] + end[2]) / two,
+        ])
+    }
+
+    /// All 8 corners of the box.
+    pub fn corners(&self) -> [P; 8] {
+        let s = self.start.to_array();
+        let e = self.end.to_array();
+        [
+            P::from_array([s[0], s[1], s[2]]),
+            P::from_array([e[0], s[1], s[2]]),
+            P::from_array([s[0], e[1], s[2]]),
+            P::from_array([e[0], e[1], s[2]]),
+            P::from_array([s[0], s[1], e[2]]),
+            P::from_array([e[0], s[1], e[2]]),
+            P::from_array([s[0], e[1], e[2]]),
+            P::from_array([e[0], e[1], e[2]]),
+        ]
+    }
+
+    pub fn contains_point(&self, point: &P) -> bool {
+        let start = self.start.to_array();
+        let end = self.end.to_array();
+        let p = point.to_array();
+        (0..3).all(|i| start[i] <= p[i] && p[i] <= end[i])
+    }
+
+    /// Whether `other` is completely inside of this box.
+    pub fn contains(&self, other: &Self) -> bool {
+        self.contains_point(&other.start) && self.contains_point(&other.end)
+    }
+
+    /// Whether boxes overlap, boxes that only touch don't intersect.
+    pub fn intersects(&self, other: &Self) -> bool {
+        let (start, end) = (self.start.to_array(), self.end.to_array());
+        let (other_start, other_end) = (other.start.to_array(), other.end.to_array());
+        (0..3).all(|i| start[i] < other_end[i] && other_start[i] < end[i])
+    }
+
+    /// Volume shared by both boxes.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        if !self.intersects(other) {
+            return None;
+        }
+
+        let (start, end) = (self.start.to_array(), self.end.to_array());
+        let (other_start, other_end) = (other.start.to_array(), other.end.to_array());
+        Some(AABB {
+            start: P::from_array([
+                max(start[0], other_start[0]),
+                max(start[1], other_start[1]),
+                max(start[2], other_start[2]),
+            ]),
+            end: P::from_array([
+                min(end[0], other_end[0]),
+                min(end[1], other_end[1]),
+                min(end[2], other_end[2]),
+            ]),
+            _unit: PhantomData,
+        })
+    }
+
+    /// Smallest box containing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        let (start, end) = (self.start.to_array(), self.end.to_array());
+        let (other_start, other_end) = (other.start.to_array(), other.end.to_array());
+        AABB {
+            start: P::from_array([
+                min(start[0], other_start[0]),
+                min(start[1], other_start[1]),
+                min(start[2], other_start[2]),
+            ]),
+            end: P::from_array([
+                max(end[0], other_end[0]),
+                max(end[1], other_end[1]),
+                max(end[2], other_end[2]),
+            ]),
+            _unit: PhantomData,
+        }
+    }
+
+    /// Grows the box by `amount` on every side.
+    pub fn expand(&self, amount: U) -> Self {
+        let start = self.start.to_array();
+        let end = self.end.to_array();
+        AABB::new(
+            P::from_array([start[0] - amount, start[1] - amount, start[2] - amount]),
+            P::from_array([end[0] + amount, end[1] + amount, end[2] + amount]),
+        )
+    }
+
+    /// Smallest box containing this box and `point`.
+    pub fn include(&self, point: &P) -> Self {
+        let (start, end) = (self.start.to_array(), self.end.to_array());
+        let p = point.to_array();
+        AABB {
+            start: P::from_array([
+                min(start[0], p[0]),
+                min(start[1], p[1]),
+                min(start[2], p[2]),
+            ]),
+            end: P::from_array([max(end[0], p[0]), max(end[1], p[1]), max(end[2], p[2])]),
+            _unit: PhantomData,
+        }
+    }
+}
+
+/// Where a volume is relative to a plane or frustum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intersection {
+    Outside,
+    Intersecting,
+    Inside,
+}
+
+/// Plane of points `p` where `normal.dot(p) + distance == 0`, `normal` points
+/// towards the inside.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+impl Plane {
+    pub fn new(normal: Vec3, distance: f32) -> Self {
+        let length = normal.length();
+        Plane {
+            normal: normal / length,
+            distance: distance / length,
+        }
+    }
+
+    /// Plane with `xyz` normal and `w` distance.
+    pub fn from_vec4(plane: Vec4) -> Self {
+        Plane::new(plane.truncate(), plane.w)
+    }
+
+    /// Distance of `point` from the plane, negative if it's outside.
+    pub fn signed_distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) + self.distance
+    }
+}
+
+/// Volume visible through a camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far planes.
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extracts planes from a view-projection matrix with wgpu clip space
+    /// depth (`0..=1`).
+    pub fn from_view_projection(matrix: Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| matrix.row(i));
+        Frustum {
+            planes: [w + x, w - x, w + y, w - y, z, w - z].map(Plane::from_vec4),
+        }
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+}
+
+impl<P: Point<f32, 3>> AABB<f32, P> {
+    fn vec_start(&self) -> Vec3 {
+        Vec3::from_array(self.start.to_array())
+    }
+
+    fn vec_end(&self) -> Vec3 {
+        Vec3::from_array(self.end.to_array())
+    }
+
+    pub fn test_plane(&self, plane: &Plane) -> Intersection {
+        let (start, end) = (self.vec_start(), self.vec_end());
+
+        // corners furthest along and against the plane normal
+        let positive = Vec3::select(plane.normal.cmpge(Vec3::ZERO), end, start);
+        let negative = Vec3::select(plane.normal.cmpge(Vec3::ZERO), start, end);
+
+        if plane.signed_distance(positive) < 0.0 {
+            Intersection::Outside
+        } else if plane.signed_distance(negative) < 0.0 {
+            Intersection::Intersecting
+        } else {
+            Intersection::Inside
+        }
+    }
+
+    /// Conservative frustum test, boxes near frustum corners may be reported
+    /// as intersecting even if they're outside.
+    pub fn test_frustum(&self, frustum: &Frustum) -> Intersection {
+        let mut result = Intersection::Inside;
+        for plane in &frustum.planes {
+            match self.test_plane(plane) {
+                Intersection::Outside => return Intersection::Outside,
+                Intersection::Intersecting => result = Intersection::Intersecting,
+                Intersection::Inside => {}
+            }
+        }
+        result
+    }
+
+    pub fn intersects_frustum(&self, frustum: &Frustum) -> bool {
+        self.test_frustum(frustum) != Intersection::Outside
+    }
+
+    /// Bounds of the box after it's transformed by `matrix`.
+    pub fn transformed(&self, matrix: Mat4) -> Self {
+        AABB::from_points(self.corners().iter().map(|it| {
+            P::from_array(
+                matrix
+                    .transform_point3(Vec3::from_array(it.to_array()))
+                    .to_array(),
+            )
+        }))
+        .unwrap()
+    }
+}
+
+/// Screen space rectangle, `Y` points down.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Rect {
+    /// Top left corner.
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Rect {
+            position: Vec2::new(x, y),
+            size: Vec2::new(width, height),
+        }
+    }
+
+    pub fn from_min_max(min: Vec2, max: Vec2) -> Self {
+        Rect {
+            position: min,
+            size: max - min,
+        }
+    }
+
+    pub fn min(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn max(&self) -> Vec2 {
+        self.position + self.size
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.position + self.size * 0.5
+    }
+
+    /// Whether `point` is inside, points on the right and bottom edges
+    /// aren't, so neighbouring rectangles never both contain a point.
+    pub fn contains(&self, point: Vec2) -> bool {
+        let max = self.max();
+        point.x >= self.position.x
+            && point.y >= self.position.y
+            && point.x < max.x
+            && point.y < max.y
+    }
+
+    /// Rectangle with its corners moved to the nearest whole pixels.
+    pub fn round(&self) -> Self {
+        Rect::from_min_max(self.min().round(), self.max().round())
+    }
+
+    /// Rectangle moved `amount` inwards from every side.
+    pub fn shrink(&self, amount: f32) -> Self {
+        Rect {
+            position: self.position + amount,
+            size: (self.size - amount * 2.0).max(Vec2::ZERO),
+        }
+    }
+
+    /// Rectangle of `size` centered in this one.
+    pub fn centered(&self, size: Vec2) -> Self {
+        Rect {
+            position: self.center() - size * 0.5,
+            size,
+        }
+    }
+
+    /// Splits off `width` from the left side, returns the left and the right
+    /// part.
+    pub fn split_left(&self, width: f32) -> (Self, Self) {
+        let width = width.clamp(0.0, self.size.x);
+        (
+            Rect::new(self.position.x, self.position.y, width, self.size.y),
+            Rect::new(
+                self.position.x + width,
+                self.position.y,
+                self.size.x - width,
+                self.size.y,
+            ),
+        )
+    }
 }
@@ -0,0 +1,83 @@
+// Bloom built from a chain of progressively smaller copies of bright parts of
+// the image, which are blurred while being added back up the chain.
+
+#include "fullscreen.wgsl"
+
+struct BloomUniform {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+};
+
+@group(0) @binding(2)
+var<uniform> bloom: BloomUniform;
+// Largest level of the chain, only bound for compositing.
+@group(0) @binding(3)
+var bloom_texture: texture_2d<f32>;
+
+// 13 tap filter from "Next Generation Post Processing in Call of Duty:
+// Advanced Warfare", it avoids flickering of small bright spots.
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    let a = sample_source(uv + texel * vec2<f32>(-2.0, -2.0)).rgb;
+    let b = sample_source(uv + texel * vec2<f32>(0.0, -2.0)).rgb;
+    let c = sample_source(uv + texel * vec2<f32>(2.0, -2.0)).rgb;
+    let d = sample_source(uv + texel * vec2<f32>(-2.0, 0.0)).rgb;
+    let e = sample_source(uv).rgb;
+    let f = sample_source(uv + texel * vec2<f32>(2.0, 0.0)).rgb;
+    let g = sample_source(uv + texel * vec2<f32>(-2.0, 2.0)).rgb;
+    let h = sample_source(uv + texel * vec2<f32>(0.0, 2.0)).rgb;
+    let i = sample_source(uv + texel * vec2<f32>(2.0, 2.0)).rgb;
+    let j = sample_source(uv + texel * vec2<f32>(-1.0, -1.0)).rgb;
+    let k = sample_source(uv + texel * vec2<f32>(1.0, -1.0)).rgb;
+    let l = sample_source(uv + texel * vec2<f32>(-1.0, 1.0)).rgb;
+    let m = sample_source(uv + texel * vec2<f32>(1.0, 1.0)).rgb;
+
+    return e * 0.125
+        + (a + c + g + i) * 0.03125
+        + (b + d + f + h) * 0.0625
+        + (j + k + l + m) * 0.125;
+}
+
+// Keeps colors above the threshold, with a quadratic falloff `knee` wide
+// below it.
+fn threshold(color: vec3<f32>) -> vec3<f32> {
+    let brightness = max(color.r, max(color.g, color.b));
+    var soft = clamp(brightness - bloom.threshold + bloom.knee, 0.0, 2.0 * bloom.knee);
+    soft = soft * soft / (4.0 * bloom.knee + 0.00001);
+    let contribution = max(soft, brightness - bloom.threshold) / max(brightness, 0.00001);
+    return color * contribution;
+}
+
+@fragment
+fn fs_prefilter(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(threshold(downsample(in.uv)), 1.0);
+}
+
+@fragment
+fn fs_downsample(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.uv), 1.0);
+}
+
+// 3x3 tent filter, the result is added to the larger level.
+@fragment
+fn fs_upsample(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    var sum = sample_source(in.uv).rgb * 4.0;
+    sum = sum + (sample_source(in.uv + texel * vec2<f32>(0.0, -1.0)).rgb
+        + sample_source(in.uv + texel * vec2<f32>(-1.0, 0.0)).rgb
+        + sample_source(in.uv + texel * vec2<f32>(1.0, 0.0)).rgb
+        + sample_source(in.uv + texel * vec2<f32>(0.0, 1.0)).rgb) * 2.0;
+    sum = sum + sample_source(in.uv + texel * vec2<f32>(-1.0, -1.0)).rgb
+        + sample_source(in.uv + texel * vec2<f32>(1.0, -1.0)).rgb
+        + sample_source(in.uv + texel * vec2<f32>(-1.0, 1.0)).rgb
+        + sample_source(in.uv + texel * vec2<f32>(1.0, 1.0)).rgb;
+    return vec4<f32>(sum / 16.0, 1.0);
+}
+
+@fragment
+fn fs_composite(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = sample_source(in.uv).rgb;
+    let glow = textureSampleLevel(bloom_texture, source_sampler, in.uv, 0.0).rgb;
+    return vec4<f32>(color + glow * bloom.intensity, 1.0);
+}
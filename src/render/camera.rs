@@ -0,0 +1,82 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use wgpu::*;
+
+use crate::math::Frustum;
+
+/// Point of view frames are rendered from.
+///
+/// Defaults to identity matrices so geometry is given in clip space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub view: Mat4,
+    pub projection: Mat4,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            view: Mat4::IDENTITY,
+            projection: Mat4::IDENTITY,
+        }
+    }
+}
+
+impl Camera {
+    /// Perspective camera at `eye` looking at `target` with `Y` up.
+    pub fn perspective(
+        eye: Vec3,
+        target: Vec3,
+        fov_y: f32,
+        aspect: f32,
+        near: f32,
+        far: f32,
+    ) -> Self {
+        Camera {
+            view: Mat4::look_at_rh(eye, target, Vec3::Y),
+            projection: Mat4::perspective_rh(fov_y, aspect, near, far),
+        }
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection * self.view
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.view.inverse().w_axis.truncate()
+    }
+
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_projection(self.view_projection())
+    }
+}
+
+/// Camera data available to shaders at `@group(1) @binding(0)`.
+#[derive(Debug, Copy, Clone, Pod, Zeroable, ShaderUniform)]
+#[repr(C)]
+pub struct CameraUniform {
+    pub view_projection: Mat4,
+}
+
+impl From<&Camera> for CameraUniform {
+    fn from(camera: &Camera) -> Self {
+        CameraUniform {
+            view_projection: camera.view_projection(),
+        }
+    }
+}
+
+pub const CAMERA_BIND_GROUP_LAYOUT: BindGroupLayoutDescriptor<'static> =
+    BindGroupLayoutDescriptor {
+        label: Some("camera_bind_group_layout"),
+        entries: &[BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::VERTEX_FRAGMENT,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    };
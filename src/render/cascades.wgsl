@@ -0,0 +1,47 @@
+// Cascaded shadow maps of the sun, bound by the renderer at group 2.
+
+struct ShadowUniform {
+    view_projections: array<mat4x4<f32>, 4>,
+    splits: vec4<f32>,
+    sun: vec4<f32>,
+    texel_sizes: vec4<f32>,
+    cascade_count: u32,
+    normal_bias: f32,
+};
+
+@group(2) @binding(0)
+var<uniform> shadow: ShadowUniform;
+@group(2) @binding(1)
+var shadow_map: texture_depth_2d_array;
+@group(2) @binding(2)
+var shadow_sampler: sampler_comparison;
+
+// Fraction of sunlight reaching a surface, filtered over 3x3 shadow map
+// texels. Surfaces beyond the last cascade are always lit.
+fn sun_visibility(world_position: vec3<f32>, normal: vec3<f32>, view_depth: f32) -> f32 {
+    var cascade = 0u;
+    loop {
+        if (cascade >= shadow.cascade_count) {
+            return 1.0;
+        }
+        if (view_depth < shadow.splits[cascade]) {
+            break;
+        }
+        cascade = cascade + 1u;
+    }
+
+    let offset = normal * shadow.texel_sizes[cascade] * shadow.normal_bias;
+    let clip = shadow.view_projections[cascade] * vec4<f32>(world_position + offset, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_map));
+
+    var lit = 0.0;
+    for (var x = -1; x <= 1; x = x + 1) {
+        for (var y = -1; y <= 1; y = y + 1) {
+            let sample_uv = uv + vec2<f32>(f32(x), f32(y)) * texel;
+            lit = lit + textureSampleCompareLevel(shadow_map, shadow_sampler, sample_uv, i32(cascade), ndc.z);
+        }
+    }
+    return lit / 9.0;
+}
@@ -0,0 +1,860 @@
+use std::collections::HashMap;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use wgpu::*;
+
+use crate::{
+    block::{BlockID, BlockRegistry, RenderLayer, AIR},
+    content::{
+        chunk::ArrayChunk,
+        light::{Light, MAX_LIGHT},
+        world::{BlockPos, ChunkCoord, Terrain, CHUNK_DISTANCE, CHUNK_SIZE},
+    },
+    math::{Point, AABB},
+};
+
+use super::{
+    debug::DebugView,
+    mesh::{Mesh, MeshId, MeshManager, PipelineId},
+    pipeline::{FragmentInterface, Pipeline, VertexInterface},
+    shader::{self, preprocessor::VirtualFiles, WgslSource},
+    shadow::CASCADES_WGSL,
+    sky::SKY_WGSL,
+    vertex::{IndexList, VertexBuffer, VertexData},
+    Renderer,
+};
+
+pub static CHUNK_SHADER: WgslSource<'static> = WgslSource::new_static(include_str!("chunk.wgsl"));
+
+#[derive(Debug, Copy, Clone, Pod, Zeroable, VertexData)]
+#[repr(C)]
+pub struct ChunkVertex {
+    /// Position within the chunk in blocks.
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub color: [f32; 4],
+    /// Sky and block light in front of the face, from 0 to 1.
+    pub light: [f32; 2],
+    /// Ambient occlusion of the corner by blocks around it, from 0 for fully
+    /// occluded to 1 for open corners.
+    pub occlusion: [f32; 1],
+    pub block: [u32; 1],
+}
+
+/// Side of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Face {
+    NegX,
+    PosX,
+    NegY,
+    PosY,
+    NegZ,
+    PosZ,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::NegX,
+        Face::PosX,
+        Face::NegY,
+        Face::PosY,
+        Face::NegZ,
+        Face::PosZ,
+    ];
+
+    pub fn axis(self) -> usize {
+        self as usize / 2
+    }
+
+    pub fn is_positive(self) -> bool {
+        self as usize % 2 == 1
+    }
+
+    pub fn normal(self) -> [i32; 3] {
+        let mut normal = [0; 3];
+        normal[self.axis()] = if self.is_positive() { 1 } else { -1 };
+        normal
+    }
+}
+
+/// Level of detail chunk meshes are built at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Lod {
+    Full,
+    Half,
+    Quarter,
+    Eighth,
+}
+
+impl Lod {
+    pub const ALL: [Lod; 4] = [Lod::Full, Lod::Half, Lod::Quarter, Lod::Eighth];
+
+    /// Number of blocks along each axis merged into a single cell.
+    pub fn scale(self) -> usize {
+        1 << self as usize
+    }
+
+    /// LOD of a chunk whose center is `distance` chunks away from the camera.
+    pub fn select(distance: f32, settings: &LodSettings) -> Lod {
+        settings
+            .distances
+            .iter()
+            .zip(&Lod::ALL[1..])
+            .filter(|(start, _)| distance >= **start)
+            .map(|(_, lod)| *lod)
+            .next_back()
+            .unwrap_or(Lod::Full)
+    }
+
+    /// Like [`Lod::select`], but a chunk only switches to a finer LOD once
+    /// it's [`LodSettings::hysteresis`] closer than the LOD boundary, so chunks
+    /// on a boundary aren't rebuilt every frame.
+    pub fn update(current: Option<Lod>, distance: f32, settings: &LodSettings) -> Lod {
+        let target = Lod::select(distance, settings);
+        match current {
+            Some(current)
+                if target < current
+                    && Lod::select(distance + settings.hysteresis, settings) >= current =>
+            {
+                current
+            }
+            _ => target,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodSettings {
+    /// Chunk distances at which [`Lod::Half`], [`Lod::Quarter`] and
+    /// [`Lod::Eighth`] start being used.
+    pub distances: [f32; 3],
+    pub hysteresis: f32,
+    /// Chunks further than this many chunks aren't meshed.
+    pub view_distance: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        LodSettings {
+            distances: [3.0, 5.0, 8.0],
+            hysteresis: 0.5,
+            view_distance: CHUNK_DISTANCE as f32,
+        }
+    }
+}
+
+/// Chunk blocks downsampled for a LOD.
+#[derive(Debug, Clone)]
+pub struct LodChunk {
+    lod: Lod,
+    size: usize,
+    blocks: Vec<BlockID>,
+}
+
+impl LodChunk {
+    /// Every cell gets the most common block of the blocks it covers, or air
+    /// if less than half of them are solid.
+    pub fn new(chunk: &ArrayChunk, lod: Lod) -> Self {
+        let scale = lod.scale();
+        let size = CHUNK_SIZE / scale;
+        let mut blocks = Vec::with_capacity(size * size * size);
+
+        let mut counts: Vec<(BlockID, usize)> = Vec::with_capacity(8);
+        for x in 0..size {
+            for y in 0..size {
+                for z in 0..size {
+                    counts.clear();
+                    let mut solid = 0;
+                    for dx in 0..scale {
+                        for dy in 0..scale {
+                            for dz in 0..scale {
+                                let block =
+                                    chunk.get(x * scale + dx, y * scale + dy, z * scale + dz);
+                                if block == AIR {
+                                    continue;
+                                }
+                                solid += 1;
+                                match counts.iter_mut().find(|(id, _)| *id == block) {
+                                    Some((_, count)) => *count += 1,
+                                    None => counts.push((block, 1)),
+                                }
+                            }
+                        }
+                    }
+
+                    let block = if solid * 2 >= scale * scale * scale {
+                        counts
+                            .iter()
+                            .max_by_key(|(id, count)| (*count, std::cmp::Reverse(*id)))
+                            .map(|(id, _)| *id)
+                            .unwrap_or(AIR)
+                    } else {
+                        AIR
+                    };
+                    blocks.push(block);
+                }
+            }
+        }
+
+        LodChunk { lod, size, blocks }
+    }
+
+    pub fn lod(&self) -> Lod {
+        self.lod
+    }
+
+    /// Number of cells along each axis.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> BlockID {
+        self.blocks[(x * self.size + y) * self.size + z]
+    }
+}
+
+/// Chunk geometry split by [`RenderLayer`], every layer is drawn with its own
+/// pipeline.
+#[derive(Debug, Clone)]
+pub struct ChunkMesh {
+    pub opaque: Mesh<ChunkVertex>,
+    pub cutout: Mesh<ChunkVertex>,
+    pub translucent: Mesh<ChunkVertex>,
+}
+
+impl Default for ChunkMesh {
+    fn default() -> Self {
+        ChunkMesh {
+            opaque: Mesh::new(VertexBuffer::new(), IndexList::new()),
+            cutout: Mesh::new(VertexBuffer::new(), IndexList::new()),
+            translucent: Mesh::new(VertexBuffer::new(), IndexList::new()),
+        }
+    }
+}
+
+impl ChunkMesh {
+    pub fn layer(&self, layer: RenderLayer) -> &Mesh<ChunkVertex> {
+        match layer {
+            RenderLayer::Opaque => &self.opaque,
+            RenderLayer::Cutout => &self.cutout,
+            RenderLayer::Translucent => &self.translucent,
+        }
+    }
+
+    pub fn layer_mut(&mut self, layer: RenderLayer) -> &mut Mesh<ChunkVertex> {
+        match layer {
+            RenderLayer::Opaque => &mut self.opaque,
+            RenderLayer::Cutout => &mut self.cutout,
+            RenderLayer::Translucent => &mut self.translucent,
+        }
+    }
+}
+
+/// Whether a face of `block` is hidden by `neighbor` in front of it.
+///
+/// Only opaque blocks hide faces of other blocks, translucent blocks also
+/// hide faces between blocks of the same kind, like water next to water.
+fn hides(blocks: &BlockRegistry, block: BlockID, neighbor: BlockID) -> bool {
+    match blocks.layer(neighbor) {
+        _ if neighbor == AIR => false,
+        RenderLayer::Opaque => true,
+        RenderLayer::Translucent => neighbor == block,
+        RenderLayer::Cutout => false,
+    }
+}
+
+/// Block of a cell relative to `chunk`, cells across a face of the chunk are
+/// looked up in its neighbor. `None` if the cell isn't in any of them.
+fn cell_at(
+    chunk: &LodChunk,
+    neighbors: &[Option<&LodChunk>; 6],
+    cell: [i32; 3],
+) -> Option<BlockID> {
+    let size = chunk.size as i32;
+    let mut outside = (0..3).filter(|it| !(0..size).contains(&cell[*it]));
+    let (x, y, z) = match (outside.next(), outside.next()) {
+        (None, _) => (cell[0] as usize, cell[1] as usize, cell[2] as usize),
+        (Some(axis), None) => {
+            let face = Face::ALL[axis * 2 + (cell[axis] >= size) as usize];
+            let [x, y, z] = cell.map(|it| it.rem_euclid(size) as usize);
+            return match neighbors[face as usize] {
+                Some(neighbor) if neighbor.lod == chunk.lod => Some(neighbor.get(x, y, z)),
+                _ => None,
+            };
+        }
+        // cells across edges and corners of the chunk
+        _ => return None,
+    };
+    Some(chunk.get(x, y, z))
+}
+
+/// Ambient occlusion of face corners in [`push_face`] order, from the opaque
+/// blocks next to and diagonal to every corner in front of the face.
+fn corner_occlusion(
+    chunk: &LodChunk,
+    neighbors: &[Option<&LodChunk>; 6],
+    blocks: &BlockRegistry,
+    cell: [usize; 3],
+    face: Face,
+) -> [f32; 4] {
+    let (u, v) = face_axes(face);
+    let normal = face.normal();
+    let front = [0, 1, 2].map(|i| cell[i] as i32 + normal[i]);
+    let solid = |du: i32, dv: i32| {
+        let mut cell = front;
+        cell[u] += du;
+        cell[v] += dv;
+        matches!(
+            cell_at(chunk, neighbors, cell),
+            Some(block) if block != AIR && blocks.layer(block) == RenderLayer::Opaque
+        )
+    };
+
+    [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(du, dv)| {
+        let (du, dv) = (du * 2 - 1, dv * 2 - 1);
+        let (side_u, side_v) = (solid(du, 0), solid(0, dv));
+        // both sides hide the diagonal block too
+        let open = if side_u && side_v {
+            0
+        } else {
+            3 - side_u as u32 - side_v as u32 - solid(du, dv) as u32
+        };
+        open as f32 / 3.0
+    })
+}
+
+/// Axes along the face, `u x v` points along its normal so corners wind
+/// counter-clockwise when seen from outside.
+fn face_axes(face: Face) -> (usize, usize) {
+    let axis = face.axis();
+    if face.is_positive() {
+        ((axis + 1) % 3, (axis + 2) % 3)
+    } else {
+        ((axis + 2) % 3, (axis + 1) % 3)
+    }
+}
+
+/// Color of blocks without a definition, until blocks get textures.
+fn placeholder_color(block: BlockID) -> [f32; 4] {
+    let hash = block.wrapping_mul(2654435761);
+    let channel = |shift: u32| ((hash >> shift) & 255) as f32 / 255.0 * 0.6 + 0.3;
+    [channel(8), channel(16), channel(24), 1.0]
+}
+
+/// Orders faces of `mesh` from the furthest to the closest to `eye`, so
+/// translucent faces blend over the ones behind them.
+///
+/// `eye` is in the same space as mesh vertices, faces are quads made of 4
+/// vertices and 6 indices.
+pub fn sort_faces(mesh: &mut Mesh<ChunkVertex>, eye: Vec3) {
+    let mut faces: Vec<(f32, u32)> = mesh
+        .vertices
+        .chunks(4)
+        .enumerate()
+        .map(|(i, corners)| {
+            let center = corners
+                .iter()
+                .map(|it| Vec3::from(it.position))
+                .sum::<Vec3>()
+                / 4.0;
+            (center.distance_squared(eye), i as u32 * 4)
+        })
+        .collect();
+    faces.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    mesh.indices.clear();
+    for (_, first) in faces {
+        for i in [0, 1, 2, 0, 2, 3] {
+            mesh.indices.push_value(first + i);
+        }
+    }
+}
+
+/// Builds meshes of block faces that aren't hidden by other blocks, faces are
+/// bucketed by the [`RenderLayer`] of their block in `blocks`.
+///
+/// `neighbors` are adjacent chunks in [`Face`] order. Border faces are only
+/// hidden by neighbors meshed at the same LOD, faces next to chunks of other
+/// LODs are always emitted so they close the seam between both surfaces.
+///
+/// `light` looks up light of blocks relative to the chunk origin, which can lie
+/// in neighboring chunks. Faces are lit by the block in front of their center
+/// and their corners are occluded by opaque blocks around them.
+pub fn build_chunk_mesh(
+    chunk: &LodChunk,
+    neighbors: &[Option<&LodChunk>; 6],
+    blocks: &BlockRegistry,
+    light: impl Fn([BlockPos; 3]) -> Light,
+) -> ChunkMesh {
+    let mut mesh = ChunkMesh::default();
+
+    let scale = chunk.lod.scale();
+
+    for x in 0..chunk.size {
+        for y in 0..chunk.size {
+            for z in 0..chunk.size {
+                let block = chunk.get(x, y, z);
+                if block == AIR {
+                    continue;
+                }
+
+                let cell = [x, y, z];
+                for face in Face::ALL {
+                    let normal = face.normal();
+                    let next = [0, 1, 2].map(|i| cell[i] as i32 + normal[i]);
+                    let in_front = cell_at(chunk, neighbors, next);
+                    if matches!(in_front, Some(neighbor) if hides(blocks, block, neighbor)) {
+                        continue;
+                    }
+
+                    let step = scale as BlockPos;
+                    let mut front = cell.map(|it| it as BlockPos * step + step / 2);
+                    front[face.axis()] = match face.is_positive() {
+                        true => (cell[face.axis()] as BlockPos + 1) * step,
+                        false => cell[face.axis()] as BlockPos * step - 1,
+                    };
+                    let face_light = light(front);
+                    let color = match blocks.get(block) {
+                        Some(definition) => definition.color.to_array(),
+                        None => placeholder_color(block),
+                    };
+                    let shading = FaceShading {
+                        scale,
+                        light: face_light,
+                        occlusion: corner_occlusion(chunk, neighbors, blocks, cell, face),
+                    };
+                    push_face(
+                        mesh.layer_mut(blocks.layer(block)),
+                        cell,
+                        face,
+                        block,
+                        color,
+                        shading,
+                    );
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+/// Inputs of [`push_face`] that depend on the LOD, neighbors and light
+/// around a face rather than the block it belongs to.
+struct FaceShading {
+    /// Blocks along every edge of a cell at the LOD of the chunk.
+    scale: usize,
+    /// Light of the block in front of the face.
+    light: Light,
+    /// Ambient occlusion of corners from [`corner_occlusion`].
+    occlusion: [f32; 4],
+}
+
+fn push_face(
+    mesh: &mut Mesh<ChunkVertex>,
+    cell: [usize; 3],
+    face: Face,
+    block: BlockID,
+    color: [f32; 4],
+    shading: FaceShading,
+) {
+    let FaceShading {
+        scale,
+        light,
+        occlusion,
+    } = shading;
+    let scale = scale as f32;
+    let axis = face.axis();
+    let (u, v) = face_axes(face);
+
+    let normal = face.normal().map(|it| it as f32);
+    let mut origin = cell.map(|it| it as f32 * scale);
+    if face.is_positive() {
+        origin[axis] += scale;
+    }
+
+    let first = mesh.vertices.len() as u32;
+    for ((du, dv), occlusion) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+        .into_iter()
+        .zip(occlusion)
+    {
+        let mut position = origin;
+        position[u] += du * scale;
+        position[v] += dv * scale;
+        mesh.vertices.push(ChunkVertex {
+            position,
+            normal,
+            color,
+            light: [light.sky, light.block].map(|it| it as f32 / MAX_LIGHT as f32),
+            occlusion: [occlusion],
+            block: [block],
+        });
+    }
+    // split along the brighter diagonal, so occlusion is interpolated the
+    // same way on every face
+    let triangles = if occlusion[0] + occlusion[2] >= occlusion[1] + occlusion[3] {
+        [0, 1, 2, 0, 2, 3]
+    } else {
+        [1, 2, 3, 1, 3, 0]
+    };
+    for i in triangles {
+        mesh.indices.push_value(first + i);
+    }
+}
+
+/// Pipeline drawing chunk meshes with `vertex` and `fragment` entry points of
+/// `chunk.wgsl`.
+fn chunk_shader_pipeline(
+    vertex: VertexInterface<'static>,
+    fragment: Option<FragmentInterface>,
+) -> Pipeline<'static, WgslSource<'static>> {
+    let files = VirtualFiles::new()
+        .with("chunk_vertex.wgsl", ChunkVertex::WGSL_STRUCT)
+        .with("common.wgsl", shader::COMMON_WGSL)
+        .with("cascades.wgsl", CASCADES_WGSL)
+        .with("sky.wgsl", SKY_WGSL);
+    let shader =
+        shader::load_source("chunk.wgsl", &CHUNK_SHADER, files).expect("invalid chunk shader");
+
+    Pipeline::new(shader, vertex, fragment)
+}
+
+fn chunk_color_pipeline(
+    format: TextureFormat,
+    fragment: &str,
+    blend: BlendState,
+) -> Pipeline<'static, WgslSource<'static>> {
+    chunk_shader_pipeline(
+        VertexInterface::new("vs_main").with_buffer(ChunkVertex::LAYOUT),
+        Some(FragmentInterface {
+            entry_point: fragment.to_string(),
+            targets: vec![Some(ColorTargetState {
+                format,
+                blend: Some(blend),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+    )
+}
+
+/// Creates the pipeline opaque chunk meshes are drawn with.
+pub fn chunk_pipeline(format: TextureFormat) -> Pipeline<'static, WgslSource<'static>> {
+    chunk_color_pipeline(format, "fs_main", BlendState::REPLACE)
+}
+
+/// Creates the pipeline alpha tested chunk meshes are drawn with.
+pub fn chunk_cutout_pipeline(format: TextureFormat) -> Pipeline<'static, WgslSource<'static>> {
+    chunk_color_pipeline(format, "fs_cutout", BlendState::REPLACE)
+}
+
+/// Creates the blending pipeline translucent chunk meshes are drawn with, it
+/// should be added with [`Renderer::add_translucent_pipeline`].
+///
+/// [`Renderer::add_translucent_pipeline`]: super::Renderer::add_translucent_pipeline
+pub fn chunk_translucent_pipeline(format: TextureFormat) -> Pipeline<'static, WgslSource<'static>> {
+    chunk_color_pipeline(format, "fs_main", BlendState::ALPHA_BLENDING)
+}
+
+/// Creates the depth-only pipeline chunk meshes cast shadows with.
+pub fn chunk_shadow_pipeline() -> Pipeline<'static, WgslSource<'static>> {
+    chunk_shader_pipeline(
+        VertexInterface::new("vs_shadow").with_buffer(ChunkVertex::LAYOUT),
+        None,
+    )
+}
+
+/// Creates the pipeline chunk meshes are drawn with in a debug `view`,
+/// `None` for views that don't replace how chunks are shaded.
+pub fn chunk_debug_pipeline(
+    format: TextureFormat,
+    view: DebugView,
+) -> Option<Pipeline<'static, WgslSource<'static>>> {
+    let fragment = match view {
+        DebugView::Normals => "fs_normals",
+        DebugView::AmbientOcclusion => "fs_occlusion",
+        DebugView::Light => "fs_light",
+        DebugView::ChunkHeatmap => "fs_heat",
+        _ => return None,
+    };
+    Some(chunk_color_pipeline(format, fragment, BlendState::REPLACE))
+}
+
+/// Pipelines chunk meshes of every [`RenderLayer`] are drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkPipelines {
+    pub opaque: PipelineId,
+    pub cutout: PipelineId,
+    pub translucent: PipelineId,
+}
+
+impl ChunkPipelines {
+    /// Adds pipelines of every layer to `renderer`, along with their debug
+    /// views. Opaque and cutout chunks cast shadows.
+    pub fn register(renderer: &mut Renderer<'_>, format: TextureFormat) -> Self {
+        let pipelines = ChunkPipelines {
+            opaque: renderer.add_pipeline(chunk_pipeline(format)),
+            cutout: renderer.add_pipeline(chunk_cutout_pipeline(format)),
+            translucent: renderer.add_translucent_pipeline(chunk_translucent_pipeline(format)),
+        };
+        for pipeline in [pipelines.opaque, pipelines.cutout] {
+            renderer.add_shadow_caster(pipeline, chunk_shadow_pipeline());
+        }
+        for layer in LAYERS {
+            for view in DebugView::ALL {
+                if let Some(debug) = chunk_debug_pipeline(format, view) {
+                    renderer.add_debug_pipeline(pipelines.layer(layer), view, debug);
+                }
+            }
+        }
+        pipelines
+    }
+
+    pub fn layer(&self, layer: RenderLayer) -> PipelineId {
+        match layer {
+            RenderLayer::Opaque => self.opaque,
+            RenderLayer::Cutout => self.cutout,
+            RenderLayer::Translucent => self.translucent,
+        }
+    }
+}
+
+const LAYERS: [RenderLayer; 3] = [
+    RenderLayer::Opaque,
+    RenderLayer::Cutout,
+    RenderLayer::Translucent,
+];
+
+#[derive(Debug)]
+struct ChunkEntry {
+    lod: Lod,
+    /// Mesh of every layer, `None` for layers without faces.
+    meshes: [Option<MeshId>; 3],
+    /// Translucent faces kept around to sort them again as the camera moves.
+    translucent: Mesh<ChunkVertex>,
+    /// Camera block translucent faces were last sorted for.
+    sorted_for: Option<[i64; 3]>,
+    /// Number of times meshes of the chunk were built.
+    builds: u32,
+}
+
+/// Builds after which chunks are shown fully hot by
+/// [`DebugView::ChunkHeatmap`].
+const HOT_BUILDS: u32 = 8;
+
+/// Keeps meshes of every chunk at the LOD matching its distance from the
+/// camera.
+#[derive(Debug)]
+pub struct ChunkMeshes {
+    pub pipelines: ChunkPipelines,
+    pub settings: LodSettings,
+    chunks: HashMap<ChunkCoord, ChunkEntry>,
+}
+
+impl ChunkMeshes {
+    pub fn new(pipelines: ChunkPipelines, settings: LodSettings) -> Self {
+        ChunkMeshes {
+            pipelines,
+            settings,
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Mesh of a chunk layer, `None` if the chunk has no faces in it.
+    pub fn mesh(&self, coord: ChunkCoord, layer: RenderLayer) -> Option<MeshId> {
+        self.chunks.get(&coord)?.meshes[layer as usize]
+    }
+
+    pub fn lod(&self, coord: ChunkCoord) -> Option<Lod> {
+        self.chunks.get(&coord).map(|it| it.lod)
+    }
+
+    /// Distance from `camera` to the chunk center in chunks.
+    pub fn distance(camera: Vec3, coord: ChunkCoord) -> f32 {
+        let origin = Vec3::from_array(coord.origin().to_array().map(|it| it as f32));
+        let center = origin + Vec3::splat(CHUNK_SIZE as f32 / 2.0);
+        camera.distance(center) / CHUNK_SIZE as f32
+    }
+
+    /// (Re)builds meshes of a chunk if it has none or its LOD changed,
+    /// chunks beyond the view distance or not loaded in `terrain` are removed.
+    ///
+    /// Meshed neighbors of a chunk whose LOD changed are rebuilt as well,
+    /// they only hide border faces behind neighbors of the same LOD.
+    ///
+    /// Returns `true` if meshes changed.
+    pub fn update(
+        &mut self,
+        meshes: &mut MeshManager,
+        terrain: &Terrain,
+        blocks: &BlockRegistry,
+        camera: Vec3,
+        coord: ChunkCoord,
+    ) -> bool {
+        let distance = Self::distance(camera, coord);
+        if distance > self.settings.view_distance {
+            return self.remove(meshes, coord);
+        }
+
+        let current = self.lod(coord);
+        let lod = Lod::update(current, distance, &self.settings);
+        if current == Some(lod) {
+            return false;
+        }
+
+        if !self.rebuild(meshes, terrain, blocks, coord, lod) {
+            return self.remove(meshes, coord);
+        }
+        if current.is_some() {
+            let neighbors = Face::ALL.map(|face| {
+                let [x, y, z] = face.normal();
+                coord.offset(x, y, z)
+            });
+            self.refresh(meshes, terrain, blocks, neighbors);
+        }
+        true
+    }
+
+    /// Meshes chunks whose blocks or light changed, chunks that are already
+    /// meshed are rebuilt at their LOD with [`ChunkMeshes::refresh`], the rest
+    /// go through [`ChunkMeshes::update`].
+    pub fn update_changed(
+        &mut self,
+        meshes: &mut MeshManager,
+        terrain: &Terrain,
+        blocks: &BlockRegistry,
+        camera: Vec3,
+        chunks: impl IntoIterator<Item = ChunkCoord>,
+    ) {
+        let (meshed, new): (Vec<_>, Vec<_>) =
+            chunks.into_iter().partition(|it| self.lod(*it).is_some());
+        self.refresh(meshes, terrain, blocks, meshed);
+        for coord in new {
+            self.update(meshes, terrain, blocks, camera, coord);
+        }
+    }
+
+    /// Builds meshes of chunks again at their current LOD, after their blocks
+    /// or light changed.
+    pub fn refresh(
+        &mut self,
+        meshes: &mut MeshManager,
+        terrain: &Terrain,
+        blocks: &BlockRegistry,
+        chunks: impl IntoIterator<Item = ChunkCoord>,
+    ) {
+        for coord in chunks {
+            if let Some(lod) = self.lod(coord) {
+                self.rebuild(meshes, terrain, blocks, coord, lod);
+            }
+        }
+    }
+
+    /// Builds meshes of a chunk at `lod`, returns `false` if the chunk isn't
+    /// loaded.
+    pub fn rebuild(
+        &mut self,
+        meshes: &mut MeshManager,
+        terrain: &Terrain,
+        blocks: &BlockRegistry,
+        coord: ChunkCoord,
+        lod: Lod,
+    ) -> bool {
+        let chunk = match terrain.chunk(coord) {
+            Some(it) => it,
+            None => return false,
+        };
+        let offsets = Face::ALL.map(|face| {
+            let [x, y, z] = face.normal();
+            coord.offset(x, y, z)
+        });
+        let neighbors = offsets.map(|neighbor| {
+            // only same LOD neighbors can hide border faces
+            match (self.lod(neighbor), terrain.chunk(neighbor)) {
+                (Some(neighbor_lod), Some(chunk)) if neighbor_lod == lod => {
+                    Some(LodChunk::new(chunk, lod))
+                }
+                _ => None,
+            }
+        });
+        let neighbors = [0, 1, 2, 3, 4, 5].map(|i| neighbors[i].as_ref());
+
+        let origin = coord.origin();
+        let mesh = build_chunk_mesh(
+            &LodChunk::new(chunk, lod),
+            &neighbors,
+            blocks,
+            |[x, y, z]| terrain.light().get(origin.offset(x, y, z)),
+        );
+
+        let mut entry = self.chunks.remove(&coord).unwrap_or(ChunkEntry {
+            lod,
+            meshes: [None; 3],
+            translucent: Mesh::new(VertexBuffer::new(), IndexList::new()),
+            sorted_for: None,
+            builds: 0,
+        });
+        entry.lod = lod;
+        entry.builds += 1;
+        let heat = (entry.builds - 1).min(HOT_BUILDS) as f32 / HOT_BUILDS as f32;
+        entry.translucent = mesh.translucent.clone();
+        entry.sorted_for = None;
+        for layer in LAYERS {
+            let slot = &mut entry.meshes[layer as usize];
+            let layer_mesh = mesh.layer(layer);
+            // empty buffers can't be bound, so empty layers have no mesh
+            if layer_mesh.indices.is_empty() {
+                if let Some(id) = slot.take() {
+                    meshes.remove(id);
+                }
+                continue;
+            }
+            match *slot {
+                Some(id) => meshes.set_mesh(id, layer_mesh.clone()),
+                None => {
+                    let id = meshes.insert(layer_mesh.clone(), self.pipelines.layer(layer));
+                    let origin = origin.to_array().map(|it| it as f32);
+                    meshes.set_transform(id, Mat4::from_translation(Vec3::from_array(origin)));
+                    meshes.set_bounds(
+                        id,
+                        Some(AABB::new(Vec3::ZERO, Vec3::splat(CHUNK_SIZE as f32))),
+                    );
+                    *slot = Some(id);
+                }
+            }
+            if let Some(id) = *slot {
+                meshes.set_heat(id, heat);
+            }
+        }
+        self.chunks.insert(coord, entry);
+        true
+    }
+
+    /// Sorts translucent faces of every chunk back to front for `camera`,
+    /// chunks are only sorted again once the camera enters another block.
+    pub fn sort_translucent(&mut self, meshes: &mut MeshManager, camera: Vec3) {
+        let block = camera.floor().to_array().map(|it| it as i64);
+        for (coord, entry) in &mut self.chunks {
+            let id = match entry.meshes[RenderLayer::Translucent as usize] {
+                Some(id) if entry.sorted_for != Some(block) => id,
+                _ => continue,
+            };
+            let origin = Vec3::from_array(coord.origin().to_array().map(|it| it as f32));
+            sort_faces(&mut entry.translucent, camera - origin);
+            meshes.set_mesh(id, entry.translucent.clone());
+            entry.sorted_for = Some(block);
+        }
+    }
+
+    pub fn remove(&mut self, meshes: &mut MeshManager, coord: ChunkCoord) -> bool {
+        match self.chunks.remove(&coord) {
+            Some(entry) => {
+                for id in entry.meshes.into_iter().flatten() {
+                    meshes.remove(id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// Vertex shader
+
+#include "common.wgsl"
+#include "cascades.wgsl"
+#include "sky.wgsl"
+#include "chunk_vertex.wgsl"
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) light: vec2<f32>,
+    @location(3) world_position: vec3<f32>,
+    @location(4) view_depth: f32,
+    @location(5) occlusion: f32,
+    @location(6) heat: f32,
+};
+
+@vertex
+fn vs_main(
+    vertex: ChunkVertex,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let world_position = mesh.model * vec4<f32>(vertex.position, 1.0);
+    out.normal = vertex.normal;
+    out.color = vertex.color;
+    out.light = vertex.light;
+    out.occlusion = vertex.occlusion;
+    out.heat = mesh.heat;
+    out.world_position = world_position.xyz;
+    out.clip_position = camera.view_projection * world_position;
+    // w of perspective projections is the view depth
+    out.view_depth = out.clip_position.w;
+    return out;
+}
+
+// Renders depth of chunks into shadow maps, `camera` holds the cascade.
+@vertex
+fn vs_shadow(
+    vertex: ChunkVertex,
+) -> @builtin(position) vec4<f32> {
+    return camera.view_projection * mesh.model * vec4<f32>(vertex.position, 1.0);
+}
+
+// Fragment shader
+
+// Sky, sun and block light reaching the surface, darkened in occluded
+// corners.
+fn lighting(in: VertexOutput) -> vec3<f32> {
+    let normal = normalize(in.normal);
+    let direct = max(dot(normal, shadow.sun.xyz), 0.0) * sun_visibility(in.world_position, normal, in.view_depth);
+    let sky_light = in.light.x * (sky.ambient.rgb + direct * sky.light.rgb);
+    // squared so lower light levels get dark quickly
+    let level = max(sky_light, vec3<f32>(in.light.y));
+    let light = mix(vec3<f32>(0.03), vec3<f32>(1.0), pow(level, vec3<f32>(2.0)));
+    return light * mix(0.4, 1.0, in.occlusion);
+}
+
+fn shade(in: VertexOutput) -> vec4<f32> {
+    let color = in.color.rgb * lighting(in);
+    return vec4<f32>(apply_fog(color, in.world_position), in.color.a);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(in);
+}
+
+// Alpha tested faces, like leaves, are either fully opaque or not drawn.
+@fragment
+fn fs_cutout(in: VertexOutput) -> @location(0) vec4<f32> {
+    if (in.color.a < 0.5) {
+        discard;
+    }
+    return vec4<f32>(shade(in).rgb, 1.0);
+}
+
+// Debug views
+
+@fragment
+fn fs_normals(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(normalize(in.normal) * 0.5 + 0.5, 1.0);
+}
+
+@fragment
+fn fs_occlusion(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(vec3<f32>(in.occlusion), 1.0);
+}
+
+@fragment
+fn fs_light(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(lighting(in), 1.0);
+}
+
+// Blue for cold chunks through green to red for hot ones.
+@fragment
+fn fs_heat(in: VertexOutput) -> @location(0) vec4<f32> {
+    let heat = clamp(in.heat, 0.0, 1.0);
+    let color = vec3<f32>(
+        smoothstep(0.5, 1.0, heat),
+        1.0 - abs(heat * 2.0 - 1.0),
+        1.0 - smoothstep(0.0, 0.5, heat)
+    );
+    return vec4<f32>(color, 1.0);
+}
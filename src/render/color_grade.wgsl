@@ -0,0 +1,23 @@
+// Remaps colors through a 3D lookup table.
+
+#include "fullscreen.wgsl"
+
+struct ColorGradeUniform {
+    strength: f32,
+    lut_size: f32,
+};
+
+@group(0) @binding(2)
+var<uniform> grade: ColorGradeUniform;
+@group(0) @binding(3)
+var lut: texture_3d<f32>;
+
+@fragment
+fn fs_main(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = clamp(sample_source(in.uv).rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    // samples texel centers so the first and last entries map exactly
+    let scale = (grade.lut_size - 1.0) / grade.lut_size;
+    let offset = 0.5 / grade.lut_size;
+    let graded = textureSampleLevel(lut, source_sampler, color * scale + offset, 0.0).rgb;
+    return vec4<f32>(mix(color, graded, grade.strength), 1.0);
+}
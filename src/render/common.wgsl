@@ -0,0 +1,16 @@
+// Bindings shared by all mesh pipelines of the renderer.
+
+struct MeshUniform {
+    model: mat4x4<f32>,
+    heat: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> mesh: MeshUniform;
+
+struct CameraUniform {
+    view_projection: mat4x4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
@@ -0,0 +1,271 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use wgpu::{Backends, Features, PowerPreference, PresentMode};
+
+use crate::{content::world::CHUNK_DISTANCE, error::ConfigError};
+
+/// File renderer settings are loaded from and saved to by the client.
+pub const CONFIG_PATH: &str = "settings.toml";
+
+/// How presenting frames is synchronized with the display.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VsyncMode {
+    /// Frames wait for the display, no tearing.
+    #[default]
+    On,
+    /// Frames are presented as soon as they're done, they can tear.
+    Off,
+    /// Waits for the display unless a frame is late, late frames tear
+    /// instead of waiting for the next refresh.
+    Adaptive,
+    /// Newest frame is shown on refresh, rendering doesn't wait for the
+    /// display and nothing tears.
+    Mailbox,
+}
+
+impl VsyncMode {
+    /// Present mode of the surface, modes `supported` doesn't contain fall
+    /// back to the closest mode every surface supports.
+    pub fn present_mode(self, supported: &[PresentMode]) -> PresentMode {
+        let (preferred, fallback) = match self {
+            // every surface supports fifo
+            VsyncMode::On => return PresentMode::Fifo,
+            VsyncMode::Off => return PresentMode::AutoNoVsync,
+            VsyncMode::Adaptive => (PresentMode::FifoRelaxed, PresentMode::Fifo),
+            VsyncMode::Mailbox => (PresentMode::Mailbox, PresentMode::AutoNoVsync),
+        };
+        if supported.contains(&preferred) {
+            preferred
+        } else {
+            fallback
+        }
+    }
+}
+
+/// Graphics API the adapter is picked from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendPreference {
+    /// Any backend, the platform's native API is preferred.
+    #[default]
+    Auto,
+    Vulkan,
+    Metal,
+    Dx12,
+    Dx11,
+    Gl,
+    BrowserWebGpu,
+}
+
+impl BackendPreference {
+    pub fn backends(self) -> Backends {
+        match self {
+            BackendPreference::Auto => Backends::all(),
+            BackendPreference::Vulkan => Backends::VULKAN,
+            BackendPreference::Metal => Backends::METAL,
+            BackendPreference::Dx12 => Backends::DX12,
+            BackendPreference::Dx11 => Backends::DX11,
+            BackendPreference::Gl => Backends::GL,
+            BackendPreference::BrowserWebGpu => Backends::BROWSER_WEBGPU,
+        }
+    }
+}
+
+/// Which adapter is picked on systems with more than one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerSetting {
+    #[default]
+    Default,
+    /// Integrated GPUs, saves battery.
+    LowPower,
+    /// Discrete GPUs.
+    HighPerformance,
+}
+
+impl From<PowerSetting> for PowerPreference {
+    fn from(setting: PowerSetting) -> Self {
+        match setting {
+            PowerSetting::Default => PowerPreference::default(),
+            PowerSetting::LowPower => PowerPreference::LowPower,
+            PowerSetting::HighPerformance => PowerPreference::HighPerformance,
+        }
+    }
+}
+
+/// Optional device features requested if the adapter has them, the renderer
+/// works without any of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeatureSettings {
+    /// Line polygon mode used by [`super::debug::DebugView::Wireframe`].
+    pub wireframe: bool,
+}
+
+impl Default for FeatureSettings {
+    fn default() -> Self {
+        FeatureSettings { wireframe: true }
+    }
+}
+
+impl FeatureSettings {
+    pub fn requested(&self) -> Features {
+        let mut features = Features::empty();
+        if self.wireframe {
+            features |= Features::POLYGON_MODE_LINE;
+        }
+        features
+    }
+}
+
+/// Renderer settings, loaded from [`CONFIG_PATH`] and changed at runtime with
+/// [`super::RendererState::set_config`]. Settings missing from the file keep
+/// their default values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateConfig {
+    pub vsync: VsyncMode,
+    /// Samples per pixel scene geometry is drawn with, 1 disables
+    /// multisampling. Counts the adapter doesn't support are lowered to ones
+    /// it does.
+    pub msaa_samples: u32,
+    pub backend: BackendPreference,
+    pub power_preference: PowerSetting,
+    /// Chunks further than this many chunks aren't meshed, fog hides the
+    /// edge of the world.
+    pub view_distance: f32,
+    /// Most frames drawn per second, unlimited if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_cap: Option<u32>,
+    // tables have to come after plain values in TOML
+    pub features: FeatureSettings,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        StateConfig {
+            vsync: VsyncMode::default(),
+            msaa_samples: 1,
+            backend: BackendPreference::default(),
+            power_preference: PowerSetting::default(),
+            view_distance: CHUNK_DISTANCE as f32,
+            frame_cap: None,
+            features: FeatureSettings::default(),
+        }
+    }
+}
+
+impl StateConfig {
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        StateConfig::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Loads settings from `path`, using the defaults if the file doesn't
+    /// exist or isn't valid.
+    pub fn load_or_default(path: impl AsRef<Path>) -> Self {
+        match StateConfig::load(path.as_ref()) {
+            Ok(config) => config,
+            Err(ConfigError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+                StateConfig::default()
+            }
+            Err(err) => {
+                tracing::warn!("unable to load {}: {}", path.as_ref().display(), err);
+                StateConfig::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    /// Largest of `supported` sample counts that isn't above
+    /// [`StateConfig::msaa_samples`].
+    pub fn sample_count(&self, supported: &[u32]) -> u32 {
+        supported
+            .iter()
+            .copied()
+            .filter(|it| *it <= self.msaa_samples)
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// What has to be reconfigured to go from these settings to `other`.
+    pub fn changes(&self, other: &StateConfig) -> ConfigChanges {
+        ConfigChanges {
+            surface: self.vsync != other.vsync,
+            pipelines: self.msaa_samples != other.msaa_samples,
+            view: self.view_distance != other.view_distance,
+            restart: self.backend != other.backend
+                || self.power_preference != other.power_preference
+                || self.features != other.features,
+        }
+    }
+}
+
+/// Parts of the renderer affected by a settings change.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigChanges {
+    /// The surface has to be configured again.
+    pub surface: bool,
+    /// Render pipelines and multisampled attachments have to be created
+    /// again.
+    pub pipelines: bool,
+    /// Chunk streaming and fog distance changed.
+    pub view: bool,
+    /// The device has to be created again, these settings take effect on the
+    /// next start.
+    pub restart: bool,
+}
+
+impl ConfigChanges {
+    pub fn is_empty(&self) -> bool {
+        *self == ConfigChanges::default()
+    }
+}
+
+/// Paces redraws so no more than [`StateConfig::frame_cap`] frames are drawn
+/// per second.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameLimiter {
+    next: Option<Instant>,
+}
+
+impl FrameLimiter {
+    /// Returns `None` if a frame should be drawn `now`, otherwise the instant
+    /// to wait for. Frames that are late don't make later ones come sooner.
+    pub fn wait(&mut self, frame_cap: Option<u32>, now: Instant) -> Option<Instant> {
+        let interval = match frame_cap {
+            Some(fps) if fps > 0 => Duration::from_secs_f64(1.0 / fps as f64),
+            _ => {
+                self.next = None;
+                return None;
+            }
+        };
+        match self.next {
+            Some(next) if now < next => Some(next),
+            Some(next) if now < next + interval => {
+                self.next = Some(next + interval);
+                None
+            }
+            _ => {
+                self.next = Some(now + interval);
+                None
+            }
+        }
+    }
+}
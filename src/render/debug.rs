@@ -0,0 +1,276 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Vec3, Vec4};
+use wgpu::*;
+
+use crate::math::AABB;
+
+use super::{
+    pipeline::{FragmentInterface, Pipeline, VertexInterface},
+    shader::{self, preprocessor::VirtualFiles, WgslSource},
+    vertex::{VertexBuffer, VertexData},
+};
+
+/// Optional device features debug views use if the adapter has them.
+pub const DEBUG_FEATURES: Features = Features::POLYGON_MODE_LINE;
+
+pub static DEBUG_LINES_SHADER: WgslSource<'static> =
+    WgslSource::new_static(include_str!("debug_lines.wgsl"));
+
+/// What the main pass shows, views other than [`DebugView::Shaded`] help
+/// with finding rendering and meshing issues.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    #[default]
+    Shaded,
+    /// Triangle edges, needs [`Features::POLYGON_MODE_LINE`].
+    Wireframe,
+    /// World space normals as colors.
+    Normals,
+    /// Shaded, with the bounds of every drawn mesh outlined.
+    ChunkBounds,
+    /// Ambient occlusion of chunk vertices only.
+    AmbientOcclusion,
+    /// Sky and block light on white surfaces, without block colors or fog.
+    Light,
+    /// Chunks colored from blue to red by how often their meshes were
+    /// rebuilt.
+    ChunkHeatmap,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 7] = [
+        DebugView::Shaded,
+        DebugView::Wireframe,
+        DebugView::Normals,
+        DebugView::ChunkBounds,
+        DebugView::AmbientOcclusion,
+        DebugView::Light,
+        DebugView::ChunkHeatmap,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Shaded => "shaded",
+            DebugView::Wireframe => "wireframe",
+            DebugView::Normals => "normals",
+            DebugView::ChunkBounds => "chunk bounds",
+            DebugView::AmbientOcclusion => "ambient occlusion",
+            DebugView::Light => "light",
+            DebugView::ChunkHeatmap => "chunk heatmap",
+        }
+    }
+
+    pub fn required_features(self) -> Features {
+        match self {
+            DebugView::Wireframe => Features::POLYGON_MODE_LINE,
+            _ => Features::empty(),
+        }
+    }
+
+    pub fn is_supported(self, features: Features) -> bool {
+        features.contains(self.required_features())
+    }
+
+    /// The view after this one, skipping views `features` don't support.
+    pub fn next(self, features: Features) -> DebugView {
+        let count = DebugView::ALL.len();
+        (1..=count)
+            .map(|offset| DebugView::ALL[(self as usize + offset) % count])
+            .find(|it| it.is_supported(features))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Pod, Zeroable, VertexData)]
+#[repr(C)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+/// World space lines drawn over the scene for a single frame.
+#[derive(Debug, Default, Clone)]
+pub struct DebugLines {
+    vertices: VertexBuffer<'static, LineVertex>,
+}
+
+impl DebugLines {
+    pub fn new() -> Self {
+        DebugLines::default()
+    }
+
+    /// Pairs of vertices, every pair is a line.
+    pub fn vertices(&self) -> &[LineVertex] {
+        &self.vertices
+    }
+
+    pub fn len(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Vec4) {
+        let color = color.to_array();
+        for position in [start, end] {
+            self.vertices.push(LineVertex {
+                position: position.to_array(),
+                color,
+            });
+        }
+    }
+
+    /// Line `length` long from `origin` along `direction`.
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, length: f32, color: Vec4) {
+        self.line(
+            origin,
+            origin + direction.normalize_or_zero() * length,
+            color,
+        );
+    }
+
+    /// The 12 edges of `bounds`.
+    pub fn aabb(&mut self, bounds: &AABB<f32, Vec3>, color: Vec4) {
+        let corners = bounds.corners();
+        // corners differing along a single axis share an edge
+        for axis in [1, 2, 4] {
+            for i in (0..8).filter(|it| it & axis == 0) {
+                self.line(corners[i], corners[i | axis], color);
+            }
+        }
+    }
+
+    /// Red, green and blue lines `size` long along `X`, `Y` and `Z`.
+    pub fn axes(&mut self, origin: Vec3, size: f32) {
+        self.line(
+            origin,
+            origin + Vec3::X * size,
+            Vec4::new(1.0, 0.0, 0.0, 1.0),
+        );
+        self.line(
+            origin,
+            origin + Vec3::Y * size,
+            Vec4::new(0.0, 1.0, 0.0, 1.0),
+        );
+        self.line(
+            origin,
+            origin + Vec3::Z * size,
+            Vec4::new(0.0, 0.0, 1.0, 1.0),
+        );
+    }
+}
+
+/// Creates the pipeline debug lines are drawn with, its only bind group is
+/// the camera.
+pub fn debug_lines_pipeline(format: TextureFormat) -> Pipeline<'static, WgslSource<'static>> {
+    let files = VirtualFiles::new().with("line_vertex.wgsl", LineVertex::WGSL_STRUCT);
+    let shader = shader::load_source("debug_lines.wgsl", &DEBUG_LINES_SHADER, files)
+        .expect("invalid debug line shader");
+
+    let mut pipeline = Pipeline::new(
+        shader,
+        VertexInterface::new("vs_main").with_buffer(LineVertex::LAYOUT),
+        Some(FragmentInterface {
+            entry_point: "fs_main".to_string(),
+            targets: vec![Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+    );
+    pipeline.topology = PrimitiveTopology::LineList;
+    // lines are hidden by geometry in front of them, but don't hide anything
+    pipeline.depth_stencil = Some(DepthStencilState {
+        format: super::DEPTH_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: CompareFunction::LessEqual,
+        stencil: StencilState::default(),
+        bias: DepthBiasState::default(),
+    });
+    pipeline
+}
+
+/// Uploads and draws [`DebugLines`].
+#[derive(Debug)]
+pub struct DebugLineRenderer {
+    pipeline: Pipeline<'static, WgslSource<'static>>,
+    render_pipeline: Option<RenderPipeline>,
+    buffer: Option<Buffer>,
+    /// Vertices the buffer has room for.
+    capacity: usize,
+    vertex_count: u32,
+}
+
+impl DebugLineRenderer {
+    pub fn new(format: TextureFormat) -> Self {
+        DebugLineRenderer {
+            pipeline: debug_lines_pipeline(format),
+            render_pipeline: None,
+            buffer: None,
+            capacity: 0,
+            vertex_count: 0,
+        }
+    }
+
+    pub fn configure(
+        &mut self,
+        device: &Device,
+        camera_layout: &BindGroupLayout,
+        sample_count: u32,
+    ) {
+        self.pipeline.sample_count = sample_count;
+        self.render_pipeline = Some(
+            self.pipeline
+                .create_render_pipeline(device, &[camera_layout]),
+        );
+    }
+
+    /// Recreates the render pipeline if its shader changed on disk.
+    pub fn reload_shader(&mut self, device: &Device, camera_layout: &BindGroupLayout) {
+        if self.pipeline.reload_shader() {
+            self.configure(device, camera_layout, self.pipeline.sample_count);
+        }
+    }
+
+    /// Uploads line vertices, the buffer grows to fit them.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, vertices: &[LineVertex]) {
+        self.vertex_count = vertices.len() as u32;
+        if vertices.is_empty() {
+            return;
+        }
+        if self.buffer.is_none() || self.capacity < vertices.len() {
+            self.capacity = vertices.len().next_power_of_two();
+            self.buffer = Some(device.create_buffer(&BufferDescriptor {
+                label: Some("debug_lines"),
+                size: (self.capacity * LineVertex::SIZE) as BufferAddress,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        if let Some(buffer) = &self.buffer {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(vertices));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertex_count == 0
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, camera: &'a BindGroup) {
+        if let (Some(pipeline), Some(buffer)) = (&self.render_pipeline, &self.buffer) {
+            if self.vertex_count > 0 {
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(0, camera, &[]);
+                pass.set_vertex_buffer(0, buffer.slice(..));
+                pass.draw(0..self.vertex_count, 0..1);
+            }
+        }
+    }
+}
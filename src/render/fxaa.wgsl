@@ -0,0 +1,143 @@
+// Fast approximate anti-aliasing, based on FXAA 3.11 by Timothy Lottes.
+// Expects tonemapped colors.
+
+#include "fullscreen.wgsl"
+
+struct FxaaUniform {
+    // Smallest local contrast, relative to the brightest neighbor, that's
+    // treated as an edge.
+    edge_threshold: f32,
+    // Contrast of dark areas below which edges are ignored.
+    edge_threshold_min: f32,
+    // How much single pixel details are smoothed out, from 0 to 1.
+    subpixel: f32,
+};
+
+@group(0) @binding(2)
+var<uniform> fxaa: FxaaUniform;
+
+let SEARCH_STEPS: i32 = 10;
+
+fn luma(color: vec3<f32>) -> f32 {
+    return sqrt(dot(color, vec3<f32>(0.299, 0.587, 0.114)));
+}
+
+fn luma_at(uv: vec2<f32>) -> f32 {
+    return luma(sample_source(uv).rgb);
+}
+
+@fragment
+fn fs_main(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    let center = sample_source(in.uv);
+
+    let m = luma(center.rgb);
+    let n = luma_at(in.uv + vec2<f32>(0.0, -texel.y));
+    let s = luma_at(in.uv + vec2<f32>(0.0, texel.y));
+    let w = luma_at(in.uv + vec2<f32>(-texel.x, 0.0));
+    let e = luma_at(in.uv + vec2<f32>(texel.x, 0.0));
+
+    let highest = max(max(max(n, s), max(w, e)), m);
+    let lowest = min(min(min(n, s), min(w, e)), m);
+    let contrast = highest - lowest;
+    if (contrast < max(fxaa.edge_threshold_min, highest * fxaa.edge_threshold)) {
+        return center;
+    }
+
+    let nw = luma_at(in.uv + vec2<f32>(-texel.x, -texel.y));
+    let ne = luma_at(in.uv + vec2<f32>(texel.x, -texel.y));
+    let sw = luma_at(in.uv + vec2<f32>(-texel.x, texel.y));
+    let se = luma_at(in.uv + vec2<f32>(texel.x, texel.y));
+
+    // blend of the pixel with its neighborhood, for details thinner than a
+    // pixel which have no edge to follow
+    let average = (2.0 * (n + s + w + e) + nw + ne + sw + se) / 12.0;
+    let subpixel_blend = smoothstep(0.0, 1.0, clamp(abs(average - m) / contrast, 0.0, 1.0));
+    let subpixel = subpixel_blend * subpixel_blend * fxaa.subpixel;
+
+    let horizontal = abs(n + s - 2.0 * m) * 2.0 + abs(ne + se - 2.0 * e) + abs(nw + sw - 2.0 * w);
+    let vertical = abs(w + e - 2.0 * m) * 2.0 + abs(nw + ne - 2.0 * n) + abs(sw + se - 2.0 * s);
+    let is_horizontal = horizontal >= vertical;
+
+    // step across the edge towards the side with the larger gradient
+    var positive = e;
+    var negative = w;
+    var step_size = texel.x;
+    if (is_horizontal) {
+        positive = s;
+        negative = n;
+        step_size = texel.y;
+    }
+    let positive_gradient = abs(positive - m);
+    let negative_gradient = abs(negative - m);
+    var opposite = positive;
+    var gradient = positive_gradient;
+    if (negative_gradient > positive_gradient) {
+        step_size = -step_size;
+        opposite = negative;
+        gradient = negative_gradient;
+    }
+
+    // walk along the edge in both directions until its end
+    var edge_uv = in.uv;
+    var edge_step = vec2<f32>(texel.x, 0.0);
+    if (is_horizontal) {
+        edge_uv.y = edge_uv.y + step_size * 0.5;
+    } else {
+        edge_uv.x = edge_uv.x + step_size * 0.5;
+        edge_step = vec2<f32>(0.0, texel.y);
+    }
+    let edge_luma = (m + opposite) * 0.5;
+    let gradient_threshold = gradient * 0.25;
+
+    var positive_uv = edge_uv + edge_step;
+    var positive_delta = luma_at(positive_uv) - edge_luma;
+    var positive_end = abs(positive_delta) >= gradient_threshold;
+    var negative_uv = edge_uv - edge_step;
+    var negative_delta = luma_at(negative_uv) - edge_luma;
+    var negative_end = abs(negative_delta) >= gradient_threshold;
+    for (var i = 0; i < SEARCH_STEPS; i = i + 1) {
+        if (positive_end && negative_end) {
+            break;
+        }
+        if (!positive_end) {
+            positive_uv = positive_uv + edge_step;
+            positive_delta = luma_at(positive_uv) - edge_luma;
+            positive_end = abs(positive_delta) >= gradient_threshold;
+        }
+        if (!negative_end) {
+            negative_uv = negative_uv - edge_step;
+            negative_delta = luma_at(negative_uv) - edge_luma;
+            negative_end = abs(negative_delta) >= gradient_threshold;
+        }
+    }
+
+    var positive_distance = positive_uv.x - in.uv.x;
+    var negative_distance = in.uv.x - negative_uv.x;
+    if (!is_horizontal) {
+        positive_distance = positive_uv.y - in.uv.y;
+        negative_distance = in.uv.y - negative_uv.y;
+    }
+
+    // only blend if the pixel is on the side of the closer edge end that
+    // crosses the edge
+    var closest_delta = negative_delta;
+    var closest = negative_distance;
+    if (positive_distance <= negative_distance) {
+        closest_delta = positive_delta;
+        closest = positive_distance;
+    }
+    var edge_blend = 0.5 - closest / (positive_distance + negative_distance);
+    if ((closest_delta < 0.0) == (m - edge_luma < 0.0)) {
+        edge_blend = 0.0;
+    }
+
+    let blend = max(edge_blend, subpixel);
+    var uv = in.uv;
+    if (is_horizontal) {
+        uv.y = uv.y + step_size * blend;
+    } else {
+        uv.x = uv.x + step_size * blend;
+    }
+    return vec4<f32>(sample_source(uv).rgb, center.a);
+}
@@ -0,0 +1,346 @@
+use std::collections::{HashMap, HashSet};
+
+use petgraph::{algo::toposort, graph::DiGraph};
+use wgpu::*;
+
+use crate::error::GraphError;
+
+/// Name of the swapchain (or offscreen target) attachment. It's provided on
+/// every [`RenderGraph::execute`] call instead of being allocated by the
+/// graph.
+pub const SURFACE: &str = "surface";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttachmentSize {
+    /// Same size as the surface.
+    Surface,
+    /// Surface size multiplied by a factor.
+    Scaled(f32),
+    Fixed(u32, u32),
+}
+
+impl AttachmentSize {
+    fn resolve(self, surface: (u32, u32)) -> (u32, u32) {
+        match self {
+            AttachmentSize::Surface => surface,
+            AttachmentSize::Scaled(factor) => (
+                ((surface.0 as f32 * factor) as u32).max(1),
+                ((surface.1 as f32 * factor) as u32).max(1),
+            ),
+            AttachmentSize::Fixed(width, height) => (width, height),
+        }
+    }
+}
+
+/// Texture allocated by the graph for passes to render into.
+#[derive(Debug, Clone, Copy)]
+pub struct AttachmentDescriptor {
+    pub format: TextureFormat,
+    pub size: AttachmentSize,
+    pub sample_count: u32,
+    pub usage: TextureUsages,
+    /// Array layers, every one can be rendered to through
+    /// [`Attachments::layer`].
+    pub layers: u32,
+    /// Dimension of the view passes sample the attachment through.
+    pub view_dimension: TextureViewDimension,
+}
+
+impl AttachmentDescriptor {
+    /// Surface sized attachment that can be rendered to and sampled.
+    pub fn new(format: TextureFormat) -> Self {
+        AttachmentDescriptor {
+            format,
+            size: AttachmentSize::Surface,
+            sample_count: 1,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            layers: 1,
+            view_dimension: TextureViewDimension::D2,
+        }
+    }
+
+    pub fn with_size(mut self, size: AttachmentSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    /// Makes the attachment an array of `layers`, it's viewed as an array
+    /// even with a single layer.
+    pub fn with_layers(mut self, layers: u32) -> Self {
+        self.layers = layers.max(1);
+        self.view_dimension = TextureViewDimension::D2Array;
+        self
+    }
+}
+
+/// Step of a frame that reads and writes graph attachments.
+///
+/// `C` is the context passes are executed with, usually the renderer that
+/// owns buffers and pipelines the passes draw with.
+pub trait GraphPass<C> {
+    /// Attachments the pass samples from.
+    fn reads(&self) -> &[&'static str] {
+        &[]
+    }
+
+    /// Attachments the pass renders into.
+    fn writes(&self) -> &[&'static str];
+
+    /// Called before any pass of the frame is recorded, used to (re)create
+    /// resources that depend on attachments and upload pass parameters.
+    fn prepare(
+        &mut self,
+        _device: &Device,
+        _queue: &Queue,
+        _context: &mut C,
+        _attachments: &Attachments,
+    ) {
+    }
+
+    fn execute(&mut self, context: &mut C, encoder: &mut CommandEncoder, attachments: &Attachments);
+}
+
+struct Transient {
+    texture: Texture,
+    view: TextureView,
+    /// Views of single layers, only kept for array attachments.
+    layers: Vec<TextureView>,
+}
+
+/// Attachment views available to passes while a frame is being recorded.
+pub struct Attachments<'a> {
+    surface: &'a TextureView,
+    transient: &'a HashMap<&'static str, Transient>,
+}
+
+impl<'a> Attachments<'a> {
+    pub fn view(&self, name: &str) -> Option<&TextureView> {
+        if name == SURFACE {
+            return Some(self.surface);
+        }
+        self.transient.get(name).map(|it| &it.view)
+    }
+
+    /// View of a single array layer, passes render into layers through them.
+    pub fn layer(&self, name: &str, layer: u32) -> Option<&TextureView> {
+        let transient = self.transient.get(name)?;
+        match (transient.layers.is_empty(), layer) {
+            (true, 0) => Some(&transient.view),
+            _ => transient.layers.get(layer as usize),
+        }
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&Texture> {
+        self.transient.get(name).map(|it| &it.texture)
+    }
+}
+
+/// Orders passes by the attachments they use and manages transient textures
+/// they render into.
+///
+/// Every pass writing an attachment runs before passes that only read it.
+/// Passes writing the same attachment run in the order they were added in.
+///
+/// Buffers passes exchange are declared as well so they're ordered the same
+/// way, the graph doesn't allocate them.
+pub struct RenderGraph<C> {
+    passes: Vec<(&'static str, Box<dyn GraphPass<C>>)>,
+    descriptors: HashMap<&'static str, AttachmentDescriptor>,
+    buffers: HashSet<&'static str>,
+    order: Option<Vec<usize>>,
+
+    size: (u32, u32),
+    transient: HashMap<&'static str, Transient>,
+}
+
+impl<C> RenderGraph<C> {
+    pub fn new() -> Self {
+        RenderGraph {
+            passes: Vec::new(),
+            descriptors: HashMap::new(),
+            buffers: HashSet::new(),
+            order: None,
+            size: (0, 0),
+            transient: HashMap::new(),
+        }
+    }
+
+    pub fn add_attachment(&mut self, name: &'static str, descriptor: AttachmentDescriptor) {
+        self.descriptors.insert(name, descriptor);
+        self.transient.remove(name);
+    }
+
+    /// Declares a buffer passes can read and write, it's owned by the context.
+    pub fn add_buffer(&mut self, name: &'static str) {
+        self.buffers.insert(name);
+        self.order = None;
+    }
+
+    pub fn add_pass(&mut self, name: &'static str, pass: impl GraphPass<C> + 'static) {
+        self.passes.push((name, Box::new(pass)));
+        self.order = None;
+    }
+
+    pub fn with_attachment(mut self, name: &'static str, descriptor: AttachmentDescriptor) -> Self {
+        self.add_attachment(name, descriptor);
+        self
+    }
+
+    pub fn with_buffer(mut self, name: &'static str) -> Self {
+        self.add_buffer(name);
+        self
+    }
+
+    pub fn with_pass(mut self, name: &'static str, pass: impl GraphPass<C> + 'static) -> Self {
+        self.add_pass(name, pass);
+        self
+    }
+
+    /// Sorts passes so every attachment is written before it's read.
+    pub fn compile(&mut self) -> Result<(), GraphError> {
+        let mut graph = DiGraph::<usize, &'static str>::with_capacity(self.passes.len(), 0);
+        let nodes: Vec<_> = (0..self.passes.len()).map(|i| graph.add_node(i)).collect();
+
+        let mut writers: HashMap<&'static str, Vec<usize>> = HashMap::new();
+        for (i, (name, pass)) in self.passes.iter().enumerate() {
+            for attachment in pass.reads().iter().chain(pass.writes()) {
+                let known = *attachment == SURFACE
+                    || self.descriptors.contains_key(attachment)
+                    || self.buffers.contains(attachment);
+                if !known {
+                    return Err(GraphError::UnknownAttachment(name, attachment));
+                }
+            }
+            for attachment in pass.writes() {
+                writers.entry(attachment).or_default().push(i);
+            }
+        }
+
+        for (attachment, writers) in &writers {
+            for pair in writers.windows(2) {
+                graph.add_edge(nodes[pair[0]], nodes[pair[1]], attachment);
+            }
+        }
+        for (reader, (name, pass)) in self.passes.iter().enumerate() {
+            for attachment in pass.reads() {
+                if pass.writes().contains(attachment) {
+                    continue;
+                }
+                let writers = writers
+                    .get(attachment)
+                    .ok_or(GraphError::NeverWritten(name, attachment))?;
+                for writer in writers {
+                    graph.add_edge(nodes[*writer], nodes[reader], attachment);
+                }
+            }
+        }
+
+        let order = toposort(&graph, None)
+            .map_err(|cycle| GraphError::Cycle(self.passes[graph[cycle.node_id()]].0))?;
+        self.order = Some(order.into_iter().map(|node| graph[node]).collect());
+
+        Ok(())
+    }
+
+    /// Names of passes in the order they'll be executed in.
+    pub fn order(&self) -> Option<impl Iterator<Item = &'static str> + '_> {
+        let order = self.order.as_ref()?;
+        Some(order.iter().map(|i| self.passes[*i].0))
+    }
+
+    /// (Re)creates transient attachments for a surface of given size.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.size = (width.max(1), height.max(1));
+        self.transient.clear();
+        self.allocate(device);
+    }
+
+    fn allocate(&mut self, device: &Device) {
+        for (name, descriptor) in &self.descriptors {
+            if self.transient.contains_key(name) {
+                continue;
+            }
+
+            let (width, height) = descriptor.size.resolve(self.size);
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some(name),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: descriptor.layers,
+                },
+                mip_level_count: 1,
+                sample_count: descriptor.sample_count,
+                dimension: TextureDimension::D2,
+                format: descriptor.format,
+                usage: descriptor.usage,
+            });
+            let view = texture.create_view(&TextureViewDescriptor {
+                dimension: Some(descriptor.view_dimension),
+                ..Default::default()
+            });
+            let layers = if descriptor.view_dimension == TextureViewDimension::D2Array {
+                (0..descriptor.layers)
+                    .map(|layer| {
+                        texture.create_view(&TextureViewDescriptor {
+                            dimension: Some(TextureViewDimension::D2),
+                            base_array_layer: layer,
+                            array_layer_count: std::num::NonZeroU32::new(1),
+                            ..Default::default()
+                        })
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            self.transient.insert(
+                name,
+                Transient {
+                    texture,
+                    view,
+                    layers,
+                },
+            );
+        }
+    }
+
+    /// Records all passes into `encoder`.
+    pub fn execute(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        context: &mut C,
+        encoder: &mut CommandEncoder,
+        surface: &TextureView,
+    ) {
+        if self.order.is_none() {
+            self.compile().expect("invalid render graph");
+        }
+        self.allocate(device);
+
+        let attachments = Attachments {
+            surface,
+            transient: &self.transient,
+        };
+        let order = self.order.as_ref().unwrap();
+        for i in order {
+            self.passes[*i]
+                .1
+                .prepare(device, queue, context, &attachments);
+        }
+        for i in order {
+            self.passes[*i].1.execute(context, encoder, &attachments);
+        }
+    }
+}
+
+impl<C> Default for RenderGraph<C> {
+    fn default() -> Self {
+        RenderGraph::new()
+    }
+}
@@ -0,0 +1,214 @@
+use std::{num::NonZeroU32, path::Path};
+
+use wgpu::*;
+
+use crate::error::RenderError;
+
+use super::{
+    debug::DEBUG_FEATURES, graph::RenderGraph, occlusion::CullingMode, post::HDR_FORMAT, Renderer,
+};
+
+/// Format of offscreen render targets.
+pub const HEADLESS_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeadlessConfig {
+    pub width: u32,
+    pub height: u32,
+    pub power_preference: PowerPreference,
+    /// Use a software adapter (WARP, lavapipe, ...) even if hardware ones
+    /// are available. Software adapters are tried anyway if there's no GPU.
+    pub force_fallback_adapter: bool,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        HeadlessConfig {
+            width: 256,
+            height: 256,
+            power_preference: PowerPreference::default(),
+            force_fallback_adapter: false,
+        }
+    }
+}
+
+/// Renderer state that draws into an offscreen texture instead of a window
+/// surface, used for tests and frame captures.
+pub struct HeadlessState {
+    pub device: Device,
+    pub queue: Queue,
+    pub adapter_info: AdapterInfo,
+    pub config: HeadlessConfig,
+
+    target: Texture,
+    output_buffer: Buffer,
+
+    pub renderer: Renderer<'static>,
+    pub graph: RenderGraph<Renderer<'static>>,
+}
+
+impl HeadlessState {
+    pub async fn new(config: HeadlessConfig) -> Result<Self, RenderError> {
+        let instance = Instance::new(Backends::all());
+
+        let mut adapter = None;
+        if !config.force_fallback_adapter {
+            adapter = instance
+                .request_adapter(&RequestAdapterOptions {
+                    power_preference: config.power_preference,
+                    compatible_surface: None,
+                    force_fallback_adapter: false,
+                })
+                .await;
+        }
+        if adapter.is_none() {
+            adapter = instance
+                .request_adapter(&RequestAdapterOptions {
+                    power_preference: config.power_preference,
+                    compatible_surface: None,
+                    force_fallback_adapter: true,
+                })
+                .await;
+        }
+        let adapter = adapter.ok_or(RenderError::NoAdapter)?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    features: adapter.features() & DEBUG_FEATURES,
+                    limits: Limits::downlevel_defaults(),
+                    label: None,
+                },
+                None, // Trace path
+            )
+            .await?;
+
+        let target = device.create_texture(&TextureDescriptor {
+            label: Some("Headless Target"),
+            size: Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: HEADLESS_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        });
+
+        let output_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Headless Output Buffer"),
+            size: (padded_bytes_per_row(config.width) * config.height) as BufferAddress,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut renderer = Renderer::dev(HDR_FORMAT);
+        renderer.culling = CullingMode::supported(&adapter);
+        renderer.features = device.features();
+        renderer.resize(&device, config.width, config.height);
+        let mut graph = Renderer::graph(
+            HEADLESS_FORMAT,
+            &renderer.post,
+            &renderer.shadows,
+            renderer.sample_count,
+        );
+        graph.resize(&device, config.width, config.height);
+
+        Ok(HeadlessState {
+            device,
+            queue,
+            adapter_info: adapter.get_info(),
+            config,
+            target,
+            output_buffer,
+            renderer,
+            graph,
+        })
+    }
+
+    /// Renders a frame and reads it back from the GPU.
+    pub fn render(&mut self) -> Result<image::RgbaImage, RenderError> {
+        if !self.renderer.is_configured() {
+            self.renderer.configure(&self.device, &self.queue);
+        }
+        self.renderer.prepare(&self.device, &self.queue);
+
+        let view = self.target.create_view(&TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Headless Encoder"),
+            });
+
+        self.graph.execute(
+            &self.device,
+            &self.queue,
+            &mut self.renderer,
+            &mut encoder,
+            &view,
+        );
+
+        let padded_row = padded_bytes_per_row(self.config.width);
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: &self.target,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &self.output_buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_row),
+                    rows_per_image: NonZeroU32::new(self.config.height),
+                },
+            },
+            Extent3d {
+                width: self.config.width,
+                height: self.config.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = self.output_buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.device.poll(Maintain::Wait);
+        rx.recv().map_err(|_| RenderError::MapAborted)??;
+
+        let row = (self.config.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(row * self.config.height as usize);
+        {
+            let mapped = slice.get_mapped_range();
+            for padded in mapped.chunks(padded_row as usize) {
+                pixels.extend_from_slice(&padded[..row]);
+            }
+        }
+        self.output_buffer.unmap();
+
+        Ok(
+            image::RgbaImage::from_raw(self.config.width, self.config.height, pixels)
+                .expect("output buffer smaller than the image"),
+        )
+    }
+
+    /// Renders a frame and saves it as a PNG image.
+    pub fn capture(&mut self, path: impl AsRef<Path>) -> Result<(), RenderError> {
+        self.render()?
+            .save_with_format(path, image::ImageFormat::Png)?;
+        Ok(())
+    }
+}
+
+/// Buffer rows copied from textures must be aligned to
+/// [`COPY_BYTES_PER_ROW_ALIGNMENT`].
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let unpadded = width * 4;
+    unpadded.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT
+}
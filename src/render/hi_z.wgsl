@@ -0,0 +1,69 @@
+// Builds a hierarchical-Z pyramid from a depth buffer, every texel of a level
+// stores the furthest depth of the texels it covers in the previous level.
+
+@group(0) @binding(0)
+var depth: texture_depth_2d;
+
+@group(0) @binding(1)
+var previous: texture_2d<f32>;
+
+@group(0) @binding(2)
+var level: texture_storage_2d<r32float, write>;
+
+@group(0) @binding(3)
+var depth_multisampled: texture_depth_multisampled_2d;
+
+@compute @workgroup_size(8, 8)
+fn copy_depth(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(level);
+    let coords = vec2<i32>(id.xy);
+    if (coords.x >= size.x || coords.y >= size.y) {
+        return;
+    }
+
+    let value = textureLoad(depth, coords, 0);
+    textureStore(level, coords, vec4<f32>(value, 0.0, 0.0, 0.0));
+}
+
+// keeps the furthest sample, so edges covering only part of a pixel don't
+// hide what's behind them
+@compute @workgroup_size(8, 8)
+fn copy_depth_multisampled(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(level);
+    let coords = vec2<i32>(id.xy);
+    if (coords.x >= size.x || coords.y >= size.y) {
+        return;
+    }
+
+    var value = 0.0;
+    let samples = textureNumSamples(depth_multisampled);
+    for (var i = 0; i < samples; i = i + 1) {
+        value = max(value, textureLoad(depth_multisampled, coords, i));
+    }
+    textureStore(level, coords, vec4<f32>(value, 0.0, 0.0, 0.0));
+}
+
+@compute @workgroup_size(8, 8)
+fn downsample(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(level);
+    let coords = vec2<i32>(id.xy);
+    if (coords.x >= size.x || coords.y >= size.y) {
+        return;
+    }
+
+    let last = textureDimensions(previous) - vec2<i32>(1, 1);
+    let base = coords * 2;
+    var value = 0.0;
+    // odd sized levels fold the last row and column into the previous texel
+    for (var y = 0; y < 3; y = y + 1) {
+        for (var x = 0; x < 3; x = x + 1) {
+            let offset = vec2<i32>(x, y);
+            let extra = (x == 2 && base.x + 2 != last.x) || (y == 2 && base.y + 2 != last.y);
+            if (!extra) {
+                let sample = min(base + offset, last);
+                value = max(value, textureLoad(previous, sample, 0).r);
+            }
+        }
+    }
+    textureStore(level, coords, vec4<f32>(value, 0.0, 0.0, 0.0));
+}
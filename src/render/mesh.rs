@@ -0,0 +1,567 @@
+use std::fmt::Debug;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use wgpu::*;
+
+use crate::math::{Frustum, AABB};
+
+use super::{
+    occlusion::{DrawArgs, MeshBounds},
+    pool::{MeshPool, PooledMesh},
+    uniform::ShaderUniform,
+    vertex::{IndexList, VertexBuffer, VertexData},
+};
+
+/// Initial size of the buffer mesh vertices are pooled in, it grows as
+/// needed.
+const POOL_VERTEX_SIZE: u64 = 4 << 20;
+/// Initial size of the buffer mesh indices are pooled in.
+const POOL_INDEX_SIZE: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshId(u32);
+
+/// Index of a pipeline in the renderer that draws a mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineId(pub usize);
+
+/// Number of meshes drawn and culled in the last frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+/// Per-mesh data available to shaders at `@group(0) @binding(0)`.
+#[derive(Debug, Copy, Clone, Pod, Zeroable, ShaderUniform)]
+#[repr(C)]
+pub struct MeshUniform {
+    pub model: Mat4,
+    /// Value from 0 to 1 the chunk heatmap debug view colors the mesh by.
+    pub heat: f32,
+    #[padding]
+    pub _padding: [u32; 3],
+}
+
+pub const MESH_BIND_GROUP_LAYOUT: BindGroupLayoutDescriptor<'static> = BindGroupLayoutDescriptor {
+    label: Some("mesh_bind_group_layout"),
+    entries: &[BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::VERTEX,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }],
+};
+
+/// Indexed geometry made of `D` vertices.
+#[derive(Debug, Clone)]
+pub struct Mesh<D: VertexData<'static>> {
+    pub vertices: VertexBuffer<'static, D>,
+    pub indices: IndexList,
+}
+
+impl<D: VertexData<'static>> Mesh<D> {
+    pub fn new(vertices: VertexBuffer<'static, D>, indices: IndexList) -> Self {
+        Mesh { vertices, indices }
+    }
+}
+
+/// Mesh with its vertex type erased so meshes of different vertex types can
+/// be stored together.
+pub trait MeshData: Debug {
+    fn layout(&self) -> VertexBufferLayout<'static>;
+    fn vertex_count(&self) -> usize;
+    fn index_count(&self) -> usize;
+
+    fn vertex_bytes(&self) -> &[u8];
+    fn index_bytes(&self) -> &[u8];
+}
+
+impl<D: VertexData<'static> + Debug> MeshData for Mesh<D> {
+    fn layout(&self) -> VertexBufferLayout<'static> {
+        D::LAYOUT
+    }
+
+    fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    fn index_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn vertex_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.vertices)
+    }
+
+    fn index_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.indices)
+    }
+}
+
+/// Vertex buffer with its data type erased, used for per-instance data.
+pub trait VertexSource: Debug {
+    fn layout(&self) -> VertexBufferLayout<'static>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn bytes(&self) -> &[u8];
+    fn create_buffer(&self, device: &Device) -> Buffer;
+}
+
+impl<D: VertexData<'static> + Debug> VertexSource for VertexBuffer<'static, D> {
+    fn layout(&self) -> VertexBufferLayout<'static> {
+        D::LAYOUT
+    }
+
+    fn len(&self) -> usize {
+        <[D]>::len(self)
+    }
+
+    fn bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(self)
+    }
+
+    fn create_buffer(&self, device: &Device) -> Buffer {
+        self.create_init_wgpu_buff(device)
+    }
+}
+
+#[derive(Debug)]
+struct GpuMesh {
+    /// Geometry in the [`MeshPool`] of the manager.
+    mesh: PooledMesh,
+    instance_buffer: Option<Buffer>,
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+#[derive(Debug)]
+struct MeshEntry {
+    data: Box<dyn MeshData>,
+    pipeline: PipelineId,
+    transform: Mat4,
+    heat: f32,
+    visible: bool,
+    /// Drawn once per instance when set.
+    instances: Option<Box<dyn VertexSource>>,
+    /// Bounds in model space, meshes without bounds are never culled.
+    bounds: Option<AABB<f32, Vec3>>,
+    culled: bool,
+
+    gpu: Option<GpuMesh>,
+    uniform_changed: bool,
+    instances_changed: bool,
+}
+
+/// Stores meshes drawn by the renderer and their GPU buffers.
+///
+/// Geometry of all meshes shares the buffers of a [`MeshPool`], it's created
+/// once meshes are first uploaded.
+#[derive(Debug, Default)]
+pub struct MeshManager {
+    meshes: Vec<Option<MeshEntry>>,
+    free: Vec<u32>,
+    pool: Option<MeshPool>,
+}
+
+impl MeshManager {
+    pub fn new() -> Self {
+        MeshManager::default()
+    }
+
+    /// Adds a mesh drawn with `pipeline`, its vertex type must match the
+    /// pipeline vertex layout.
+    pub fn insert<D: VertexData<'static> + Debug + 'static>(
+        &mut self,
+        mesh: Mesh<D>,
+        pipeline: PipelineId,
+    ) -> MeshId {
+        let entry = MeshEntry {
+            data: Box::new(mesh),
+            pipeline,
+            transform: Mat4::IDENTITY,
+            heat: 0.0,
+            visible: true,
+            instances: None,
+            bounds: None,
+            culled: false,
+            gpu: None,
+            uniform_changed: false,
+            instances_changed: false,
+        };
+
+        match self.free.pop() {
+            Some(i) => {
+                self.meshes[i as usize] = Some(entry);
+                MeshId(i)
+            }
+            None => {
+                self.meshes.push(Some(entry));
+                MeshId(self.meshes.len() as u32 - 1)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: MeshId) -> bool {
+        match self.meshes.get_mut(id.0 as usize).and_then(Option::take) {
+            Some(entry) => {
+                Self::release(&mut self.pool, entry.gpu);
+                self.free.push(id.0);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Frees pooled geometry of a mesh.
+    fn release(pool: &mut Option<MeshPool>, gpu: Option<GpuMesh>) {
+        if let (Some(pool), Some(gpu)) = (pool, gpu) {
+            pool.remove(gpu.mesh);
+        }
+    }
+
+    /// Replaces geometry of a mesh, it's uploaded again before the next
+    /// frame.
+    pub fn set_mesh<D: VertexData<'static> + Debug + 'static>(
+        &mut self,
+        id: MeshId,
+        mesh: Mesh<D>,
+    ) {
+        if let Some(entry) = self.meshes.get_mut(id.0 as usize).and_then(Option::as_mut) {
+            entry.data = Box::new(mesh);
+            Self::release(&mut self.pool, entry.gpu.take());
+        }
+    }
+
+    /// Sets per-instance data of a mesh, it's bound to vertex buffer slot 1
+    /// and the mesh is drawn once for every instance.
+    pub fn set_instances<I: VertexData<'static> + Debug + 'static>(
+        &mut self,
+        id: MeshId,
+        instances: VertexBuffer<'static, I>,
+    ) {
+        debug_assert!(
+            VertexBuffer::<I>::is_instanced(),
+            "instance data must use VertexStepMode::Instance"
+        );
+        if let Some(entry) = self.entry_mut(id) {
+            entry.instances = Some(Box::new(instances));
+            entry.instances_changed = true;
+        }
+    }
+
+    pub fn clear_instances(&mut self, id: MeshId) {
+        if let Some(entry) = self.entry_mut(id) {
+            entry.instances = None;
+            entry.instances_changed = true;
+        }
+    }
+
+    pub fn transform(&self, id: MeshId) -> Option<Mat4> {
+        self.entry(id).map(|it| it.transform)
+    }
+
+    pub fn set_transform(&mut self, id: MeshId, transform: Mat4) {
+        if let Some(entry) = self.entry_mut(id) {
+            entry.transform = transform;
+            entry.uniform_changed = true;
+        }
+    }
+
+    /// Sets the value from 0 to 1 [`DebugView::ChunkHeatmap`] colors the mesh
+    /// by.
+    ///
+    /// [`DebugView::ChunkHeatmap`]: super::debug::DebugView::ChunkHeatmap
+    pub fn set_heat(&mut self, id: MeshId, heat: f32) {
+        if let Some(entry) = self.entry_mut(id) {
+            entry.heat = heat;
+            entry.uniform_changed = true;
+        }
+    }
+
+    /// Number of indices in the geometry of a mesh.
+    pub fn index_count(&self, id: MeshId) -> Option<usize> {
+        self.entry(id).map(|it| it.data.index_count())
+    }
+
+    pub fn bounds(&self, id: MeshId) -> Option<AABB<f32, Vec3>> {
+        self.entry(id).and_then(|it| it.bounds)
+    }
+
+    /// Sets model space bounds used to cull the mesh.
+    pub fn set_bounds(&mut self, id: MeshId, bounds: Option<AABB<f32, Vec3>>) {
+        if let Some(entry) = self.entry_mut(id) {
+            entry.bounds = bounds;
+        }
+    }
+
+    /// Marks visible meshes outside of `frustum` as culled so they're skipped
+    /// when drawing.
+    pub fn cull(&mut self, frustum: &Frustum) -> CullStats {
+        let mut stats = CullStats::default();
+        for entry in self.meshes.iter_mut().flatten() {
+            entry.culled = match entry.bounds {
+                Some(bounds) if entry.visible => !bounds
+                    .transformed(entry.transform)
+                    .intersects_frustum(frustum),
+                _ => false,
+            };
+
+            if !entry.visible {
+                continue;
+            }
+            if entry.culled {
+                stats.culled += 1;
+            } else {
+                stats.drawn += 1;
+            }
+        }
+        stats
+    }
+
+    pub fn is_visible(&self, id: MeshId) -> bool {
+        self.entry(id).map(|it| it.visible).unwrap_or_default()
+    }
+
+    pub fn set_visible(&mut self, id: MeshId, visible: bool) {
+        if let Some(entry) = self.entry_mut(id) {
+            entry.visible = visible;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.meshes.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = MeshId> + '_ {
+        self.meshes
+            .iter()
+            .enumerate()
+            .filter(|(_, it)| it.is_some())
+            .map(|(i, _)| MeshId(i as u32))
+    }
+
+    fn entry(&self, id: MeshId) -> Option<&MeshEntry> {
+        self.meshes.get(id.0 as usize)?.as_ref()
+    }
+
+    fn entry_mut(&mut self, id: MeshId) -> Option<&mut MeshEntry> {
+        self.meshes.get_mut(id.0 as usize)?.as_mut()
+    }
+
+    /// Uploads new meshes, changed transforms and heat.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, layout: &BindGroupLayout) {
+        let pool = self
+            .pool
+            .get_or_insert_with(|| MeshPool::new(device, POOL_VERTEX_SIZE, POOL_INDEX_SIZE));
+        for entry in self.meshes.iter_mut().flatten() {
+            let uniform = MeshUniform {
+                model: entry.transform,
+                heat: entry.heat,
+                _padding: [0; 3],
+            };
+
+            match &mut entry.gpu {
+                Some(gpu) => {
+                    if entry.uniform_changed {
+                        queue.write_buffer(&gpu.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+                    }
+                    if entry.instances_changed {
+                        gpu.instance_buffer =
+                            entry.instances.as_ref().map(|it| it.create_buffer(device));
+                    }
+                }
+                None => {
+                    let uniform_buffer = uniform.create_init_wgpu_buff(device);
+                    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                        label: Some("mesh_bind_group"),
+                        layout,
+                        entries: &[BindGroupEntry {
+                            binding: 0,
+                            resource: uniform_buffer.as_entire_binding(),
+                        }],
+                    });
+
+                    entry.gpu = Some(GpuMesh {
+                        mesh: pool.insert(device, queue, entry.data.as_ref()),
+                        instance_buffer: entry
+                            .instances
+                            .as_ref()
+                            .map(|it| it.create_buffer(device)),
+                        uniform_buffer,
+                        bind_group,
+                    });
+                }
+            }
+            entry.uniform_changed = false;
+            entry.instances_changed = false;
+        }
+    }
+
+    /// Bounds and draw arguments of every mesh slot for GPU culling, indexed
+    /// the same way as draws in [`Self::draw_indirect`].
+    pub fn cull_data(&self, instances: u32) -> (Vec<MeshBounds>, Vec<DrawArgs>) {
+        self.meshes
+            .iter()
+            .map(|entry| {
+                let entry = match entry {
+                    Some(it) if it.visible && !it.culled => it,
+                    _ => return (MeshBounds::default(), DrawArgs::default()),
+                };
+
+                let bounds = match entry.bounds {
+                    Some(bounds) => {
+                        let world = bounds.transformed(entry.transform);
+                        MeshBounds {
+                            start: world.start().extend(1.0).to_array(),
+                            end: world.end().extend(1.0).to_array(),
+                        }
+                    }
+                    None => MeshBounds::default(),
+                };
+                let draw = DrawArgs {
+                    index_count: entry.data.index_count() as u32,
+                    instance_count: entry.instance_count(instances),
+                    ..Default::default()
+                };
+                (bounds, draw)
+            })
+            .unzip()
+    }
+
+    fn visible(&self, pipeline: PipelineId) -> impl Iterator<Item = (usize, &MeshEntry, &GpuMesh)> {
+        self.meshes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, it)| Some((i, it.as_ref()?)))
+            .filter(move |(_, it)| it.visible && !it.culled && it.pipeline == pipeline)
+            .filter_map(|(i, it)| Some((i, it, it.gpu.as_ref()?)))
+    }
+
+    /// Binds a mesh and its pooled geometry, returns `false` if there's
+    /// nothing to draw.
+    fn bind<'a>(&'a self, pass: &mut RenderPass<'a>, gpu: &'a GpuMesh) -> bool {
+        match &self.pool {
+            Some(pool) if pool.bind(pass, gpu.mesh) => {}
+            _ => return false,
+        }
+        pass.set_bind_group(0, &gpu.bind_group, &[]);
+        if let Some(buffer) = &gpu.instance_buffer {
+            pass.set_vertex_buffer(1, buffer.slice(..));
+        }
+        true
+    }
+
+    /// Records indexed draws of visible meshes that use `pipeline`, meshes
+    /// without instance data are drawn `instances` times.
+    pub fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, pipeline: PipelineId, instances: u32) {
+        for (_, entry, gpu) in self.visible(pipeline) {
+            if self.bind(pass, gpu) {
+                pass.draw_indexed(
+                    0..gpu.mesh.index_count(),
+                    0,
+                    0..entry.instance_count(instances),
+                );
+            }
+        }
+    }
+
+    /// Records indexed draws of visible meshes that use `pipeline` and
+    /// intersect `frustum`, ignoring culling against the camera.
+    pub fn draw_in<'a>(
+        &'a self,
+        pass: &mut RenderPass<'a>,
+        pipeline: PipelineId,
+        instances: u32,
+        frustum: &Frustum,
+    ) {
+        let entries = self
+            .meshes
+            .iter()
+            .flatten()
+            .filter(|it| it.visible && it.pipeline == pipeline)
+            .filter(|it| match it.bounds {
+                Some(bounds) => bounds.transformed(it.transform).intersects_frustum(frustum),
+                None => true,
+            });
+        for entry in entries {
+            match &entry.gpu {
+                Some(gpu) if self.bind(pass, gpu) => pass.draw_indexed(
+                    0..gpu.mesh.index_count(),
+                    0,
+                    0..entry.instance_count(instances),
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    /// Records indexed draws of visible meshes that use `pipeline` ordered
+    /// from the furthest to the closest to `eye`, meshes are compared by the
+    /// center of their bounds or their origin.
+    pub fn draw_sorted<'a>(
+        &'a self,
+        pass: &mut RenderPass<'a>,
+        pipeline: PipelineId,
+        instances: u32,
+        eye: Vec3,
+    ) {
+        let mut entries: Vec<_> = self
+            .visible(pipeline)
+            .map(|(_, entry, gpu)| {
+                let center = match entry.bounds {
+                    Some(bounds) => entry.transform.transform_point3(bounds.center()),
+                    None => entry.transform.transform_point3(Vec3::ZERO),
+                };
+                (center.distance_squared(eye), entry, gpu)
+            })
+            .collect();
+        entries.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        for (_, entry, gpu) in entries {
+            if self.bind(pass, gpu) {
+                pass.draw_indexed(
+                    0..gpu.mesh.index_count(),
+                    0,
+                    0..entry.instance_count(instances),
+                );
+            }
+        }
+    }
+
+    /// Records draws of visible meshes that use `pipeline` with arguments
+    /// from `draws` written by GPU culling.
+    pub fn draw_indirect<'a>(
+        &'a self,
+        pass: &mut RenderPass<'a>,
+        pipeline: PipelineId,
+        draws: &'a Buffer,
+    ) {
+        for (i, _, gpu) in self.visible(pipeline) {
+            if self.bind(pass, gpu) {
+                pass.draw_indexed_indirect(draws, i as BufferAddress * DrawArgs::SIZE);
+            }
+        }
+    }
+}
+
+impl MeshEntry {
+    fn instance_count(&self, default: u32) -> u32 {
+        match &self.instances {
+            Some(data) => data.len() as u32,
+            None => default,
+        }
+    }
+}
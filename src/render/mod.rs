@@ -1,24 +1,74 @@
+pub mod camera;
+pub mod chunk;
+pub mod config;
+pub mod debug;
+pub mod font;
+pub mod graph;
+pub mod headless;
+pub mod mesh;
+pub mod occlusion;
 pub mod pipeline;
+pub mod pool;
+pub mod post;
 pub mod shader;
+pub mod shadow;
+pub mod sky;
 pub mod texture;
+pub mod ui;
+pub mod uniform;
 pub mod vertex;
 
+use glam::{Vec3, Vec4};
 use wgpu::*;
-use winit::{event::WindowEvent, window::Window};
-
-use crate::render::{
-    vertex::{DevVertexData, StaticVertexBuffer},
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
 };
 
-use vertex::VertexBuffer;
+use crate::{
+    math::AABB,
+    render::vertex::{DevVertexData, StaticVertexBuffer},
+};
 
 use self::{
-    texture::MENU_ICONS,
-    vertex::{Index, IndexList, VertexData}, pipeline::{Pipeline, VertexInterface, FragmentInterface}, shader::{WgslSource, DEV_SHADER},
+    camera::{Camera, CameraUniform, CAMERA_BIND_GROUP_LAYOUT},
+    config::{ConfigChanges, StateConfig},
+    debug::{DebugLineRenderer, DebugLines, DebugView},
+    mesh::{CullStats, Mesh, MeshManager, PipelineId, MESH_BIND_GROUP_LAYOUT},
+    occlusion::{CullingMode, OcclusionCuller},
+    post::{PostSettings, HDR, HDR_FORMAT},
+    shadow::{ShadowMaps, ShadowSettings, SHADOW_BIND_GROUP_LAYOUT, SHADOW_MAPS},
+    sky::{FogSettings, Sky, SkyUniform, SKY_BIND_GROUP_LAYOUT},
+    uniform::ShaderUniform,
+    ui::{UiAtlas, UiBatch, UiPass, UI_ATLAS_BIND_GROUP_LAYOUT},
+    vertex::{IndexList, VertexData}, pipeline::{Pipeline, VertexInterface, FragmentInterface}, shader::{WgslSource, preprocessor::VirtualFiles},
+    graph::{AttachmentDescriptor, Attachments, GraphPass, RenderGraph},
 };
 
-pub struct StateConfig {
-    present_mode: PresentMode,
+/// Depth buffer attachment of the main pass.
+pub const DEPTH: &str = "depth";
+/// Indirect draws of meshes that passed occlusion culling, written by
+/// [`CullPass`] and drawn by [`MainPass`].
+pub const DRAWS: &str = "draws";
+pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+/// Color attachment multisampled scene geometry is drawn into, it's resolved
+/// into [`HDR`] by every pass drawing into it.
+pub const HDR_MULTISAMPLED: &str = "hdr_multisampled";
+
+/// Sample counts the scene can be drawn with on `adapter`, always including 1.
+///
+/// Formats that can be multisampled are only guaranteed to support 4 samples
+/// and wgpu can't query which other counts an adapter supports.
+pub fn supported_sample_counts(adapter: &Adapter) -> Vec<u32> {
+    let color = adapter.get_texture_format_features(HDR_FORMAT).flags;
+    let depth = adapter.get_texture_format_features(DEPTH_FORMAT).flags;
+    let resolvable =
+        TextureFormatFeatureFlags::MULTISAMPLE | TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE;
+    if color.contains(resolvable) && depth.contains(TextureFormatFeatureFlags::MULTISAMPLE) {
+        vec![1, 4]
+    } else {
+        vec![1]
+    }
 }
 
 pub static DEV_VERTICES: StaticVertexBuffer<DevVertexData> = StaticVertexBuffer(&[
@@ -42,12 +92,16 @@ pub struct RendererState {
     pub queue: Queue,
     pub surface_config: SurfaceConfiguration,
     pub size: winit::dpi::PhysicalSize<u32>,
+    config: StateConfig,
+    present_modes: Vec<PresentMode>,
+    sample_counts: Vec<u32>,
 
     pub renderer: Renderer<'static>,
+    pub graph: RenderGraph<Renderer<'static>>,
 }
 
 impl RendererState {
-    pub async fn new(window: &Window) -> Self {
+    pub async fn new(window: &Window, config: StateConfig) -> Self {
         let size = {
             let mut w = window.inner_size();
             w.width = w.width.max(1);
@@ -56,12 +110,11 @@ impl RendererState {
         };
 
         // The instance is a handle to our GPU
-        // Backends::all => Vulkan + Metal + DX12 + Browser WebGPU
-        let instance = Instance::new(Backends::all());
+        let instance = Instance::new(config.backend.backends());
         let surface = unsafe { instance.create_surface(window) };
         let adapter = instance
             .request_adapter(&RequestAdapterOptions {
-                power_preference: PowerPreference::default(),
+                power_preference: config.power_preference.into(),
                 compatible_surface: Some(&surface),
                 force_fallback_adapter: false,
             })
@@ -71,7 +124,7 @@ impl RendererState {
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
-                    features: Features::empty(),
+                    features: adapter.features() & config.features.requested(),
                     // WebGL doesn't support all of wgpu's features, so if
                     // we're building for the web we'll have to disable some.
                     limits: if cfg!(target_arch = "wasm32") {
@@ -86,47 +139,92 @@ impl RendererState {
             .await
             .unwrap();
 
+        // post-processing writes linear colors, so sRGB surfaces are preferred
+        let formats = surface.get_supported_formats(&adapter);
+        let format = formats
+            .iter()
+            .copied()
+            .find(|it| it.describe().srgb)
+            .unwrap_or(formats[0]);
+        let present_modes = surface.get_supported_present_modes(&adapter);
+        let sample_counts = supported_sample_counts(&adapter);
         let surface_config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_supported_formats(&adapter)[0],
+            format,
             width: size.width,
             height: size.height,
-            present_mode: PresentMode::Fifo,
+            present_mode: config.vsync.present_mode(&present_modes),
             alpha_mode: CompositeAlphaMode::Auto,
         };
         surface.configure(&device, &surface_config);
 
-        let pipeline = Pipeline::new(
-            DEV_SHADER.clone(),
-            VertexInterface {
-                entry_point: "vs_main".to_string(),
-                buffers: vec![DevVertexData::LAYOUT],
-            },
-            Some(FragmentInterface {
-                entry_point: "fs_main".to_string(),
-                targets: vec![Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            })
-        );
-
-        let frame_state = Renderer::new(
-            pipeline,
-            DEV_VERTICES.into(),
-            IndexList::from(&[0, 1, 4, 1, 2, 4, 2, 3, 4]),
-            1,
+        let mut renderer = Renderer::dev(HDR_FORMAT);
+        renderer.culling = CullingMode::supported(&adapter);
+        renderer.features = device.features();
+        renderer.sample_count = pick_sample_count(&config, &sample_counts);
+        renderer.resize(&device, size.width, size.height);
+        let mut graph = Renderer::graph(
+            surface_config.format,
+            &renderer.post,
+            &renderer.shadows,
+            renderer.sample_count,
         );
+        graph.resize(&device, size.width, size.height);
 
-        RendererState {
+        let mut state = RendererState {
             surface,
             device,
             queue,
             surface_config,
             size,
-            renderer: frame_state,
+            config,
+            present_modes,
+            sample_counts,
+            renderer,
+            graph,
+        };
+        state.apply_view_distance();
+        state
+    }
+
+    pub fn config(&self) -> &StateConfig {
+        &self.config
+    }
+
+    /// Switches to `config`, reconfiguring the surface and pipelines the
+    /// changed settings affect. Settings in [`ConfigChanges::restart`] only
+    /// take effect once the state is created again.
+    pub fn set_config(&mut self, config: StateConfig) -> ConfigChanges {
+        let changes = self.config.changes(&config);
+        self.config = config;
+
+        if changes.surface {
+            self.surface_config.present_mode = self.config.vsync.present_mode(&self.present_modes);
+            self.surface.configure(&self.device, &self.surface_config);
+        }
+        if changes.pipelines {
+            self.renderer.sample_count = pick_sample_count(&self.config, &self.sample_counts);
+            self.renderer.invalidate();
+            self.graph = Renderer::graph(
+                self.surface_config.format,
+                &self.renderer.post,
+                &self.renderer.shadows,
+                self.renderer.sample_count,
+            );
+            self.graph
+                .resize(&self.device, self.size.width, self.size.height);
         }
+        if changes.view {
+            self.apply_view_distance();
+        }
+        if changes.restart {
+            tracing::info!("backend, power preference and feature changes apply after a restart");
+        }
+        changes
+    }
+
+    fn apply_view_distance(&mut self) {
+        self.renderer.sky.fog = FogSettings::for_view_distance(self.config.view_distance);
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -135,11 +233,31 @@ impl RendererState {
             self.surface_config.width = new_size.width;
             self.surface_config.height = new_size.height;
             self.surface.configure(&self.device, &self.surface_config);
+            self.graph
+                .resize(&self.device, new_size.width, new_size.height);
+            self.renderer
+                .resize(&self.device, new_size.width, new_size.height);
         }
     }
 
     pub fn input(&mut self, event: &WindowEvent) -> bool {
-        false
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F3),
+                        ..
+                    },
+                ..
+            } => {
+                let view = self.renderer.debug_view.next(self.renderer.features);
+                tracing::info!("debug view: {}", view.name());
+                self.renderer.debug_view = view;
+                true
+            }
+            _ => false,
+        }
     }
 
     pub fn update(&mut self) {}
@@ -148,8 +266,10 @@ impl RendererState {
         let output = self.surface.get_current_texture()?;
 
         if !self.renderer.is_configured() {
-            self.renderer.configure(&self);
+            self.renderer.configure(&self.device, &self.queue);
         }
+        self.renderer.reload_shaders(&self.device);
+        self.renderer.prepare(&self.device, &self.queue);
 
         let out_view = output
             .texture
@@ -161,7 +281,8 @@ impl RendererState {
                 label: Some("Render Encoder"),
             });
 
-        self.renderer.draw(&mut encoder, &out_view);
+        self.graph
+            .execute(&self.device, &self.queue, &mut self.renderer, &mut encoder, &out_view);
 
         // submit will accept anything that implements IntoIter
         self.queue.submit(std::iter::once(encoder.finish()));
@@ -171,146 +292,876 @@ impl RendererState {
     }
 }
 
+/// Sample count of `config` the adapter supports, logs if it's lower than
+/// the configured one.
+fn pick_sample_count(config: &StateConfig, supported: &[u32]) -> u32 {
+    let sample_count = config.sample_count(supported);
+    if sample_count != config.msaa_samples {
+        tracing::warn!(
+            "{} samples per pixel aren't supported, using {}",
+            config.msaa_samples,
+            sample_count
+        );
+    }
+    sample_count
+}
+
 #[derive(Debug)]
 pub struct Renderer<'v> {
-    pub pipeline: Pipeline<'v, WgslSource<'static>>,
-    pub vertices: VertexBuffer<'static, DevVertexData>,
-    pub index_list: IndexList,
+    pub pipelines: Vec<Pipeline<'v, WgslSource<'static>>>,
+    pub meshes: MeshManager,
     pub instance_count: u32,
+    pub camera: Camera,
+    /// Takes effect on the next [`Renderer::configure`].
+    pub culling: CullingMode,
+    /// Meshes drawn and frustum culled in the last frame, meshes hidden by
+    /// occlusion culling are counted as drawn.
+    pub cull_stats: CullStats,
+    /// Time of day, sun and fog, the sun or moon cast shadows.
+    pub sky: Sky,
+    /// Cascade count and resolution take effect on the next
+    /// [`Renderer::configure`].
+    pub shadows: ShadowSettings,
+    /// Effects applied to the rendered image.
+    pub post: PostSettings,
+    /// Pipelines without a variant for the view are drawn shaded.
+    pub debug_view: DebugView,
+    /// Lines drawn over the next frame, they're cleared once uploaded.
+    pub debug_lines: DebugLines,
+    /// Screen space UI drawn over the next frame, it's cleared once
+    /// uploaded.
+    pub ui: UiBatch,
+    /// Features of the device, [`DebugView::Wireframe`] takes effect on the
+    /// next [`Renderer::configure`].
+    pub features: Features,
+    /// Samples per pixel scene geometry is drawn with, takes effect on the
+    /// next [`Renderer::configure`]. The graph has to be created with the
+    /// same count.
+    pub sample_count: u32,
+    size: (u32, u32),
+
+    render_pipelines: Vec<RenderPipeline>,
+    /// Pipelines drawn blended after opaque geometry.
+    translucent_pipelines: Vec<PipelineId>,
+    /// Line variants of every render pipeline, empty if the device can't
+    /// draw them.
+    wireframe_pipelines: Vec<RenderPipeline>,
+    debug_pipelines: Vec<(PipelineId, DebugView, Pipeline<'v, WgslSource<'static>>)>,
+    debug_render_pipelines: Vec<RenderPipeline>,
+    line_renderer: DebugLineRenderer,
+    mesh_bind_group_layout: Option<BindGroupLayout>,
+    camera_bind_group_layout: Option<BindGroupLayout>,
+    camera_buffer: Option<Buffer>,
+    camera_bind_group: Option<BindGroup>,
+    occlusion: Option<OcclusionCuller>,
+
+    shadow_casters: Vec<(PipelineId, Pipeline<'v, WgslSource<'static>>)>,
+    shadow_pipelines: Vec<(PipelineId, RenderPipeline)>,
+    shadow_bind_group_layout: Option<BindGroupLayout>,
+    shadow_maps: Option<ShadowMaps>,
+
+    sky_pipeline: Pipeline<'v, WgslSource<'static>>,
+    sky_render_pipeline: Option<RenderPipeline>,
+    sky_bind_group_layout: Option<BindGroupLayout>,
+    sky_buffer: Option<Buffer>,
+    sky_bind_group: Option<BindGroup>,
+
+    /// Icons and font glyphs sampled by [`UiPass`].
+    ui_atlas_bind_group: Option<BindGroup>,
+}
+
+/// Color attachment scene passes draw into and the one it's resolved into,
+/// if it's multisampled.
+fn scene_target<'a>(
+    attachments: &'a Attachments<'_>,
+    multisampled: bool,
+) -> (&'a TextureView, Option<&'a TextureView>) {
+    let hdr = attachments.view(HDR).unwrap();
+    if multisampled {
+        (attachments.view(HDR_MULTISAMPLED).unwrap(), Some(hdr))
+    } else {
+        (hdr, None)
+    }
+}
+
+/// Culls meshes against the depth pyramid of the previous frame, leaves
+/// [`DRAWS`] untouched if occlusion culling isn't supported.
+pub struct CullPass;
+
+impl GraphPass<Renderer<'static>> for CullPass {
+    fn writes(&self) -> &[&'static str] {
+        &[DRAWS]
+    }
+
+    fn execute(
+        &mut self,
+        renderer: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        _: &Attachments,
+    ) {
+        if let Some(occlusion) = &renderer.occlusion {
+            occlusion.cull(encoder);
+        }
+    }
+}
+
+/// Renders shadow casters into [`SHADOW_MAPS`], a layer per cascade.
+pub struct ShadowPass;
+
+impl GraphPass<Renderer<'static>> for ShadowPass {
+    fn writes(&self) -> &[&'static str] {
+        &[SHADOW_MAPS]
+    }
+
+    fn execute(
+        &mut self,
+        renderer: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        attachments: &Attachments,
+    ) {
+        renderer.draw_shadows(encoder, attachments);
+    }
+}
+
+/// Draws renderer geometry into the HDR attachment.
+pub struct MainPass {
+    /// Draws into [`HDR_MULTISAMPLED`] and resolves it into [`HDR`].
+    pub multisampled: bool,
+}
+
+impl GraphPass<Renderer<'static>> for MainPass {
+    fn reads(&self) -> &[&'static str] {
+        &[SHADOW_MAPS, DRAWS]
+    }
+
+    fn writes(&self) -> &[&'static str] {
+        if self.multisampled {
+            &[HDR, HDR_MULTISAMPLED, DEPTH]
+        } else {
+            &[HDR, DEPTH]
+        }
+    }
+
+    fn prepare(
+        &mut self,
+        device: &Device,
+        _: &Queue,
+        renderer: &mut Renderer<'static>,
+        attachments: &Attachments,
+    ) {
+        renderer.bind_shadow_maps(device, attachments.view(SHADOW_MAPS).unwrap());
+    }
+
+    fn execute(
+        &mut self,
+        renderer: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        attachments: &Attachments,
+    ) {
+        let (output, resolve) = scene_target(attachments, self.multisampled);
+        renderer.draw(encoder, output, resolve, attachments.view(DEPTH).unwrap());
+    }
+}
+
+/// Blends translucent geometry over the output of [`MainPass`].
+pub struct TranslucentPass {
+    pub multisampled: bool,
+}
 
-    menu_icons: texture::TextureResource,
+impl GraphPass<Renderer<'static>> for TranslucentPass {
+    fn reads(&self) -> &[&'static str] {
+        &[DEPTH, SHADOW_MAPS]
+    }
+
+    fn writes(&self) -> &[&'static str] {
+        if self.multisampled {
+            &[HDR, HDR_MULTISAMPLED]
+        } else {
+            &[HDR]
+        }
+    }
 
-    vertex_buffer: Option<Buffer>,
-    index_buffer: Option<Buffer>,
-    render_pipeline: Option<RenderPipeline>,
+    fn execute(
+        &mut self,
+        renderer: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        attachments: &Attachments,
+    ) {
+        let (output, resolve) = scene_target(attachments, self.multisampled);
+        renderer.draw_translucent(encoder, output, resolve, attachments.view(DEPTH).unwrap());
+    }
+}
 
-    menu_icon_bind_group: Option<BindGroup>,
+/// Draws [`Renderer::debug_lines`] over the scene.
+pub struct DebugPass {
+    pub multisampled: bool,
+}
+
+impl GraphPass<Renderer<'static>> for DebugPass {
+    fn reads(&self) -> &[&'static str] {
+        &[DEPTH]
+    }
+
+    fn writes(&self) -> &[&'static str] {
+        if self.multisampled {
+            &[HDR, HDR_MULTISAMPLED]
+        } else {
+            &[HDR]
+        }
+    }
+
+    fn execute(
+        &mut self,
+        renderer: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        attachments: &Attachments,
+    ) {
+        let (output, resolve) = scene_target(attachments, self.multisampled);
+        renderer.draw_debug(encoder, output, resolve, attachments.view(DEPTH).unwrap());
+    }
+}
+
+/// Builds the depth pyramid meshes are occlusion culled with in the next
+/// frame.
+pub struct HiZPass;
+
+impl GraphPass<Renderer<'static>> for HiZPass {
+    fn reads(&self) -> &[&'static str] {
+        &[DEPTH]
+    }
+
+    fn writes(&self) -> &[&'static str] {
+        &[]
+    }
+
+    fn prepare(
+        &mut self,
+        device: &Device,
+        _: &Queue,
+        renderer: &mut Renderer<'static>,
+        attachments: &Attachments,
+    ) {
+        if let Some(occlusion) = &mut renderer.occlusion {
+            occlusion.bind_depth(device, attachments.view(DEPTH).unwrap());
+        }
+    }
+
+    fn execute(
+        &mut self,
+        renderer: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        _: &Attachments,
+    ) {
+        if let Some(occlusion) = &mut renderer.occlusion {
+            occlusion.build_hi_z(encoder);
+        }
+    }
+}
+
+impl Renderer<'static> {
+    /// Creates the graph of passes frames are rendered with, scene geometry is
+    /// drawn into [`HDR`] and post-processed into the `format` surface,
+    /// [`Renderer::ui`] is drawn over it.
+    ///
+    /// Pipelines of the renderer have to target [`HDR_FORMAT`]. With more
+    /// than one sample per pixel geometry is drawn into [`HDR_MULTISAMPLED`]
+    /// and resolved into [`HDR`], `sample_count` has to match
+    /// [`Renderer::sample_count`]. Cascades are sized by `shadows`, the graph
+    /// has to be recreated when they change.
+    pub fn graph(
+        format: TextureFormat,
+        post: &PostSettings,
+        shadows: &ShadowSettings,
+        sample_count: u32,
+    ) -> RenderGraph<Self> {
+        let multisampled = sample_count > 1;
+        let mut graph = RenderGraph::new()
+            .with_attachment(
+                DEPTH,
+                AttachmentDescriptor::new(DEPTH_FORMAT).with_sample_count(sample_count),
+            )
+            .with_attachment(HDR, AttachmentDescriptor::new(HDR_FORMAT))
+            .with_attachment(SHADOW_MAPS, shadows.attachment())
+            .with_buffer(DRAWS)
+            .with_pass("shadow", ShadowPass)
+            .with_pass("cull", CullPass)
+            .with_pass("main", MainPass { multisampled })
+            .with_pass("translucent", TranslucentPass { multisampled })
+            .with_pass("debug", DebugPass { multisampled })
+            .with_pass("hi_z", HiZPass);
+        if multisampled {
+            // only ever resolved, never sampled
+            graph.add_attachment(
+                HDR_MULTISAMPLED,
+                AttachmentDescriptor {
+                    usage: TextureUsages::RENDER_ATTACHMENT,
+                    ..AttachmentDescriptor::new(HDR_FORMAT).with_sample_count(sample_count)
+                },
+            );
+        }
+        post::add_post_passes(&mut graph, post, format);
+        // writes the surface after post-processing is done with it
+        graph.add_pass("ui", UiPass::new(format));
+        graph
+    }
+
+    /// Creates a renderer drawing development geometry into `format` targets.
+    pub fn dev(format: TextureFormat) -> Self {
+        let shader_files = VirtualFiles::new()
+            .with("dev_vertex.wgsl", DevVertexData::WGSL_STRUCT)
+            .with("common.wgsl", shader::COMMON_WGSL);
+        let shader = shader::load_source("shader.wgsl", &shader::DEV_SHADER, shader_files)
+            .expect("invalid dev shader");
+
+        let pipeline = Pipeline::new(
+            shader,
+            VertexInterface {
+                entry_point: "vs_main".to_string(),
+                buffers: vec![DevVertexData::LAYOUT],
+            },
+            Some(FragmentInterface {
+                entry_point: "fs_main".to_string(),
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            })
+        );
+        pipeline.validate().expect("invalid dev shader");
+
+        let mut renderer = Renderer::new(1);
+        let pipeline = renderer.add_pipeline(pipeline);
+        let mesh = renderer.meshes.insert(
+            Mesh::new(DEV_VERTICES.into(), IndexList::from(&[0, 1, 2])),
+            pipeline,
+        );
+        renderer.meshes.set_bounds(
+            mesh,
+            AABB::from_points(DEV_VERTICES.0.iter().map(|it| Vec3::from(it.position))),
+        );
+        renderer
+    }
 }
 
 impl<'v> Renderer<'v> {
-    pub fn new(
-        pipeline: Pipeline<'v, WgslSource<'static>>,
-        vertices: VertexBuffer<'static, DevVertexData>,
-        index_list: IndexList,
-        instance_count: u32,
-    ) -> Self {
+    pub fn new(instance_count: u32) -> Self {
         Renderer {
-            pipeline,
-            vertices,
-            index_list,
+            pipelines: Vec::new(),
+            meshes: MeshManager::new(),
             instance_count,
+            camera: Camera::default(),
+            culling: CullingMode::Frustum,
+            cull_stats: CullStats::default(),
+            sky: Sky::default(),
+            shadows: ShadowSettings::default(),
+            post: PostSettings::default(),
+            debug_view: DebugView::default(),
+            debug_lines: DebugLines::new(),
+            ui: UiBatch::new(1, 1),
+            features: Features::empty(),
+            sample_count: 1,
+            size: (1, 1),
+
+            render_pipelines: Vec::new(),
+            translucent_pipelines: Vec::new(),
+            wireframe_pipelines: Vec::new(),
+            debug_pipelines: Vec::new(),
+            debug_render_pipelines: Vec::new(),
+            line_renderer: DebugLineRenderer::new(HDR_FORMAT),
+            mesh_bind_group_layout: None,
+            camera_bind_group_layout: None,
+            camera_buffer: None,
+            camera_bind_group: None,
+            occlusion: None,
 
-            menu_icons: texture::TextureResource::rgba8_from_memory(MENU_ICONS),
+            shadow_casters: Vec::new(),
+            shadow_pipelines: Vec::new(),
+            shadow_bind_group_layout: None,
+            shadow_maps: None,
 
-            vertex_buffer: None,
-            index_buffer: None,
-            render_pipeline: None,
-            menu_icon_bind_group: None,
+            sky_pipeline: sky::sky_pipeline(HDR_FORMAT),
+            sky_render_pipeline: None,
+            sky_bind_group_layout: None,
+            sky_buffer: None,
+            sky_bind_group: None,
+
+            ui_atlas_bind_group: None,
         }
     }
 
+    /// Adds a pipeline meshes can be drawn with, pipelines are drawn in the
+    /// order they were added.
+    ///
+    /// The main pass has a [`DEPTH_FORMAT`] depth buffer, pipelines without
+    /// depth state get the default depth test.
+    pub fn add_pipeline(&mut self, mut pipeline: Pipeline<'v, WgslSource<'static>>) -> PipelineId {
+        pipeline.depth_stencil.get_or_insert(DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::Less,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        });
+        self.pipelines.push(pipeline);
+        // render pipelines are created again on next configure
+        self.render_pipelines.clear();
+        PipelineId(self.pipelines.len() - 1)
+    }
+
+    /// Adds a pipeline drawn after all opaque pipelines, its meshes are
+    /// sorted back to front and depth tested without writing depth, so they
+    /// can be blended over what's behind them.
+    pub fn add_translucent_pipeline(
+        &mut self,
+        mut pipeline: Pipeline<'v, WgslSource<'static>>,
+    ) -> PipelineId {
+        pipeline.depth_stencil.get_or_insert(DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: CompareFunction::Less,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        });
+        let id = self.add_pipeline(pipeline);
+        self.translucent_pipelines.push(id);
+        id
+    }
+
+    /// Makes meshes drawn with `pipeline` cast shadows, they're drawn into
+    /// shadow maps with the depth-only `shadow` pipeline.
+    pub fn add_shadow_caster(
+        &mut self,
+        pipeline: PipelineId,
+        shadow: Pipeline<'v, WgslSource<'static>>,
+    ) {
+        self.shadow_casters.push((pipeline, shadow));
+        self.shadow_pipelines.clear();
+    }
+
+    /// Draws meshes of `pipeline` with `debug` while `view` is the
+    /// [`Renderer::debug_view`]. Without depth state it gets the one of
+    /// `pipeline`.
+    pub fn add_debug_pipeline(
+        &mut self,
+        pipeline: PipelineId,
+        view: DebugView,
+        mut debug: Pipeline<'v, WgslSource<'static>>,
+    ) {
+        if debug.depth_stencil.is_none() {
+            debug.depth_stencil = self.pipelines[pipeline.0].depth_stencil.clone();
+        }
+        self.debug_pipelines.push((pipeline, view, debug));
+        self.debug_render_pipelines.clear();
+    }
+
+    /// Makes [`Renderer::is_configured`] false, so render pipelines and
+    /// bindings are created again on the next [`Renderer::configure`].
+    pub fn invalidate(&mut self) {
+        self.render_pipelines.clear();
+    }
+
     pub fn is_configured(&self) -> bool {
-        self.vertex_buffer.is_some()
-            && self.index_buffer.is_some()
-            && self.render_pipeline.is_some()
+        self.camera_bind_group.is_some()
+            && self.render_pipelines.len() == self.pipelines.len()
+            && self.shadow_pipelines.len() == self.shadow_casters.len()
+            && self.debug_render_pipelines.len() == self.debug_pipelines.len()
     }
 
-    pub fn configure(&mut self, state: &RendererState) {
-        self.vertex_buffer = Some(self.vertices.create_init_wgpu_buff(&state.device));
-        self.index_buffer = Some(self.index_list.create_init_wgpu_buff(&state.device));
-        self.render_pipeline = Some(
-            self.pipeline
-                .create_render_pipeline(&state),
-        );
+    pub fn configure(&mut self, device: &Device, queue: &Queue) {
+        let mesh_layout = device.create_bind_group_layout(&MESH_BIND_GROUP_LAYOUT);
+        let camera_layout = device.create_bind_group_layout(&CAMERA_BIND_GROUP_LAYOUT);
+        let shadow_layout = device.create_bind_group_layout(&SHADOW_BIND_GROUP_LAYOUT);
+        let sky_layout = device.create_bind_group_layout(&SKY_BIND_GROUP_LAYOUT);
+        let layouts = [&mesh_layout, &camera_layout, &shadow_layout, &sky_layout];
+        let sample_count = self.sample_count;
+        self.render_pipelines = self
+            .pipelines
+            .iter_mut()
+            .map(|it| {
+                it.sample_count = sample_count;
+                it.create_render_pipeline(device, &layouts)
+            })
+            .collect();
+        self.wireframe_pipelines = if self.features.contains(Features::POLYGON_MODE_LINE) {
+            self.pipelines
+                .iter_mut()
+                .map(|it| create_wireframe_pipeline(it, device, &layouts))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        self.debug_render_pipelines = self
+            .debug_pipelines
+            .iter_mut()
+            .map(|(_, _, it)| {
+                it.sample_count = sample_count;
+                it.create_render_pipeline(device, &layouts)
+            })
+            .collect();
+        self.line_renderer
+            .configure(device, &camera_layout, sample_count);
 
-        let tex = self
-            .menu_icons
-            .create_texture_and_upload(&state.device, &state.queue);
-        let view = tex.create_view(&TextureViewDescriptor::default());
-        let sampler = state.device.create_sampler(&SamplerDescriptor {
-            address_mode_u: AddressMode::ClampToEdge,
-            address_mode_v: AddressMode::ClampToEdge,
-            address_mode_w: AddressMode::ClampToEdge,
-            mag_filter: FilterMode::Nearest,
-            min_filter: FilterMode::Nearest,
-            mipmap_filter: FilterMode::Nearest,
-            ..Default::default()
-        });
+        let depth_stencil = self.shadows.depth_stencil();
+        self.shadow_pipelines = self
+            .shadow_casters
+            .iter_mut()
+            .map(|(casts, pipeline)| {
+                pipeline.depth_stencil = Some(depth_stencil.clone());
+                (*casts, pipeline.create_render_pipeline(device, &[&mesh_layout, &camera_layout]))
+            })
+            .collect();
+        self.shadow_maps = Some(ShadowMaps::new(device, &self.shadows, &camera_layout));
 
-        let bind_group_layout =
-            state
-                .device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: Some("menu_icons_bind_group_layout"),
-                    entries: &[
-                        BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: ShaderStages::FRAGMENT,
-                            ty: BindingType::Texture {
-                                multisampled: false,
-                                view_dimension: TextureViewDimension::D2,
-                                sample_type: TextureSampleType::Float { filterable: true },
-                            },
-                            count: None,
-                        },
-                        BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: ShaderStages::FRAGMENT,
-                            // This should match the filterable field of the
-                            // corresponding Texture entry above.
-                            ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                    ],
-                });
-
-        let bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(&sampler),
+        let camera_buffer = CameraUniform::from(&self.camera).create_init_wgpu_buff(device);
+        self.camera_bind_group = Some(device.create_bind_group(&BindGroupDescriptor {
+            label: Some("camera_bind_group"),
+            layout: &camera_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        }));
+        self.camera_buffer = Some(camera_buffer);
+
+        let sky_buffer = SkyUniform::new(&self.sky, &self.camera).create_init_wgpu_buff(device);
+        self.sky_bind_group = Some(device.create_bind_group(&BindGroupDescriptor {
+            label: Some("sky_bind_group"),
+            layout: &sky_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: sky_buffer.as_entire_binding(),
+            }],
+        }));
+        self.sky_buffer = Some(sky_buffer);
+        self.sky_pipeline.sample_count = sample_count;
+        self.sky_render_pipeline =
+            Some(self.sky_pipeline.create_render_pipeline(device, &[&sky_layout]));
+
+        self.mesh_bind_group_layout = Some(mesh_layout);
+        self.camera_bind_group_layout = Some(camera_layout);
+        self.shadow_bind_group_layout = Some(shadow_layout);
+        self.sky_bind_group_layout = Some(sky_layout);
+
+        self.occlusion = match self.culling {
+            CullingMode::Occlusion => Some(OcclusionCuller::new(
+                device,
+                self.size.0,
+                self.size.1,
+                sample_count,
+            )),
+            CullingMode::Frustum => None,
+        };
+
+        let ui_layout = device.create_bind_group_layout(&UI_ATLAS_BIND_GROUP_LAYOUT);
+        self.ui_atlas_bind_group =
+            Some(UiAtlas::menu_icons().create_bind_group(device, queue, &ui_layout));
+    }
+
+    /// Recreates render pipelines whose shaders changed on disk.
+    pub fn reload_shaders(&mut self, device: &Device) {
+        let layouts = match (
+            &self.mesh_bind_group_layout,
+            &self.camera_bind_group_layout,
+            &self.shadow_bind_group_layout,
+            &self.sky_bind_group_layout,
+        ) {
+            (Some(mesh), Some(camera), Some(shadow), Some(sky)) => [mesh, camera, shadow, sky],
+            _ => return,
+        };
+
+        for (i, (pipeline, render_pipeline)) in self
+            .pipelines
+            .iter_mut()
+            .zip(self.render_pipelines.iter_mut())
+            .enumerate()
+        {
+            if pipeline.reload_shader() {
+                *render_pipeline = pipeline.create_render_pipeline(device, &layouts);
+                if let Some(wireframe) = self.wireframe_pipelines.get_mut(i) {
+                    *wireframe = create_wireframe_pipeline(pipeline, device, &layouts);
+                }
+            }
+        }
+        for ((_, _, pipeline), render_pipeline) in self
+            .debug_pipelines
+            .iter_mut()
+            .zip(self.debug_render_pipelines.iter_mut())
+        {
+            if pipeline.reload_shader() {
+                *render_pipeline = pipeline.create_render_pipeline(device, &layouts);
+            }
+        }
+        self.line_renderer.reload_shader(device, layouts[1]);
+        for ((_, pipeline), (_, render_pipeline)) in self
+            .shadow_casters
+            .iter_mut()
+            .zip(self.shadow_pipelines.iter_mut())
+        {
+            if pipeline.reload_shader() {
+                *render_pipeline = pipeline.create_render_pipeline(device, &layouts[..2]);
+            }
+        }
+        if self.sky_pipeline.reload_shader() {
+            self.sky_render_pipeline =
+                Some(self.sky_pipeline.create_render_pipeline(device, &layouts[3..]));
+        }
+    }
+
+    /// Uploads meshes, camera and sky that changed since the last frame,
+    /// culls meshes outside of the camera frustum and fits shadow cascades
+    /// to it. Debug lines are uploaded and cleared.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+        if let Some(layout) = &self.mesh_bind_group_layout {
+            self.meshes.prepare(device, queue, layout);
+        }
+        if let Some(buffer) = &self.camera_buffer {
+            let uniform = CameraUniform::from(&self.camera);
+            queue.write_buffer(buffer, 0, bytemuck::bytes_of(&uniform));
+        }
+        if let Some(buffer) = &self.sky_buffer {
+            let uniform = SkyUniform::new(&self.sky, &self.camera);
+            queue.write_buffer(buffer, 0, bytemuck::bytes_of(&uniform));
+        }
+        if let Some(shadow_maps) = &mut self.shadow_maps {
+            let light = self.sky.light_direction();
+            shadow_maps.prepare(queue, &self.camera, light, &self.shadows);
+        }
+
+        if self.debug_view == DebugView::ChunkBounds {
+            for id in self.meshes.ids() {
+                if let (true, Some(bounds), Some(transform)) = (
+                    self.meshes.is_visible(id),
+                    self.meshes.bounds(id),
+                    self.meshes.transform(id),
+                ) {
+                    self.debug_lines
+                        .aabb(&bounds.transformed(transform), Vec4::new(1.0, 0.9, 0.2, 1.0));
+                }
+            }
+        }
+        self.line_renderer.prepare(device, queue, self.debug_lines.vertices());
+        self.debug_lines.clear();
+
+        self.cull_stats = self.meshes.cull(&self.camera.frustum());
+        if let Some(occlusion) = &mut self.occlusion {
+            let (bounds, draws) = self.meshes.cull_data(self.instance_count);
+            occlusion.prepare(device, queue, self.camera.view_projection(), &bounds, &draws);
+        }
+    }
+
+    /// Resizes resources that depend on the size of the depth buffer.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.size = (width, height);
+        self.ui.resize(width, height);
+        if let Some(occlusion) = &mut self.occlusion {
+            occlusion.resize(device, width, height);
+        }
+    }
+
+    /// Binds the shadow map array the main pass samples.
+    pub(crate) fn bind_shadow_maps(&mut self, device: &Device, maps: &TextureView) {
+        if let (Some(shadow_maps), Some(layout)) =
+            (&mut self.shadow_maps, &self.shadow_bind_group_layout)
+        {
+            shadow_maps.bind_maps(device, layout, maps);
+        }
+    }
+
+    /// Renders shadow casters into shadow maps sampled by the main pass.
+    pub(crate) fn draw_shadows(&self, commands: &mut CommandEncoder, attachments: &Attachments) {
+        if let Some(shadow_maps) = &self.shadow_maps {
+            shadow_maps.render(
+                commands,
+                attachments,
+                &self.meshes,
+                &self.shadow_pipelines,
+                self.instance_count,
+            );
+        }
+    }
+
+    /// Draws opaque geometry and the sky into `output`, it's resolved into
+    /// `resolve` if it's multisampled.
+    pub(crate) fn draw(
+        &self,
+        commands: &mut CommandEncoder,
+        output: &wgpu::TextureView,
+        resolve: Option<&wgpu::TextureView>,
+        depth: &wgpu::TextureView,
+    ) {
+        let mut render_pass = commands.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: output,
+                resolve_target: resolve,
+                ops: Operations {
+                    // covered by the sky after geometry is drawn
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: true,
                 },
-            ],
-            label: Some("diffuse_bind_group"),
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
         });
 
-        self.menu_icon_bind_group = Some(bind_group);
+        let (camera, shadow_maps, sky) = match (
+            &self.camera_bind_group,
+            self.shadow_maps.as_ref().and_then(ShadowMaps::bind_group),
+            &self.sky_bind_group,
+        ) {
+            (Some(camera), Some(shadow_maps), Some(sky)) => (camera, shadow_maps, sky),
+            _ => return,
+        };
+
+        let opaque = self
+            .render_pipelines
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.translucent_pipelines.contains(&PipelineId(*i)));
+        for (i, render_pipeline) in opaque {
+            let render_pipeline = self.view_pipeline(PipelineId(i)).unwrap_or(render_pipeline);
+            render_pass.set_pipeline(render_pipeline);
+            render_pass.set_bind_group(1, camera, &[]);
+            render_pass.set_bind_group(2, shadow_maps, &[]);
+            render_pass.set_bind_group(3, sky, &[]);
+            match &self.occlusion {
+                Some(occlusion) => {
+                    self.meshes
+                        .draw_indirect(&mut render_pass, PipelineId(i), occlusion.draws())
+                }
+                None => self
+                    .meshes
+                    .draw(&mut render_pass, PipelineId(i), self.instance_count),
+            }
+        }
+
+        // drawn last so it's only shaded where no geometry is in front of it
+        if let Some(sky_pipeline) = &self.sky_render_pipeline {
+            render_pass.set_pipeline(sky_pipeline);
+            render_pass.set_bind_group(0, sky, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
     }
 
-    pub(crate) fn draw(&self, commands: &mut CommandEncoder, output: &wgpu::TextureView) {
+    /// Draws translucent pipelines over the surface and depth left by
+    /// [`Renderer::draw`].
+    pub(crate) fn draw_translucent(
+        &self,
+        commands: &mut CommandEncoder,
+        output: &wgpu::TextureView,
+        resolve: Option<&wgpu::TextureView>,
+        depth: &wgpu::TextureView,
+    ) {
         let mut render_pass = commands.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
+            label: Some("Translucent Pass"),
             color_attachments: &[Some(RenderPassColorAttachment {
                 view: output,
-                resolve_target: None,
+                resolve_target: resolve,
                 ops: Operations {
-                    load: LoadOp::Clear(Color {
-                        r: 0.1,
-                        g: 0.2,
-                        b: 0.3,
-                        a: 1.0,
-                    }),
+                    load: LoadOp::Load,
                     store: true,
                 },
             })],
-            depth_stencil_attachment: None,
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
         });
 
-        unsafe {
-            render_pass.set_pipeline(self.render_pipeline.as_ref().unwrap_unchecked());
-            render_pass
-                .set_vertex_buffer(0, self.vertex_buffer.as_ref().unwrap_unchecked().slice(..));
-            render_pass.set_index_buffer(
-                self.index_buffer.as_ref().unwrap_unchecked().slice(..),
-                Index::FORMAT,
-            );
+        let (camera, shadow_maps, sky) = match (
+            &self.camera_bind_group,
+            self.shadow_maps.as_ref().and_then(ShadowMaps::bind_group),
+            &self.sky_bind_group,
+        ) {
+            (Some(camera), Some(shadow_maps), Some(sky)) => (camera, shadow_maps, sky),
+            _ => return,
+        };
+
+        let eye = self.camera.position();
+        for id in &self.translucent_pipelines {
+            let render_pipeline = self
+                .view_pipeline(*id)
+                .unwrap_or(&self.render_pipelines[id.0]);
+            render_pass.set_pipeline(render_pipeline);
+            render_pass.set_bind_group(1, camera, &[]);
+            render_pass.set_bind_group(2, shadow_maps, &[]);
+            render_pass.set_bind_group(3, sky, &[]);
+            self.meshes
+                .draw_sorted(&mut render_pass, *id, self.instance_count, eye);
         }
+    }
 
-        render_pass.draw(0..self.vertices.len() as u32, 0..self.instance_count as u32);
+    /// Variant of `pipeline` drawn in the current debug view, if it has one.
+    fn view_pipeline(&self, pipeline: PipelineId) -> Option<&RenderPipeline> {
+        match self.debug_view {
+            DebugView::Shaded | DebugView::ChunkBounds => None,
+            DebugView::Wireframe => self.wireframe_pipelines.get(pipeline.0),
+            view => self
+                .debug_pipelines
+                .iter()
+                .zip(&self.debug_render_pipelines)
+                .find(|((id, debug_view, _), _)| *id == pipeline && *debug_view == view)
+                .map(|(_, it)| it),
+        }
     }
+
+    /// Draws debug lines over the surface and depth left by
+    /// [`Renderer::draw_translucent`].
+    pub(crate) fn draw_debug(
+        &self,
+        commands: &mut CommandEncoder,
+        output: &wgpu::TextureView,
+        resolve: Option<&wgpu::TextureView>,
+        depth: &wgpu::TextureView,
+    ) {
+        let camera = match &self.camera_bind_group {
+            Some(camera) if !self.line_renderer.is_empty() => camera,
+            _ => return,
+        };
+
+        let mut render_pass = commands.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Debug Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: output,
+                resolve_target: resolve,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        self.line_renderer.draw(&mut render_pass, camera);
+    }
+}
+
+/// Creates a render pipeline drawing only triangle edges of `pipeline`,
+/// which requires [`Features::POLYGON_MODE_LINE`].
+fn create_wireframe_pipeline(
+    pipeline: &mut Pipeline<'_, WgslSource<'static>>,
+    device: &Device,
+    layouts: &[&BindGroupLayout],
+) -> RenderPipeline {
+    let polygon_mode = std::mem::replace(&mut pipeline.polygon_mode, PolygonMode::Line);
+    let render_pipeline = pipeline.create_render_pipeline(device, layouts);
+    pipeline.polygon_mode = polygon_mode;
+    render_pipeline
 }
@@ -0,0 +1,513 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec2};
+use wgpu::*;
+
+use super::{
+    shader::{ShaderSource, WgslSource},
+    uniform::ShaderUniform,
+};
+
+pub static HI_Z_SHADER: WgslSource<'static> = WgslSource::new_static(include_str!("hi_z.wgsl"));
+pub static OCCLUSION_SHADER: WgslSource<'static> =
+    WgslSource::new_static(include_str!("occlusion.wgsl"));
+
+const HI_Z_FORMAT: TextureFormat = TextureFormat::R32Float;
+const HI_Z_WORKGROUP: u32 = 8;
+const CULL_WORKGROUP: u32 = 64;
+
+const HI_Z_STORAGE: BindGroupLayoutEntry = BindGroupLayoutEntry {
+    binding: 2,
+    visibility: ShaderStages::COMPUTE,
+    ty: BindingType::StorageTexture {
+        access: StorageTextureAccess::WriteOnly,
+        format: HI_Z_FORMAT,
+        view_dimension: TextureViewDimension::D2,
+    },
+    count: None,
+};
+
+// R32Float can't be filtered, so layouts derived from shaders (which assume
+// filterable floats) can't be used.
+const HI_Z_TEXTURE: BindingType = BindingType::Texture {
+    sample_type: TextureSampleType::Float { filterable: false },
+    view_dimension: TextureViewDimension::D2,
+    multisampled: false,
+};
+
+const HI_Z_COPY_LAYOUT: BindGroupLayoutDescriptor<'static> = BindGroupLayoutDescriptor {
+    label: Some("hi_z_copy_layout"),
+    entries: &[
+        BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Depth,
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+        HI_Z_STORAGE,
+    ],
+};
+
+const HI_Z_COPY_MULTISAMPLED_LAYOUT: BindGroupLayoutDescriptor<'static> =
+    BindGroupLayoutDescriptor {
+        label: Some("hi_z_copy_multisampled_layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 3,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Depth,
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: true,
+                },
+                count: None,
+            },
+            HI_Z_STORAGE,
+        ],
+    };
+
+const HI_Z_DOWNSAMPLE_LAYOUT: BindGroupLayoutDescriptor<'static> = BindGroupLayoutDescriptor {
+    label: Some("hi_z_downsample_layout"),
+    entries: &[
+        BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStages::COMPUTE,
+            ty: HI_Z_TEXTURE,
+            count: None,
+        },
+        HI_Z_STORAGE,
+    ],
+};
+
+const fn storage_buffer(binding: u32, read_only: bool) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+const CULL_LAYOUT: BindGroupLayoutDescriptor<'static> = BindGroupLayoutDescriptor {
+    label: Some("occlusion_cull_layout"),
+    entries: &[
+        BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        storage_buffer(1, true),
+        storage_buffer(2, false),
+        BindGroupLayoutEntry {
+            binding: 3,
+            visibility: ShaderStages::COMPUTE,
+            ty: HI_Z_TEXTURE,
+            count: None,
+        },
+    ],
+};
+
+/// How meshes hidden from the camera are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullingMode {
+    /// Meshes outside of the camera frustum are skipped on the CPU.
+    Frustum,
+    /// Meshes are additionally tested against depth of the previous frame on
+    /// the GPU and drawn indirectly.
+    Occlusion,
+}
+
+impl CullingMode {
+    /// Occlusion culling if the adapter can run compute shaders and indirect
+    /// draws, frustum culling otherwise.
+    ///
+    /// GL adapters always use frustum culling, GLSL can't `textureLoad` from
+    /// depth textures the depth pyramid is built from.
+    pub fn supported(adapter: &Adapter) -> Self {
+        let required = DownlevelFlags::COMPUTE_SHADERS | DownlevelFlags::INDIRECT_EXECUTION;
+        let capable = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(required);
+        if capable && adapter.get_info().backend != Backend::Gl {
+            CullingMode::Occlusion
+        } else {
+            CullingMode::Frustum
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Pod, Zeroable, ShaderUniform)]
+#[repr(C)]
+pub struct CullUniform {
+    pub view_projection: Mat4,
+    pub hi_z_size: Vec2,
+    /// 0 while there's no pyramid to test against.
+    pub hi_z_levels: u32,
+    pub mesh_count: u32,
+}
+
+/// World space bounds of a mesh tested by the cull shader.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct MeshBounds {
+    /// `w` is 0 for meshes that are never culled.
+    pub start: [f32; 4],
+    pub end: [f32; 4],
+}
+
+/// Arguments of `draw_indexed_indirect`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct DrawArgs {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub first_instance: u32,
+}
+
+impl DrawArgs {
+    pub const SIZE: BufferAddress = std::mem::size_of::<DrawArgs>() as BufferAddress;
+}
+
+/// Depth pyramid, level `n` stores the furthest depth of 2x2 texels of level
+/// `n - 1`.
+#[derive(Debug)]
+struct HiZ {
+    size: (u32, u32),
+    levels: u32,
+    view: TextureView,
+    level_views: Vec<TextureView>,
+    /// Downsampling bind groups of levels after the first one.
+    downsample: Vec<BindGroup>,
+    built: bool,
+}
+
+impl HiZ {
+    fn new(device: &Device, layout: &BindGroupLayout, width: u32, height: u32) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+        let levels = 32 - width.max(height).leading_zeros();
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("hi_z"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: levels,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: HI_Z_FORMAT,
+            usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+        });
+
+        let level_views: Vec<_> = (0..levels)
+            .map(|level| {
+                texture.create_view(&TextureViewDescriptor {
+                    base_mip_level: level,
+                    mip_level_count: std::num::NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let downsample = level_views
+            .windows(2)
+            .map(|views| {
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("hi_z_downsample"),
+                    layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::TextureView(&views[0]),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: BindingResource::TextureView(&views[1]),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        HiZ {
+            size: (width, height),
+            levels,
+            view: texture.create_view(&TextureViewDescriptor::default()),
+            level_views,
+            downsample,
+            built: false,
+        }
+    }
+
+    fn level_size(&self, level: u32) -> (u32, u32) {
+        ((self.size.0 >> level).max(1), (self.size.1 >> level).max(1))
+    }
+}
+
+/// Culls meshes on the GPU against a hierarchical-Z pyramid built from depth
+/// of the previous frame and writes the result into an indirect draw buffer.
+///
+/// Depth of the previous frame is reprojected with the current camera, so
+/// meshes revealed by fast camera movement can be missing for a frame.
+#[derive(Debug)]
+pub struct OcclusionCuller {
+    /// Whether the depth buffer the pyramid is built from is multisampled.
+    multisampled: bool,
+    copy_layout: BindGroupLayout,
+    downsample_layout: BindGroupLayout,
+    cull_layout: BindGroupLayout,
+    copy_pipeline: ComputePipeline,
+    downsample_pipeline: ComputePipeline,
+    cull_pipeline: ComputePipeline,
+
+    uniform_buffer: Buffer,
+    bounds_buffer: Buffer,
+    draws_buffer: Buffer,
+    capacity: usize,
+    mesh_count: u32,
+
+    hi_z: HiZ,
+    depth_bind_group: Option<BindGroup>,
+    cull_bind_group: Option<BindGroup>,
+}
+
+impl OcclusionCuller {
+    /// Creates a culler building its pyramid from a `width` by `height` depth
+    /// buffer with `sample_count` samples per pixel.
+    pub fn new(device: &Device, width: u32, height: u32, sample_count: u32) -> Self {
+        let hi_z_shader = HI_Z_SHADER.create_shader_module(device);
+        let cull_shader = OCCLUSION_SHADER.create_shader_module(device);
+        let compute = |label, layout, module, entry_point| {
+            let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                module,
+                entry_point,
+            })
+        };
+
+        let multisampled = sample_count > 1;
+        let (copy_layout, copy_entry_point) = if multisampled {
+            (
+                device.create_bind_group_layout(&HI_Z_COPY_MULTISAMPLED_LAYOUT),
+                "copy_depth_multisampled",
+            )
+        } else {
+            (
+                device.create_bind_group_layout(&HI_Z_COPY_LAYOUT),
+                "copy_depth",
+            )
+        };
+        let downsample_layout = device.create_bind_group_layout(&HI_Z_DOWNSAMPLE_LAYOUT);
+        let cull_layout = device.create_bind_group_layout(&CULL_LAYOUT);
+
+        let copy_pipeline = compute("hi_z_copy", &copy_layout, &hi_z_shader, copy_entry_point);
+        let downsample_pipeline = compute(
+            "hi_z_downsample",
+            &downsample_layout,
+            &hi_z_shader,
+            "downsample",
+        );
+        let cull_pipeline = compute("occlusion_cull", &cull_layout, &cull_shader, "cull_meshes");
+
+        let hi_z = HiZ::new(device, &downsample_layout, width, height);
+
+        let capacity = 64;
+        OcclusionCuller {
+            uniform_buffer: CullUniform::zeroed().create_init_wgpu_buff(device),
+            bounds_buffer: Self::create_bounds_buffer(device, capacity),
+            draws_buffer: Self::create_draws_buffer(device, capacity),
+            capacity,
+            mesh_count: 0,
+
+            multisampled,
+            copy_layout,
+            downsample_layout,
+            cull_layout,
+            copy_pipeline,
+            downsample_pipeline,
+            cull_pipeline,
+
+            hi_z,
+            depth_bind_group: None,
+            cull_bind_group: None,
+        }
+    }
+
+    fn create_bounds_buffer(device: &Device, capacity: usize) -> Buffer {
+        device.create_buffer(&BufferDescriptor {
+            label: Some("Mesh Bounds Buffer"),
+            size: (capacity * std::mem::size_of::<MeshBounds>()) as BufferAddress,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_draws_buffer(device: &Device, capacity: usize) -> Buffer {
+        device.create_buffer(&BufferDescriptor {
+            label: Some("Indirect Draw Buffer"),
+            size: capacity as BufferAddress * DrawArgs::SIZE,
+            usage: BufferUsages::STORAGE | BufferUsages::INDIRECT | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Buffer with [`DrawArgs`] of every mesh after [`Self::cull`].
+    pub fn draws(&self) -> &Buffer {
+        &self.draws_buffer
+    }
+
+    /// Recreates the pyramid for a depth buffer of a different size, meshes
+    /// aren't occlusion tested until it's built again.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        if self.hi_z.size == (width.max(1), height.max(1)) {
+            return;
+        }
+        self.hi_z = HiZ::new(device, &self.downsample_layout, width, height);
+        self.depth_bind_group = None;
+        self.cull_bind_group = None;
+    }
+
+    /// Uploads mesh bounds and draws that are culled in the next
+    /// [`Self::cull`].
+    pub fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        view_projection: Mat4,
+        bounds: &[MeshBounds],
+        draws: &[DrawArgs],
+    ) {
+        debug_assert_eq!(bounds.len(), draws.len());
+
+        if bounds.len() > self.capacity {
+            self.capacity = bounds.len().next_power_of_two();
+            self.bounds_buffer = Self::create_bounds_buffer(device, self.capacity);
+            self.draws_buffer = Self::create_draws_buffer(device, self.capacity);
+            self.cull_bind_group = None;
+        }
+        self.mesh_count = bounds.len() as u32;
+
+        let uniform = CullUniform {
+            view_projection,
+            hi_z_size: Vec2::new(self.hi_z.size.0 as f32, self.hi_z.size.1 as f32),
+            hi_z_levels: if self.hi_z.built { self.hi_z.levels } else { 0 },
+            mesh_count: self.mesh_count,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+        queue.write_buffer(&self.bounds_buffer, 0, bytemuck::cast_slice(bounds));
+        queue.write_buffer(&self.draws_buffer, 0, bytemuck::cast_slice(draws));
+
+        if self.cull_bind_group.is_none() {
+            self.cull_bind_group = Some(device.create_bind_group(&BindGroupDescriptor {
+                label: Some("occlusion_cull"),
+                layout: &self.cull_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: self.uniform_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: self.bounds_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: self.draws_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: BindingResource::TextureView(&self.hi_z.view),
+                    },
+                ],
+            }));
+        }
+    }
+
+    /// Records the compute pass that zeroes instance counts of hidden meshes.
+    pub fn cull(&self, encoder: &mut CommandEncoder) {
+        let bind_group = match &self.cull_bind_group {
+            Some(it) if self.mesh_count > 0 => it,
+            _ => return,
+        };
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Occlusion Cull"),
+        });
+        pass.set_pipeline(&self.cull_pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.dispatch_workgroups(self.mesh_count.div_ceil(CULL_WORKGROUP), 1, 1);
+    }
+
+    /// Binds the depth buffer the pyramid is built from.
+    pub fn bind_depth(&mut self, device: &Device, depth: &TextureView) {
+        self.depth_bind_group = Some(device.create_bind_group(&BindGroupDescriptor {
+            label: Some("hi_z_copy"),
+            layout: &self.copy_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: if self.multisampled { 3 } else { 0 },
+                    resource: BindingResource::TextureView(depth),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&self.hi_z.level_views[0]),
+                },
+            ],
+        }));
+    }
+
+    /// Records passes building the pyramid from the bound depth buffer, it's
+    /// used to cull meshes in the next frame.
+    pub fn build_hi_z(&mut self, encoder: &mut CommandEncoder) {
+        let depth = match &self.depth_bind_group {
+            Some(it) => it,
+            None => return,
+        };
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Hi-Z"),
+        });
+        let dispatch = |pass: &mut ComputePass, (width, height): (u32, u32)| {
+            pass.dispatch_workgroups(
+                width.div_ceil(HI_Z_WORKGROUP),
+                height.div_ceil(HI_Z_WORKGROUP),
+                1,
+            );
+        };
+
+        pass.set_pipeline(&self.copy_pipeline);
+        pass.set_bind_group(0, depth, &[]);
+        dispatch(&mut pass, self.hi_z.size);
+
+        pass.set_pipeline(&self.downsample_pipeline);
+        for (i, bind_group) in self.hi_z.downsample.iter().enumerate() {
+            pass.set_bind_group(0, bind_group, &[]);
+            dispatch(&mut pass, self.hi_z.level_size(i as u32 + 1));
+        }
+        drop(pass);
+
+        self.hi_z.built = true;
+    }
+}
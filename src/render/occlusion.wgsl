@@ -0,0 +1,94 @@
+// Tests mesh bounds against the view frustum and the hierarchical-Z pyramid of
+// the previous frame, draws of hidden meshes get zero instances.
+
+struct CullUniform {
+    view_projection: mat4x4<f32>,
+    hi_z_size: vec2<f32>,
+    hi_z_levels: u32,
+    mesh_count: u32,
+};
+
+struct MeshBounds {
+    // `w` of `start` is 0 for meshes that are never culled
+    start: vec4<f32>,
+    end: vec4<f32>,
+};
+
+struct DrawArgs {
+    index_count: u32,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> cull: CullUniform;
+
+@group(0) @binding(1)
+var<storage, read> bounds: array<MeshBounds>;
+
+@group(0) @binding(2)
+var<storage, read_write> draws: array<DrawArgs>;
+
+@group(0) @binding(3)
+var hi_z: texture_2d<f32>;
+
+fn is_visible(mesh: MeshBounds) -> bool {
+    var ndc_min = vec3<f32>(1.0, 1.0, 1.0);
+    var ndc_max = vec3<f32>(-1.0, -1.0, 0.0);
+
+    for (var i = 0u; i < 8u; i = i + 1u) {
+        let corner = vec3<f32>(
+            select(mesh.start.x, mesh.end.x, (i & 1u) != 0u),
+            select(mesh.start.y, mesh.end.y, (i & 2u) != 0u),
+            select(mesh.start.z, mesh.end.z, (i & 4u) != 0u),
+        );
+        let clip = cull.view_projection * vec4<f32>(corner, 1.0);
+        // boxes crossing the near plane can't be tested reliably
+        if (clip.w <= 0.0) {
+            return true;
+        }
+        let ndc = clip.xyz / clip.w;
+        ndc_min = min(ndc_min, ndc);
+        ndc_max = max(ndc_max, ndc);
+    }
+
+    if (any(ndc_max.xy < vec2<f32>(-1.0, -1.0)) || any(ndc_min.xy > vec2<f32>(1.0, 1.0)) || ndc_min.z > 1.0) {
+        return false;
+    }
+    if (cull.hi_z_levels == 0u) {
+        return true;
+    }
+
+    // screen rectangle covered by the box, y flipped to texture coordinates
+    let uv_min = clamp(vec2<f32>(ndc_min.x, -ndc_max.y) * 0.5 + 0.5, vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0));
+    let uv_max = clamp(vec2<f32>(ndc_max.x, -ndc_min.y) * 0.5 + 0.5, vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0));
+    let extent = (uv_max - uv_min) * cull.hi_z_size;
+
+    // level where the rectangle spans at most 2x2 texels
+    let level = min(u32(ceil(log2(max(max(extent.x, extent.y), 1.0)))), cull.hi_z_levels - 1u);
+    let size = textureDimensions(hi_z, i32(level));
+    let last = size - vec2<i32>(1, 1);
+    let texel_min = min(vec2<i32>(uv_min * vec2<f32>(size)), last);
+    let texel_max = min(vec2<i32>(uv_max * vec2<f32>(size)), last);
+
+    let occluder = max(
+        max(textureLoad(hi_z, texel_min, i32(level)).r, textureLoad(hi_z, vec2<i32>(texel_max.x, texel_min.y), i32(level)).r),
+        max(textureLoad(hi_z, vec2<i32>(texel_min.x, texel_max.y), i32(level)).r, textureLoad(hi_z, texel_max, i32(level)).r),
+    );
+    return ndc_min.z <= occluder;
+}
+
+@compute @workgroup_size(64)
+fn cull_meshes(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if (i >= cull.mesh_count) {
+        return;
+    }
+
+    let mesh = bounds[i];
+    if (mesh.start.w != 0.0 && !is_visible(mesh)) {
+        draws[i].instance_count = 0u;
+    }
+}
@@ -1,13 +1,90 @@
 use wgpu::*;
 
-use super::shader::ShaderSource;
+use crate::error::ShaderError;
 
+use super::shader::{validation, ShaderSource};
+
+/// Vertex entry point and the buffers it reads from.
+///
+/// Buffer attributes whose locations overlap an earlier buffer are moved after
+/// it, so per-instance data can be added without renumbering locations by
+/// hand.
 #[derive(Debug)]
 pub struct VertexInterface<'a> {
     pub entry_point: String,
     pub buffers: Vec<VertexBufferLayout<'a>>,
 }
 
+impl<'a> VertexInterface<'a> {
+    pub fn new(entry_point: impl ToString) -> Self {
+        VertexInterface {
+            entry_point: entry_point.to_string(),
+            buffers: Vec::new(),
+        }
+    }
+
+    pub fn with_buffer(mut self, layout: VertexBufferLayout<'a>) -> Self {
+        self.buffers.push(layout);
+        self
+    }
+
+    /// Location the first attribute of every buffer is moved to.
+    pub fn first_locations(&self) -> Vec<ShaderLocation> {
+        self.attributes()
+            .iter()
+            .map(|it| {
+                it.iter()
+                    .map(|it| it.shader_location)
+                    .min()
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Attributes of every buffer with resolved shader locations.
+    pub fn attributes(&self) -> Vec<Vec<VertexAttribute>> {
+        let mut next_free = 0;
+        self.buffers
+            .iter()
+            .map(|buffer| {
+                let first = buffer
+                    .attributes
+                    .iter()
+                    .map(|it| it.shader_location)
+                    .min()
+                    .unwrap_or_default();
+                let offset = next_free.max(first) - first;
+
+                let attributes: Vec<_> = buffer
+                    .attributes
+                    .iter()
+                    .map(|it| VertexAttribute {
+                        shader_location: it.shader_location + offset,
+                        ..*it
+                    })
+                    .collect();
+
+                if let Some(last) = attributes.iter().map(|it| it.shader_location).max() {
+                    next_free = next_free.max(last + 1);
+                }
+                attributes
+            })
+            .collect()
+    }
+
+    fn layouts<'s>(&self, attributes: &'s [Vec<VertexAttribute>]) -> Vec<VertexBufferLayout<'s>> {
+        self.buffers
+            .iter()
+            .zip(attributes)
+            .map(|(buffer, attributes)| VertexBufferLayout {
+                array_stride: buffer.array_stride,
+                step_mode: buffer.step_mode,
+                attributes,
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug)]
 pub struct FragmentInterface {
     pub entry_point: String,
@@ -23,6 +100,9 @@ pub struct Pipeline<'v, S: ShaderSource> {
 
     pub topology: PrimitiveTopology,
     pub polygon_mode: PolygonMode,
+    pub depth_stencil: Option<DepthStencilState>,
+    /// Samples per pixel of the attachments it draws into.
+    pub sample_count: u32,
 
     shader_module: Option<ShaderModule>,
 }
@@ -40,19 +120,80 @@ impl<'v, S: ShaderSource> Pipeline<'v, S> {
 
             topology: PrimitiveTopology::TriangleList,
             polygon_mode: PolygonMode::Fill,
+            depth_stencil: None,
+            sample_count: 1,
 
             shader_module: None,
         }
     }
 
-    pub fn create_render_pipeline(&self, device: &Device) -> RenderPipeline {
+    /// Validates the shader and checks that it matches declared interfaces.
+    pub fn validate(&self) -> Result<(), ShaderError> {
+        self.check_interfaces(&self.shader.validate()?)
+    }
+
+    fn check_interfaces(&self, module: &naga::Module) -> Result<(), ShaderError> {
+        let attributes = self.vertex_interface.attributes();
+        validation::check_vertex_inputs(
+            module,
+            &self.vertex_interface.entry_point,
+            &self.vertex_interface.layouts(&attributes),
+        )?;
+        if let Some(fragment) = &self.fragment_interface {
+            validation::check_entry_point(
+                module,
+                &fragment.entry_point,
+                naga::ShaderStage::Fragment,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Swaps in the changed shader source if it's valid.
+    ///
+    /// Returns `true` if the render pipeline has to be recreated. Invalid
+    /// changes are logged and the previous shader is kept.
+    pub fn reload_shader(&mut self) -> bool {
+        let changed = match self.shader.poll_changes() {
+            Some(changed) => changed,
+            None => return false,
+        };
+
+        let checked = changed.and_then(|shader| {
+            self.check_interfaces(&shader.validate()?)?;
+            Ok(shader)
+        });
+
+        match checked {
+            Ok(shader) => {
+                tracing::info!("reloaded shader");
+                self.shader = shader;
+                self.shader_module = None;
+                true
+            }
+            Err(err) => {
+                tracing::error!("keeping previous shader: {}", err);
+                false
+            }
+        }
+    }
+
+    pub fn create_render_pipeline(
+        &mut self,
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+    ) -> RenderPipeline {
         let shader = self
             .shader_module
             .get_or_insert_with(|| self.shader.create_shader_module(device));
 
+        let attributes = self.vertex_interface.attributes();
+        let buffers = self.vertex_interface.layouts(&attributes);
+
         let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[],
+            bind_group_layouts,
             push_constant_ranges: &[],
         });
 
@@ -60,30 +201,33 @@ impl<'v, S: ShaderSource> Pipeline<'v, S> {
             label: None,
             layout: Some(&render_pipeline_layout),
             vertex: VertexState {
-                module: &shader,
+                module: shader,
                 entry_point: &self.vertex_interface.entry_point,
-                buffers: &self.vertex_interface.buffers,
+                buffers: &buffers,
             },
-            fragment: self.fragment_interface.map(|interface| FragmentState {
-                module: &shader,
-                entry_point: &interface.entry_point,
-                targets: &interface.targets.as_slice(),
-            }),
+            fragment: self
+                .fragment_interface
+                .as_ref()
+                .map(|interface| FragmentState {
+                    module: shader,
+                    entry_point: &interface.entry_point,
+                    targets: interface.targets.as_slice(),
+                }),
             primitive: PrimitiveState {
                 topology: self.topology,
                 strip_index_format: None,
                 front_face: FrontFace::Ccw,
                 cull_mode: Some(Face::Back),
-                // Requires Features::NON_FILL_POLYGON_MODE
+                // Requires Features::POLYGON_MODE_LINE or POLYGON_MODE_POINT
                 polygon_mode: self.polygon_mode,
                 // Requires Features::DEPTH_CLIP_CONTROL
                 unclipped_depth: false,
                 // Requires Features::CONSERVATIVE_RASTERIZATION
                 conservative: false,
             },
-            depth_stencil: None,
+            depth_stencil: self.depth_stencil.clone(),
             multisample: MultisampleState {
-                count: 1,
+                count: self.sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -0,0 +1,424 @@
+use std::ops::Range;
+
+use wgpu::*;
+
+use super::{mesh::MeshData, vertex::Index};
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AllocationId(u32);
+
+/// Allocation moved by [`RangeAllocator::defragment`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Move {
+    pub id: AllocationId,
+    pub from: Range<u64>,
+    pub to: Range<u64>,
+}
+
+/// First-fit allocator of ranges within a buffer.
+///
+/// It only does the bookkeeping so it doesn't need a GPU, [`BufferPool`] uses
+/// it to place data into a shared wgpu buffer.
+#[derive(Debug, Clone)]
+pub struct RangeAllocator {
+    size: u64,
+    alignment: u64,
+    /// Sorted and coalesced free ranges.
+    free: Vec<Range<u64>>,
+    allocations: Vec<Option<Range<u64>>>,
+    unused_ids: Vec<u32>,
+}
+
+impl RangeAllocator {
+    pub fn new(size: u64, alignment: u64) -> Self {
+        assert!(alignment > 0, "alignment must be non-zero");
+        let mut result = RangeAllocator {
+            size: 0,
+            alignment,
+            free: Vec::new(),
+            allocations: Vec::new(),
+            unused_ids: Vec::new(),
+        };
+        result.grow(size);
+        result
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn alignment(&self) -> u64 {
+        self.alignment
+    }
+
+    pub fn free_space(&self) -> u64 {
+        self.free.iter().map(|it| it.end - it.start).sum()
+    }
+
+    pub fn largest_free(&self) -> u64 {
+        self.free
+            .iter()
+            .map(|it| it.end - it.start)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Fraction of free space that can't be used by a single allocation, `0.0`
+    /// when all free space is contiguous.
+    pub fn fragmentation(&self) -> f32 {
+        let free = self.free_space();
+        if free == 0 {
+            return 0.0;
+        }
+        1.0 - self.largest_free() as f32 / free as f32
+    }
+
+    /// Size `len` takes up once it's aligned.
+    pub fn allocation_size(&self, len: u64) -> u64 {
+        align_up(len.max(1), self.alignment)
+    }
+
+    pub fn allocate(&mut self, len: u64) -> Option<AllocationId> {
+        let len = self.allocation_size(len);
+        let slot = self.free.iter().position(|it| it.end - it.start >= len)?;
+
+        let start = self.free[slot].start;
+        if self.free[slot].end - start == len {
+            self.free.remove(slot);
+        } else {
+            self.free[slot].start += len;
+        }
+
+        Some(self.insert(start..start + len))
+    }
+
+    fn insert(&mut self, range: Range<u64>) -> AllocationId {
+        match self.unused_ids.pop() {
+            Some(id) => {
+                self.allocations[id as usize] = Some(range);
+                AllocationId(id)
+            }
+            None => {
+                self.allocations.push(Some(range));
+                AllocationId(self.allocations.len() as u32 - 1)
+            }
+        }
+    }
+
+    /// Byte range of an allocation, may be longer than the requested length
+    /// because of alignment.
+    pub fn range(&self, id: AllocationId) -> Option<Range<u64>> {
+        self.allocations.get(id.0 as usize)?.clone()
+    }
+
+    pub fn free(&mut self, id: AllocationId) -> bool {
+        let range = match self
+            .allocations
+            .get_mut(id.0 as usize)
+            .and_then(Option::take)
+        {
+            Some(it) => it,
+            None => return false,
+        };
+        self.unused_ids.push(id.0);
+        self.release(range);
+        true
+    }
+
+    fn release(&mut self, range: Range<u64>) {
+        let i = self.free.partition_point(|it| it.start < range.start);
+
+        let joins_next = i < self.free.len() && self.free[i].start == range.end;
+        let joins_prev = i > 0 && self.free[i - 1].end == range.start;
+
+        match (joins_prev, joins_next) {
+            (true, true) => {
+                self.free[i - 1].end = self.free[i].end;
+                self.free.remove(i);
+            }
+            (true, false) => self.free[i - 1].end = range.end,
+            (false, true) => self.free[i].start = range.start,
+            (false, false) => self.free.insert(i, range),
+        }
+    }
+
+    /// Extends the managed space to `size` bytes, existing allocations stay
+    /// where they are.
+    pub fn grow(&mut self, size: u64) {
+        let size = size / self.alignment * self.alignment;
+        if size <= self.size {
+            return;
+        }
+
+        let old = self.size;
+        self.size = size;
+        self.release(old..size);
+    }
+
+    /// Packs all allocations at the start of the space so free space is
+    /// contiguous, returns the allocations that were moved.
+    ///
+    /// Allocations keep their relative order, so every move is towards the
+    /// start.
+    pub fn defragment(&mut self) -> Vec<Move> {
+        let mut live: Vec<(u32, Range<u64>)> = self
+            .allocations
+            .iter()
+            .enumerate()
+            .filter_map(|(i, it)| it.clone().map(|range| (i as u32, range)))
+            .collect();
+        live.sort_by_key(|(_, range)| range.start);
+
+        let mut moves = Vec::new();
+        let mut end = 0;
+        for (id, from) in live {
+            let to = end..end + (from.end - from.start);
+            end = to.end;
+
+            if to != from {
+                self.allocations[id as usize] = Some(to.clone());
+                moves.push(Move {
+                    id: AllocationId(id),
+                    from,
+                    to,
+                });
+            }
+        }
+
+        self.free.clear();
+        if end < self.size {
+            self.free.push(end..self.size);
+        }
+        moves
+    }
+}
+
+/// Large wgpu buffer data is sub-allocated from instead of creating a buffer
+/// for every upload.
+///
+/// The buffer is grown or defragmented when an allocation doesn't fit.
+#[derive(Debug)]
+pub struct BufferPool {
+    label: &'static str,
+    usage: BufferUsages,
+    buffer: Buffer,
+    allocator: RangeAllocator,
+}
+
+impl BufferPool {
+    pub fn new(device: &Device, label: &'static str, usage: BufferUsages, size: u64) -> Self {
+        let usage = usage | BufferUsages::COPY_DST | BufferUsages::COPY_SRC;
+        let allocator = RangeAllocator::new(size, COPY_BUFFER_ALIGNMENT);
+
+        BufferPool {
+            label,
+            usage,
+            buffer: Self::create_buffer(device, label, usage, allocator.size()),
+            allocator,
+        }
+    }
+
+    fn create_buffer(device: &Device, label: &str, usage: BufferUsages, size: u64) -> Buffer {
+        device.create_buffer(&BufferDescriptor {
+            label: Some(label),
+            size,
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn allocator(&self) -> &RangeAllocator {
+        &self.allocator
+    }
+
+    /// Copies `data` into the pool.
+    pub fn allocate(&mut self, device: &Device, queue: &Queue, data: &[u8]) -> AllocationId {
+        let id = match self.allocator.allocate(data.len() as u64) {
+            Some(id) => id,
+            None => {
+                let needed = self.allocator.allocation_size(data.len() as u64);
+                if self.allocator.free_space() >= needed {
+                    self.defragment(device, queue);
+                } else {
+                    let size = (self.allocator.size() * 2).max(self.allocator.size() + needed);
+                    self.grow(device, queue, size);
+                }
+                self.allocator
+                    .allocate(data.len() as u64)
+                    .expect("pool has space for allocation")
+            }
+        };
+
+        let range = self.allocator.range(id).unwrap();
+        let padded = align_up(data.len() as u64, COPY_BUFFER_ALIGNMENT) as usize;
+        if padded == data.len() {
+            queue.write_buffer(&self.buffer, range.start, data);
+        } else {
+            let mut data = data.to_vec();
+            data.resize(padded, 0);
+            queue.write_buffer(&self.buffer, range.start, &data);
+        }
+        id
+    }
+
+    pub fn free(&mut self, id: AllocationId) -> bool {
+        self.allocator.free(id)
+    }
+
+    pub fn slice(&self, id: AllocationId) -> Option<BufferSlice<'_>> {
+        self.allocator.range(id).map(|it| self.buffer.slice(it))
+    }
+
+    /// Moves data into a larger buffer.
+    pub fn grow(&mut self, device: &Device, queue: &Queue, size: u64) {
+        let old_size = self.allocator.size();
+        self.allocator.grow(size);
+        if self.allocator.size() == old_size {
+            return;
+        }
+
+        let buffer = Self::create_buffer(device, self.label, self.usage, self.allocator.size());
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Buffer Pool Grow"),
+        });
+        encoder.copy_buffer_to_buffer(&self.buffer, 0, &buffer, 0, old_size);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        self.buffer = buffer;
+    }
+
+    /// Packs allocated data at the start of the buffer.
+    ///
+    /// Ranges of allocations change so previously returned slices have to be
+    /// requested again.
+    pub fn defragment(&mut self, device: &Device, queue: &Queue) {
+        let moves = self.allocator.defragment();
+        if moves.is_empty() {
+            return;
+        }
+
+        // wgpu doesn't allow overlapping copies within a buffer
+        let buffer = Self::create_buffer(device, self.label, self.usage, self.allocator.size());
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Buffer Pool Defragment"),
+        });
+        let moved = |id| moves.iter().any(|it: &Move| it.id == id);
+        for id in (0..self.allocator.allocations.len() as u32).map(AllocationId) {
+            if let Some(range) = self.allocator.range(id) {
+                if !moved(id) {
+                    encoder.copy_buffer_to_buffer(
+                        &self.buffer,
+                        range.start,
+                        &buffer,
+                        range.start,
+                        range.end - range.start,
+                    );
+                }
+            }
+        }
+        for it in &moves {
+            encoder.copy_buffer_to_buffer(
+                &self.buffer,
+                it.from.start,
+                &buffer,
+                it.to.start,
+                it.to.end - it.to.start,
+            );
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        self.buffer = buffer;
+    }
+}
+
+/// Location of a mesh inside a [`MeshPool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PooledMesh {
+    vertices: AllocationId,
+    indices: AllocationId,
+    index_count: u32,
+}
+
+impl PooledMesh {
+    pub fn index_count(&self) -> u32 {
+        self.index_count
+    }
+}
+
+/// Stores vertices and indices of many meshes (such as chunks) in shared
+/// buffers.
+#[derive(Debug)]
+pub struct MeshPool {
+    pub vertices: BufferPool,
+    pub indices: BufferPool,
+}
+
+impl MeshPool {
+    pub fn new(device: &Device, vertex_size: u64, index_size: u64) -> Self {
+        MeshPool {
+            vertices: BufferPool::new(
+                device,
+                "Mesh Pool Vertices",
+                BufferUsages::VERTEX,
+                vertex_size,
+            ),
+            indices: BufferPool::new(device, "Mesh Pool Indices", BufferUsages::INDEX, index_size),
+        }
+    }
+
+    pub fn insert(&mut self, device: &Device, queue: &Queue, mesh: &dyn MeshData) -> PooledMesh {
+        PooledMesh {
+            vertices: self.vertices.allocate(device, queue, mesh.vertex_bytes()),
+            indices: self.indices.allocate(device, queue, mesh.index_bytes()),
+            index_count: mesh.index_count() as u32,
+        }
+    }
+
+    pub fn remove(&mut self, mesh: PooledMesh) {
+        self.vertices.free(mesh.vertices);
+        self.indices.free(mesh.indices);
+    }
+
+    /// Defragments buffers where more than `threshold` of free space is
+    /// fragmented.
+    pub fn maintain(&mut self, device: &Device, queue: &Queue, threshold: f32) {
+        if self.vertices.allocator().fragmentation() > threshold {
+            self.vertices.defragment(device, queue);
+        }
+        if self.indices.allocator().fragmentation() > threshold {
+            self.indices.defragment(device, queue);
+        }
+    }
+
+    /// Binds vertices and indices of a pooled mesh as offset slices of the
+    /// shared buffers, so it's drawn from index 0 and base vertex 0. Returns
+    /// `false` if the mesh was removed.
+    pub fn bind<'a>(&'a self, pass: &mut RenderPass<'a>, mesh: PooledMesh) -> bool {
+        let (vertices, indices) = match (
+            self.vertices.slice(mesh.vertices),
+            self.indices.slice(mesh.indices),
+        ) {
+            (Some(v), Some(i)) => (v, i),
+            _ => return false,
+        };
+
+        pass.set_vertex_buffer(0, vertices);
+        pass.set_index_buffer(indices, Index::FORMAT);
+        true
+    }
+
+    /// Records an indexed draw of a pooled mesh.
+    pub fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, mesh: PooledMesh, instances: Range<u32>) {
+        if self.bind(pass, mesh) {
+            pass.draw_indexed(0..mesh.index_count, 0, instances);
+        }
+    }
+}
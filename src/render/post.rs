@@ -0,0 +1,1027 @@
+use std::path::Path;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::*;
+
+use crate::error::RenderError;
+
+use super::{
+    graph::{AttachmentDescriptor, Attachments, GraphPass, RenderGraph, SURFACE},
+    pipeline::{FragmentInterface, Pipeline, VertexInterface},
+    shader::{self, preprocessor::VirtualFiles, WgslSource},
+    uniform::ShaderUniform,
+    Renderer,
+};
+
+/// HDR color attachment scene geometry is drawn into before post-processing.
+pub const HDR: &str = "hdr";
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Longest post-processing chain, every effect but the last one renders into
+/// its own attachment.
+pub const MAX_POST_EFFECTS: usize = 8;
+const TARGETS: [&str; MAX_POST_EFFECTS] = [
+    "post_0", "post_1", "post_2", "post_3", "post_4", "post_5", "post_6", "post_7",
+];
+
+/// Fullscreen triangle and source bindings, included as `fullscreen.wgsl`.
+pub static FULLSCREEN_WGSL: &str = include_str!("fullscreen.wgsl");
+pub static TONEMAP_SHADER: WgslSource<'static> =
+    WgslSource::new_static(include_str!("tonemap.wgsl"));
+pub static BLOOM_SHADER: WgslSource<'static> = WgslSource::new_static(include_str!("bloom.wgsl"));
+pub static FXAA_SHADER: WgslSource<'static> = WgslSource::new_static(include_str!("fxaa.wgsl"));
+pub static COLOR_GRADE_SHADER: WgslSource<'static> =
+    WgslSource::new_static(include_str!("color_grade.wgsl"));
+
+/// Curve HDR colors are mapped into the displayable range with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// Only scales by exposure, colors above 1 are clipped.
+    Linear,
+    Reinhard,
+    /// Filmic curve of the Academy Color Encoding System.
+    Aces,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TonemapSettings {
+    pub tonemapper: Tonemapper,
+    /// Colors are multiplied by it before tonemapping.
+    pub exposure: f32,
+}
+
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        TonemapSettings {
+            tonemapper: Tonemapper::Aces,
+            exposure: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    /// Brightness colors start glowing at.
+    pub threshold: f32,
+    /// Width of the soft transition below the threshold.
+    pub knee: f32,
+    /// Strength of the glow added to the image.
+    pub intensity: f32,
+    /// Number of times the image is halved, more levels spread the glow
+    /// further.
+    pub levels: u32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        BloomSettings {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.3,
+            levels: 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FxaaSettings {
+    /// Smallest local contrast, relative to the brightest pixel, that's
+    /// smoothed out.
+    pub edge_threshold: f32,
+    /// Contrast of dark areas below which edges are ignored.
+    pub edge_threshold_min: f32,
+    /// How much single pixel details are blended, from 0 to 1.
+    pub subpixel: f32,
+}
+
+impl Default for FxaaSettings {
+    fn default() -> Self {
+        FxaaSettings {
+            edge_threshold: 0.125,
+            edge_threshold_min: 0.0312,
+            subpixel: 0.75,
+        }
+    }
+}
+
+/// Color lookup table, colors are remapped to the entry at their `rgb`
+/// coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lut {
+    size: u32,
+    /// `rgba8` entries with red changing the fastest and blue the slowest.
+    data: Vec<u8>,
+}
+
+impl Lut {
+    /// Table that maps every color to itself.
+    pub fn identity(size: u32) -> Self {
+        let size = size.max(2);
+        let value = |i: u32| (i * 255 / (size - 1)) as u8;
+        let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.extend_from_slice(&[value(r), value(g), value(b), 255]);
+                }
+            }
+        }
+        Lut { size, data }
+    }
+
+    /// Reads a table from a strip of `size` squares laid out left to right,
+    /// the common format of LUTs exported by image editors. Red grows to the
+    /// right within a square, green downwards and blue with every square.
+    pub fn from_image(image: &image::RgbaImage) -> Result<Self, RenderError> {
+        let (width, height) = image.dimensions();
+        if height < 2 || width != height * height {
+            return Err(RenderError::InvalidLut(width, height));
+        }
+
+        let size = height;
+        let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.extend_from_slice(&image.get_pixel(b * size + r, g).0);
+                }
+            }
+        }
+        Ok(Lut { size, data })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RenderError> {
+        Lut::from_image(&image::open(path)?.to_rgba8())
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Entry colors map to, `r`, `g` and `b` are in `0..size`.
+    pub fn get(&self, r: u32, g: u32, b: u32) -> [u8; 4] {
+        let i = (((b * self.size + g) * self.size + r) * 4) as usize;
+        [
+            self.data[i],
+            self.data[i + 1],
+            self.data[i + 2],
+            self.data[i + 3],
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorGradeSettings {
+    pub lut: Lut,
+    /// Blend between original (0) and graded (1) colors.
+    pub strength: f32,
+}
+
+impl Default for ColorGradeSettings {
+    fn default() -> Self {
+        ColorGradeSettings {
+            lut: Lut::identity(16),
+            strength: 1.0,
+        }
+    }
+}
+
+/// Fullscreen pass of the post-processing chain.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostEffect {
+    Bloom(BloomSettings),
+    Tonemap(TonemapSettings),
+    /// Expects tonemapped colors.
+    Fxaa(FxaaSettings),
+    /// Expects tonemapped colors.
+    ColorGrade(ColorGradeSettings),
+}
+
+impl PostEffect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostEffect::Bloom(_) => "bloom",
+            PostEffect::Tonemap(_) => "tonemap",
+            PostEffect::Fxaa(_) => "fxaa",
+            PostEffect::ColorGrade(_) => "color_grade",
+        }
+    }
+}
+
+/// Effects applied to the [`HDR`] image in order, the last one writes the
+/// surface.
+///
+/// Effect parameters can change every frame, changes to the order or kinds of
+/// effects take effect once the graph is created again with
+/// [`Renderer::graph`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostSettings {
+    /// At most [`MAX_POST_EFFECTS`] long, without a [`PostEffect::Tonemap`]
+    /// colors are clipped when they're written to the surface.
+    pub effects: Vec<PostEffect>,
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        PostSettings {
+            effects: vec![
+                PostEffect::Bloom(BloomSettings::default()),
+                PostEffect::Tonemap(TonemapSettings::default()),
+                PostEffect::Fxaa(FxaaSettings::default()),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Pod, Zeroable, ShaderUniform)]
+#[repr(C)]
+pub struct TonemapUniform {
+    pub exposure: f32,
+    pub curve: u32,
+    #[padding]
+    pub _padding: [u32; 2],
+}
+
+impl From<&TonemapSettings> for TonemapUniform {
+    fn from(settings: &TonemapSettings) -> Self {
+        TonemapUniform {
+            exposure: settings.exposure,
+            curve: settings.tonemapper as u32,
+            _padding: [0; 2],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Pod, Zeroable, ShaderUniform)]
+#[repr(C)]
+pub struct BloomUniform {
+    pub threshold: f32,
+    pub knee: f32,
+    pub intensity: f32,
+    #[padding]
+    pub _padding: u32,
+}
+
+impl From<&BloomSettings> for BloomUniform {
+    fn from(settings: &BloomSettings) -> Self {
+        BloomUniform {
+            threshold: settings.threshold,
+            knee: settings.knee,
+            intensity: settings.intensity,
+            _padding: 0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Pod, Zeroable, ShaderUniform)]
+#[repr(C)]
+pub struct FxaaUniform {
+    pub edge_threshold: f32,
+    pub edge_threshold_min: f32,
+    pub subpixel: f32,
+    #[padding]
+    pub _padding: u32,
+}
+
+impl From<&FxaaSettings> for FxaaUniform {
+    fn from(settings: &FxaaSettings) -> Self {
+        FxaaUniform {
+            edge_threshold: settings.edge_threshold,
+            edge_threshold_min: settings.edge_threshold_min,
+            subpixel: settings.subpixel,
+            _padding: 0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Pod, Zeroable, ShaderUniform)]
+#[repr(C)]
+pub struct ColorGradeUniform {
+    pub strength: f32,
+    pub lut_size: f32,
+    #[padding]
+    pub _padding: [u32; 2],
+}
+
+impl From<&ColorGradeSettings> for ColorGradeUniform {
+    fn from(settings: &ColorGradeSettings) -> Self {
+        ColorGradeUniform {
+            strength: settings.strength,
+            lut_size: settings.lut.size as f32,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Pipeline drawing a fullscreen triangle with `fragment` entry point of a
+/// post-processing shader in `file`.
+fn post_pipeline(
+    file: &'static str,
+    source: &WgslSource<'static>,
+    fragment: &str,
+    target: ColorTargetState,
+) -> Pipeline<'static, WgslSource<'static>> {
+    let files = VirtualFiles::new().with("fullscreen.wgsl", FULLSCREEN_WGSL);
+    let shader = shader::load_source(file, source, files).expect("invalid post-processing shader");
+
+    Pipeline::new(
+        shader,
+        VertexInterface::new("vs_fullscreen"),
+        Some(FragmentInterface {
+            entry_point: fragment.to_string(),
+            targets: vec![Some(target)],
+        }),
+    )
+}
+
+fn replace(format: TextureFormat) -> ColorTargetState {
+    ColorTargetState {
+        format,
+        blend: Some(BlendState::REPLACE),
+        write_mask: ColorWrites::ALL,
+    }
+}
+
+pub fn tonemap_pipeline(format: TextureFormat) -> Pipeline<'static, WgslSource<'static>> {
+    post_pipeline("tonemap.wgsl", &TONEMAP_SHADER, "fs_main", replace(format))
+}
+
+pub fn fxaa_pipeline(format: TextureFormat) -> Pipeline<'static, WgslSource<'static>> {
+    post_pipeline("fxaa.wgsl", &FXAA_SHADER, "fs_main", replace(format))
+}
+
+pub fn color_grade_pipeline(format: TextureFormat) -> Pipeline<'static, WgslSource<'static>> {
+    post_pipeline(
+        "color_grade.wgsl",
+        &COLOR_GRADE_SHADER,
+        "fs_main",
+        replace(format),
+    )
+}
+
+/// Step of the bloom effect, each is drawn with its own pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BloomStage {
+    /// Keeps bright parts of the image in the first, half sized level.
+    Prefilter,
+    /// Halves the previous level.
+    Downsample,
+    /// Blurs a level and adds it to the next larger one.
+    Upsample,
+    /// Adds the glow to the image.
+    Composite,
+}
+
+pub fn bloom_pipeline(
+    stage: BloomStage,
+    format: TextureFormat,
+) -> Pipeline<'static, WgslSource<'static>> {
+    let (fragment, target) = match stage {
+        BloomStage::Prefilter => ("fs_prefilter", replace(HDR_FORMAT)),
+        BloomStage::Downsample => ("fs_downsample", replace(HDR_FORMAT)),
+        BloomStage::Upsample => (
+            "fs_upsample",
+            ColorTargetState {
+                format: HDR_FORMAT,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent::REPLACE,
+                }),
+                write_mask: ColorWrites::ALL,
+            },
+        ),
+        BloomStage::Composite => ("fs_composite", replace(format)),
+    };
+    post_pipeline("bloom.wgsl", &BLOOM_SHADER, fragment, target)
+}
+
+struct PostResources {
+    layout: BindGroupLayout,
+    render_pipeline: RenderPipeline,
+    sampler: Sampler,
+    uniform: Buffer,
+}
+
+/// Post-processing pipeline with its GPU resources, bound at group 0 with
+/// the source texture, its sampler, parameters and an optional extra
+/// texture.
+struct PostPipeline {
+    pipeline: Pipeline<'static, WgslSource<'static>>,
+    extra: Option<TextureViewDimension>,
+    resources: Option<PostResources>,
+}
+
+impl PostPipeline {
+    fn new(
+        pipeline: Pipeline<'static, WgslSource<'static>>,
+        extra: Option<TextureViewDimension>,
+    ) -> Self {
+        PostPipeline {
+            pipeline,
+            extra,
+            resources: None,
+        }
+    }
+
+    /// Creates GPU resources on first use and after the shader changed, then
+    /// uploads `params`.
+    fn prepare<U: ShaderUniform>(&mut self, device: &Device, queue: &Queue, params: &U) {
+        if self.pipeline.reload_shader() {
+            self.resources = None;
+        }
+        if self.resources.is_none() {
+            self.resources = Some(self.create_resources(device, params));
+        }
+        if let Some(resources) = &self.resources {
+            queue.write_buffer(&resources.uniform, 0, bytemuck::bytes_of(params));
+        }
+    }
+
+    fn create_resources<U: ShaderUniform>(&mut self, device: &Device, params: &U) -> PostResources {
+        let texture = |binding, view_dimension| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                multisampled: false,
+                view_dimension,
+                sample_type: TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let mut entries = vec![
+            texture(0, TextureViewDimension::D2),
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ];
+        if let Some(dimension) = self.extra {
+            entries.push(texture(3, dimension));
+        }
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("post_layout"),
+            entries: &entries,
+        });
+
+        PostResources {
+            render_pipeline: self.pipeline.create_render_pipeline(device, &[&layout]),
+            layout,
+            sampler: device.create_sampler(&SamplerDescriptor {
+                label: Some("post_sampler"),
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                ..Default::default()
+            }),
+            uniform: params.create_init_wgpu_buff(device),
+        }
+    }
+
+    fn bind_group(
+        &self,
+        device: &Device,
+        source: &TextureView,
+        extra: Option<&TextureView>,
+    ) -> Option<BindGroup> {
+        let resources = self.resources.as_ref()?;
+        let mut entries = vec![
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(source),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Sampler(&resources.sampler),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: resources.uniform.as_entire_binding(),
+            },
+        ];
+        if let Some(extra) = extra {
+            entries.push(BindGroupEntry {
+                binding: 3,
+                resource: BindingResource::TextureView(extra),
+            });
+        }
+        Some(device.create_bind_group(&BindGroupDescriptor {
+            label: Some("post_bind_group"),
+            layout: &resources.layout,
+            entries: &entries,
+        }))
+    }
+
+    fn draw(
+        &self,
+        encoder: &mut CommandEncoder,
+        target: &TextureView,
+        bind_group: &BindGroup,
+        load: LoadOp<Color>,
+    ) {
+        let resources = match &self.resources {
+            Some(it) => it,
+            None => return,
+        };
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Post Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations { load, store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&resources.render_pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// Attachments a post-processing pass reads from and writes into, `index`
+/// is the position of its effect in [`PostSettings::effects`].
+struct PostTarget {
+    index: usize,
+    input: [&'static str; 1],
+    output: [&'static str; 1],
+}
+
+impl PostTarget {
+    fn views<'a>(&self, attachments: &'a Attachments) -> (&'a TextureView, &'a TextureView) {
+        (
+            attachments.view(self.input[0]).unwrap(),
+            attachments.view(self.output[0]).unwrap(),
+        )
+    }
+}
+
+/// Adds passes of the post-processing chain to `graph`, the first one reads
+/// [`HDR`] and the last one writes the `format` surface.
+///
+/// Without effects a pass copying the image to the surface is added.
+pub fn add_post_passes(
+    graph: &mut RenderGraph<Renderer<'static>>,
+    settings: &PostSettings,
+    format: TextureFormat,
+) {
+    if settings.effects.len() > MAX_POST_EFFECTS {
+        tracing::warn!(
+            "only the first {} of {} post effects are applied",
+            MAX_POST_EFFECTS,
+            settings.effects.len()
+        );
+    }
+    let effects = &settings.effects[..settings.effects.len().min(MAX_POST_EFFECTS)];
+    if effects.is_empty() {
+        let settings = TonemapSettings {
+            tonemapper: Tonemapper::Linear,
+            exposure: 1.0,
+        };
+        let target = PostTarget {
+            index: usize::MAX,
+            input: [HDR],
+            output: [SURFACE],
+        };
+        graph.add_pass("present", TonemapPass::new(target, settings, format));
+        return;
+    }
+
+    for (index, effect) in effects.iter().enumerate() {
+        let input = match index {
+            0 => HDR,
+            _ => TARGETS[index - 1],
+        };
+        let last = index == effects.len() - 1;
+        let (output, format) = if last {
+            (SURFACE, format)
+        } else {
+            graph.add_attachment(TARGETS[index], AttachmentDescriptor::new(HDR_FORMAT));
+            (TARGETS[index], HDR_FORMAT)
+        };
+
+        let target = PostTarget {
+            index,
+            input: [input],
+            output: [output],
+        };
+        match effect {
+            PostEffect::Bloom(settings) => {
+                graph.add_pass(effect.name(), BloomPass::new(target, *settings, format))
+            }
+            PostEffect::Tonemap(settings) => {
+                graph.add_pass(effect.name(), TonemapPass::new(target, *settings, format))
+            }
+            PostEffect::Fxaa(settings) => {
+                graph.add_pass(effect.name(), FxaaPass::new(target, *settings, format))
+            }
+            PostEffect::ColorGrade(settings) => graph.add_pass(
+                effect.name(),
+                ColorGradePass::new(target, settings.clone(), format),
+            ),
+        }
+    }
+}
+
+/// Exposure and tonemapping of HDR colors.
+pub struct TonemapPass {
+    target: PostTarget,
+    settings: TonemapSettings,
+    pipeline: PostPipeline,
+    bind_group: Option<BindGroup>,
+}
+
+impl TonemapPass {
+    fn new(target: PostTarget, settings: TonemapSettings, format: TextureFormat) -> Self {
+        TonemapPass {
+            target,
+            settings,
+            pipeline: PostPipeline::new(tonemap_pipeline(format), None),
+            bind_group: None,
+        }
+    }
+}
+
+impl GraphPass<Renderer<'static>> for TonemapPass {
+    fn reads(&self) -> &[&'static str] {
+        &self.target.input
+    }
+
+    fn writes(&self) -> &[&'static str] {
+        &self.target.output
+    }
+
+    fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        renderer: &mut Renderer<'static>,
+        attachments: &Attachments,
+    ) {
+        if let Some(PostEffect::Tonemap(settings)) = renderer.post.effects.get(self.target.index) {
+            self.settings = *settings;
+        }
+        self.pipeline
+            .prepare(device, queue, &TonemapUniform::from(&self.settings));
+        let (input, _) = self.target.views(attachments);
+        self.bind_group = self.pipeline.bind_group(device, input, None);
+    }
+
+    fn execute(
+        &mut self,
+        _: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        attachments: &Attachments,
+    ) {
+        if let Some(bind_group) = &self.bind_group {
+            let (_, output) = self.target.views(attachments);
+            self.pipeline
+                .draw(encoder, output, bind_group, LoadOp::Clear(Color::BLACK));
+        }
+    }
+}
+
+/// Smooths out aliased edges.
+pub struct FxaaPass {
+    target: PostTarget,
+    settings: FxaaSettings,
+    pipeline: PostPipeline,
+    bind_group: Option<BindGroup>,
+}
+
+impl FxaaPass {
+    fn new(target: PostTarget, settings: FxaaSettings, format: TextureFormat) -> Self {
+        FxaaPass {
+            target,
+            settings,
+            pipeline: PostPipeline::new(fxaa_pipeline(format), None),
+            bind_group: None,
+        }
+    }
+}
+
+impl GraphPass<Renderer<'static>> for FxaaPass {
+    fn reads(&self) -> &[&'static str] {
+        &self.target.input
+    }
+
+    fn writes(&self) -> &[&'static str] {
+        &self.target.output
+    }
+
+    fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        renderer: &mut Renderer<'static>,
+        attachments: &Attachments,
+    ) {
+        if let Some(PostEffect::Fxaa(settings)) = renderer.post.effects.get(self.target.index) {
+            self.settings = *settings;
+        }
+        self.pipeline
+            .prepare(device, queue, &FxaaUniform::from(&self.settings));
+        let (input, _) = self.target.views(attachments);
+        self.bind_group = self.pipeline.bind_group(device, input, None);
+    }
+
+    fn execute(
+        &mut self,
+        _: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        attachments: &Attachments,
+    ) {
+        if let Some(bind_group) = &self.bind_group {
+            let (_, output) = self.target.views(attachments);
+            self.pipeline
+                .draw(encoder, output, bind_group, LoadOp::Clear(Color::BLACK));
+        }
+    }
+}
+
+/// Remaps colors through a [`Lut`].
+pub struct ColorGradePass {
+    target: PostTarget,
+    settings: ColorGradeSettings,
+    pipeline: PostPipeline,
+    /// Uploaded table, replaced when the settings get a different one.
+    lut: Option<(Lut, TextureView)>,
+    bind_group: Option<BindGroup>,
+}
+
+impl ColorGradePass {
+    fn new(target: PostTarget, settings: ColorGradeSettings, format: TextureFormat) -> Self {
+        ColorGradePass {
+            target,
+            settings,
+            pipeline: PostPipeline::new(
+                color_grade_pipeline(format),
+                Some(TextureViewDimension::D3),
+            ),
+            lut: None,
+            bind_group: None,
+        }
+    }
+
+    fn upload_lut(device: &Device, queue: &Queue, lut: &Lut) -> TextureView {
+        let size = Extent3d {
+            width: lut.size,
+            height: lut.size,
+            depth_or_array_layers: lut.size,
+        };
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("color_grade_lut"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format: TextureFormat::Rgba8Unorm,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        });
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            &lut.data,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(lut.size * 4),
+                rows_per_image: std::num::NonZeroU32::new(lut.size),
+            },
+            size,
+        );
+        texture.create_view(&TextureViewDescriptor::default())
+    }
+}
+
+impl GraphPass<Renderer<'static>> for ColorGradePass {
+    fn reads(&self) -> &[&'static str] {
+        &self.target.input
+    }
+
+    fn writes(&self) -> &[&'static str] {
+        &self.target.output
+    }
+
+    fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        renderer: &mut Renderer<'static>,
+        attachments: &Attachments,
+    ) {
+        if let Some(PostEffect::ColorGrade(settings)) = renderer.post.effects.get(self.target.index)
+        {
+            if *settings != self.settings {
+                self.settings = settings.clone();
+            }
+        }
+        if !matches!(&self.lut, Some((lut, _)) if *lut == self.settings.lut) {
+            let view = Self::upload_lut(device, queue, &self.settings.lut);
+            self.lut = Some((self.settings.lut.clone(), view));
+        }
+        self.pipeline
+            .prepare(device, queue, &ColorGradeUniform::from(&self.settings));
+
+        let (input, _) = self.target.views(attachments);
+        let lut = self.lut.as_ref().map(|(_, view)| view);
+        self.bind_group = self.pipeline.bind_group(device, input, lut);
+    }
+
+    fn execute(
+        &mut self,
+        _: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        attachments: &Attachments,
+    ) {
+        if let Some(bind_group) = &self.bind_group {
+            let (_, output) = self.target.views(attachments);
+            self.pipeline
+                .draw(encoder, output, bind_group, LoadOp::Clear(Color::BLACK));
+        }
+    }
+}
+
+/// Chain of half sized textures bright parts of the image are blurred in.
+struct BloomLevels {
+    size: (u32, u32),
+    /// Levels asked for, there are fewer if the image gets to a single pixel
+    /// before.
+    requested: u32,
+    views: Vec<TextureView>,
+}
+
+impl BloomLevels {
+    fn new(device: &Device, size: (u32, u32), levels: u32) -> Self {
+        let mut views = Vec::new();
+        let (mut width, mut height) = size;
+        for _ in 0..levels.max(1) {
+            width = (width / 2).max(1);
+            height = (height / 2).max(1);
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some("bloom_level"),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: HDR_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            });
+            views.push(texture.create_view(&TextureViewDescriptor::default()));
+            if width == 1 && height == 1 {
+                break;
+            }
+        }
+        BloomLevels {
+            size,
+            requested: levels,
+            views,
+        }
+    }
+}
+
+/// Makes bright parts of the image glow.
+pub struct BloomPass {
+    target: PostTarget,
+    settings: BloomSettings,
+    prefilter: PostPipeline,
+    downsample: PostPipeline,
+    upsample: PostPipeline,
+    composite: PostPipeline,
+    levels: Option<BloomLevels>,
+    /// Prefilter, downsample, upsample and composite bind groups in the
+    /// order they're drawn.
+    bind_groups: Vec<BindGroup>,
+}
+
+impl BloomPass {
+    fn new(target: PostTarget, settings: BloomSettings, format: TextureFormat) -> Self {
+        let pipeline = |stage| PostPipeline::new(bloom_pipeline(stage, format), None);
+        BloomPass {
+            target,
+            settings,
+            prefilter: pipeline(BloomStage::Prefilter),
+            downsample: pipeline(BloomStage::Downsample),
+            upsample: pipeline(BloomStage::Upsample),
+            composite: PostPipeline::new(
+                bloom_pipeline(BloomStage::Composite, format),
+                Some(TextureViewDimension::D2),
+            ),
+            levels: None,
+            bind_groups: Vec::new(),
+        }
+    }
+}
+
+impl GraphPass<Renderer<'static>> for BloomPass {
+    fn reads(&self) -> &[&'static str] {
+        &self.target.input
+    }
+
+    fn writes(&self) -> &[&'static str] {
+        &self.target.output
+    }
+
+    fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        renderer: &mut Renderer<'static>,
+        attachments: &Attachments,
+    ) {
+        if let Some(PostEffect::Bloom(settings)) = renderer.post.effects.get(self.target.index) {
+            self.settings = *settings;
+        }
+        let params = BloomUniform::from(&self.settings);
+        for pipeline in [
+            &mut self.prefilter,
+            &mut self.downsample,
+            &mut self.upsample,
+            &mut self.composite,
+        ] {
+            pipeline.prepare(device, queue, &params);
+        }
+
+        let outdated = match &self.levels {
+            Some(levels) => {
+                levels.size != renderer.size || levels.requested != self.settings.levels
+            }
+            None => true,
+        };
+        if outdated {
+            self.levels = Some(BloomLevels::new(
+                device,
+                renderer.size,
+                self.settings.levels,
+            ));
+        }
+        let levels = &self.levels.as_ref().unwrap().views;
+
+        let (input, _) = self.target.views(attachments);
+        let mut bind_groups = Vec::new();
+        bind_groups.extend(self.prefilter.bind_group(device, input, None));
+        for source in &levels[..levels.len() - 1] {
+            bind_groups.extend(self.downsample.bind_group(device, source, None));
+        }
+        for source in levels[1..].iter().rev() {
+            bind_groups.extend(self.upsample.bind_group(device, source, None));
+        }
+        bind_groups.extend(self.composite.bind_group(device, input, Some(&levels[0])));
+        self.bind_groups = bind_groups;
+    }
+
+    fn execute(
+        &mut self,
+        _: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        attachments: &Attachments,
+    ) {
+        let levels = match &self.levels {
+            Some(levels) => &levels.views,
+            None => return,
+        };
+        // prefilter, downsamples and upsamples between levels and composite
+        if self.bind_groups.len() != levels.len() * 2 {
+            return;
+        }
+        let clear = LoadOp::Clear(Color::BLACK);
+
+        let mut bind_groups = self.bind_groups.iter();
+        self.prefilter
+            .draw(encoder, &levels[0], bind_groups.next().unwrap(), clear);
+        for target in &levels[1..] {
+            self.downsample
+                .draw(encoder, target, bind_groups.next().unwrap(), clear);
+        }
+        for target in levels[..levels.len() - 1].iter().rev() {
+            self.upsample
+                .draw(encoder, target, bind_groups.next().unwrap(), LoadOp::Load);
+        }
+        let (_, output) = self.target.views(attachments);
+        self.composite
+            .draw(encoder, output, bind_groups.next().unwrap(), clear);
+    }
+}
@@ -1,20 +1,58 @@
+pub mod preprocessor;
+pub mod validation;
+pub mod watch;
+
+pub use watch::load_source;
+
+use std::{
+    borrow::Cow,
+    io::{Read, Write},
+};
+
+use byteorder::LE;
 use wgpu::{Device, ShaderModule};
 
-use crate::util::CowStr;
+use crate::{
+    content::resouces::Storable,
+    error::{FormatError, PreprocessError, ResourceError, ShaderError},
+    util::CowStr,
+};
+
+use self::{
+    preprocessor::{Preprocessor, SourceMap},
+    watch::WatchedFile,
+};
 
 pub trait ShaderSource {
     fn create_shader_module(&self, device: &wgpu::Device) -> wgpu::ShaderModule;
+
+    /// Parses and validates the shader on the CPU.
+    fn validate(&self) -> Result<naga::Module, ShaderError>;
+
+    /// Returns the new source if the shader changed since the last call.
+    fn poll_changes(&mut self) -> Option<Result<Self, ShaderError>>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct WgslSource<'a> {
     pub source: CowStr<'a>,
+    /// Maps lines of preprocessed sources back to original files.
+    pub source_map: Option<SourceMap>,
+    /// File the source is reloaded from when it changes.
+    pub watch: Option<WatchedFile>,
 }
 
 impl WgslSource<'static> {
     pub const fn new_static(source: &'static str) -> Self {
         WgslSource {
-            source: source.into(),
+            source: Cow::Borrowed(source),
+            source_map: None,
+            watch: None,
         }
     }
 }
@@ -23,17 +61,83 @@ impl<'a> WgslSource<'a> {
     pub fn new(source: impl Into<CowStr<'a>>) -> Self {
         WgslSource {
             source: source.into(),
+            source_map: None,
+            watch: None,
         }
     }
+
+    /// Loads the source from a file that's reloaded when it changes.
+    pub fn from_file(file: WatchedFile) -> Result<WgslSource<'static>, ShaderError> {
+        file.load()
+    }
+
+    /// Expands preprocessor directives in the source, `file` is used to name
+    /// the source in errors and the [`SourceMap`].
+    pub fn preprocess(
+        &self,
+        file: &str,
+        preprocessor: &Preprocessor,
+    ) -> Result<WgslSource<'static>, PreprocessError> {
+        let (source, source_map) = preprocessor.process(file, &self.source)?;
+
+        Ok(WgslSource {
+            source: source.into(),
+            source_map: Some(source_map),
+            watch: None,
+        })
+    }
 }
 
 impl<'a> ShaderSource for WgslSource<'a> {
     fn create_shader_module(&self, device: &Device) -> ShaderModule {
         device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(self.source.into()),
+            source: wgpu::ShaderSource::Wgsl(self.source.clone()),
         })
     }
+
+    fn validate(&self) -> Result<naga::Module, ShaderError> {
+        validation::validate_wgsl(&self.source, self.source_map.as_ref())
+    }
+
+    fn poll_changes(&mut self) -> Option<Result<Self, ShaderError>> {
+        let watch = self.watch.as_mut()?;
+        if !watch.poll() {
+            return None;
+        }
+
+        Some(watch.clone().load())
+    }
+}
+
+/// WGSL source stored in a resource bank so it can be included by shaders.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShaderResource {
+    pub source: String,
+}
+
+impl Storable for ShaderResource {
+    fn save<W: Write>(&self, out: &mut W) -> Result<(), ResourceError> {
+        use byteorder::WriteBytesExt;
+
+        out.write_u32::<LE>(self.source.len() as u32)?;
+        out.write_all(self.source.as_bytes())?;
+        Ok(())
+    }
+
+    fn load<R: Read>(source: &mut R) -> Result<Self, ResourceError> {
+        use byteorder::ReadBytesExt;
+
+        let len = source.read_u32::<LE>()?;
+        let mut data = vec![0; len as usize];
+        source.read_exact(data.as_mut_slice())?;
+
+        let source = String::from_utf8(data).map_err(|_| FormatError::InvalidEncoding)?;
+        Ok(ShaderResource { source })
+    }
 }
 
+/// Bindings shared by mesh pipelines, included as `common.wgsl`.
+pub static COMMON_WGSL: &str = include_str!("common.wgsl");
+
 pub static DEV_SHADER: WgslSource<'static> = WgslSource::new_static(include_str!("shader.wgsl"));
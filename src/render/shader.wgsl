@@ -1,9 +1,7 @@
 // Vertex shader
 
-struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) color: vec3<f32>,
-};
+#include "common.wgsl"
+#include "dev_vertex.wgsl"
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
@@ -12,11 +10,11 @@ struct VertexOutput {
 
 @vertex
 fn vs_main(
-    model: VertexInput,
+    model: DevVertexData,
 ) -> VertexOutput {
     var out: VertexOutput;
     out.color = model.color;
-    out.clip_position = vec4<f32>(model.position, 1.0);
+    out.clip_position = camera.view_projection * mesh.model * vec4<f32>(model.position, 1.0);
     return out;
 }
 
@@ -0,0 +1,365 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    path::PathBuf,
+};
+
+use crate::{
+    content::resouces::{AnyResource, ResourceBank},
+    error::PreprocessError,
+    util::CowStr,
+};
+
+/// Position of a line in one of the original (unprocessed) shader files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    pub file: String,
+    /// Line number, starting at 1.
+    pub line: usize,
+}
+
+impl SourceLocation {
+    pub fn new(file: impl Into<String>, line: usize) -> Self {
+        SourceLocation {
+            file: file.into(),
+            line,
+        }
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Maps lines of preprocessed source back to files they came from.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceMap(Vec<SourceLocation>);
+
+impl SourceMap {
+    /// Returns location of a (1 based) line in preprocessed source.
+    pub fn location(&self, line: usize) -> Option<&SourceLocation> {
+        self.0.get(line.checked_sub(1)?)
+    }
+
+    /// Returns names of files that contributed lines to the source.
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        let mut seen = HashSet::new();
+        self.0
+            .iter()
+            .map(|location| location.file.as_str())
+            .filter(move |file| seen.insert(*file))
+    }
+
+    /// Returns location of a byte offset into preprocessed `source`.
+    pub fn location_of_offset(&self, source: &str, offset: usize) -> Option<&SourceLocation> {
+        let line = source.get(..offset)?.matches('\n').count() + 1;
+        self.location(line)
+    }
+}
+
+/// Provides sources of files shaders can `#include`.
+pub trait IncludeSource {
+    fn read(&self, path: &str) -> Option<CowStr<'_>>;
+}
+
+/// In-memory collection of shader files.
+#[derive(Debug, Default, Clone)]
+pub struct VirtualFiles {
+    files: HashMap<String, CowStr<'static>>,
+}
+
+impl VirtualFiles {
+    pub fn new() -> Self {
+        VirtualFiles::default()
+    }
+
+    pub fn insert(&mut self, path: impl Into<String>, source: impl Into<CowStr<'static>>) {
+        self.files.insert(path.into(), source.into());
+    }
+
+    pub fn with(mut self, path: impl Into<String>, source: impl Into<CowStr<'static>>) -> Self {
+        self.insert(path, source);
+        self
+    }
+}
+
+impl IncludeSource for VirtualFiles {
+    fn read(&self, path: &str) -> Option<CowStr<'_>> {
+        self.files.get(path).map(|source| source.as_ref().into())
+    }
+}
+
+/// Files in a directory on disk.
+#[derive(Debug, Clone)]
+pub struct Directory(PathBuf);
+
+impl Directory {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Directory(path.into())
+    }
+
+    /// Returns the path of an included file if it exists in this directory.
+    pub fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let path = self.0.join(path);
+        path.is_file().then_some(path)
+    }
+}
+
+impl IncludeSource for Directory {
+    fn read(&self, path: &str) -> Option<CowStr<'_>> {
+        let path = self.resolve(path)?;
+        std::fs::read_to_string(path).ok().map(Into::into)
+    }
+}
+
+impl IncludeSource for ResourceBank<AnyResource> {
+    fn read(&self, path: &str) -> Option<CowStr<'_>> {
+        match self.get(path)? {
+            AnyResource::Shader(shader) => Some(shader.source.as_str().into()),
+            _ => None,
+        }
+    }
+}
+
+/// Expands `#include`, `#define`, `#undef`, `#ifdef`, `#ifndef`, `#else` and
+/// `#endif` directives in WGSL sources.
+///
+/// Every file is included at most once, so shared declarations can be
+/// included by any file that needs them.
+pub struct Preprocessor<'f> {
+    files: Vec<&'f dyn IncludeSource>,
+    defines: HashMap<String, String>,
+}
+
+struct Conditional {
+    location: SourceLocation,
+    active: bool,
+    parent_active: bool,
+    has_else: bool,
+}
+
+struct ProcessState {
+    output: String,
+    source_map: SourceMap,
+    defines: HashMap<String, String>,
+    included: HashSet<String>,
+    stack: Vec<String>,
+}
+
+impl<'f> Preprocessor<'f> {
+    pub fn new() -> Self {
+        Preprocessor {
+            files: Vec::new(),
+            defines: HashMap::new(),
+        }
+    }
+
+    /// Adds a source of included files. Sources are searched in the order
+    /// they were added in.
+    pub fn with_files(mut self, files: &'f dyn IncludeSource) -> Self {
+        self.files.push(files);
+        self
+    }
+
+    pub fn define(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.defines.insert(name.into(), value.into());
+    }
+
+    pub fn with_define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.define(name, value);
+        self
+    }
+
+    pub fn undefine(&mut self, name: &str) {
+        self.defines.remove(name);
+    }
+
+    /// Processes `source` of file called `file` and returns the expanded
+    /// source along with a map of its lines.
+    pub fn process(
+        &self,
+        file: &str,
+        source: &str,
+    ) -> Result<(String, SourceMap), PreprocessError> {
+        let mut state = ProcessState {
+            output: String::with_capacity(source.len()),
+            source_map: SourceMap::default(),
+            defines: self.defines.clone(),
+            included: HashSet::new(),
+            stack: Vec::new(),
+        };
+        state.included.insert(file.to_string());
+
+        self.process_file(&mut state, file, source)?;
+
+        Ok((state.output, state.source_map))
+    }
+
+    fn read(&self, path: &str) -> Option<CowStr<'f>> {
+        self.files.iter().find_map(|files| files.read(path))
+    }
+
+    fn process_file(
+        &self,
+        state: &mut ProcessState,
+        file: &str,
+        source: &str,
+    ) -> Result<(), PreprocessError> {
+        state.stack.push(file.to_string());
+        let mut conditionals: Vec<Conditional> = Vec::new();
+
+        for (i, line) in source.lines().enumerate() {
+            let location = SourceLocation::new(file, i + 1);
+            let active = conditionals.last().map(|c| c.active).unwrap_or(true);
+
+            let directive = match line.trim_start().strip_prefix('#') {
+                Some(directive) => directive.trim(),
+                None => {
+                    if active {
+                        state.output += &substitute(line, &state.defines);
+                        state.output.push('\n');
+                        state.source_map.0.push(location);
+                    }
+                    continue;
+                }
+            };
+
+            let (name, args) = directive
+                .split_once(char::is_whitespace)
+                .map(|(name, args)| (name, args.trim()))
+                .unwrap_or((directive, ""));
+
+            match name {
+                "ifdef" | "ifndef" => {
+                    let defined = state.defines.contains_key(identifier(&location, args)?);
+                    conditionals.push(Conditional {
+                        location,
+                        active: active && defined == (name == "ifdef"),
+                        parent_active: active,
+                        has_else: false,
+                    });
+                }
+                "else" => match conditionals.last_mut() {
+                    Some(c) if !c.has_else => {
+                        c.active = c.parent_active && !c.active;
+                        c.has_else = true;
+                    }
+                    _ => {
+                        return Err(PreprocessError::UnmatchedDirective(
+                            location,
+                            name.to_string(),
+                        ))
+                    }
+                },
+                "endif" => {
+                    if conditionals.pop().is_none() {
+                        return Err(PreprocessError::UnmatchedDirective(
+                            location,
+                            name.to_string(),
+                        ));
+                    }
+                }
+                _ if !active => {}
+                "define" => {
+                    let (define, value) = args
+                        .split_once(char::is_whitespace)
+                        .map(|(name, value)| (name, value.trim()))
+                        .unwrap_or((args, ""));
+                    let define = identifier(&location, define)?;
+                    state.defines.insert(define.to_string(), value.to_string());
+                }
+                "undef" => {
+                    state.defines.remove(identifier(&location, args)?);
+                }
+                "include" => {
+                    let path = args
+                        .strip_prefix('"')
+                        .and_then(|it| it.strip_suffix('"'))
+                        .ok_or_else(|| {
+                            PreprocessError::MalformedDirective(
+                                location.clone(),
+                                "expected a quoted path".to_string(),
+                            )
+                        })?;
+
+                    if state.stack.iter().any(|it| it == path) {
+                        return Err(PreprocessError::IncludeCycle(location, path.to_string()));
+                    }
+                    if !state.included.insert(path.to_string()) {
+                        continue;
+                    }
+
+                    let included = self.read(path).ok_or_else(|| {
+                        PreprocessError::IncludeNotFound(location, path.to_string())
+                    })?;
+                    self.process_file(state, path, &included)?;
+                }
+                other => {
+                    return Err(PreprocessError::UnknownDirective(
+                        location,
+                        other.to_string(),
+                    ))
+                }
+            }
+        }
+
+        if let Some(unterminated) = conditionals.pop() {
+            return Err(PreprocessError::UnterminatedConditional(
+                unterminated.location,
+            ));
+        }
+
+        state.stack.pop();
+        Ok(())
+    }
+}
+
+impl<'f> Default for Preprocessor<'f> {
+    fn default() -> Self {
+        Preprocessor::new()
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn identifier<'a>(location: &SourceLocation, value: &'a str) -> Result<&'a str, PreprocessError> {
+    let starts_with_digit = value.starts_with(|c: char| c.is_ascii_digit());
+    if value.is_empty() || starts_with_digit || !value.chars().all(is_identifier_char) {
+        return Err(PreprocessError::MalformedDirective(
+            location.clone(),
+            format!("'{}' is not a valid identifier", value),
+        ));
+    }
+    Ok(value)
+}
+
+/// Replaces defined identifiers in a line with their values.
+fn substitute(line: &str, defines: &HashMap<String, String>) -> String {
+    if defines.is_empty() {
+        return line.to_string();
+    }
+
+    let mut result = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find(|c: char| is_identifier_char(c)) {
+        result += &rest[..start];
+        rest = &rest[start..];
+
+        let end = rest
+            .find(|c: char| !is_identifier_char(c))
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+        match defines.get(word) {
+            Some(value) if !word.starts_with(|c: char| c.is_ascii_digit()) => result += value,
+            _ => result += word,
+        }
+        rest = &rest[end..];
+    }
+    result += rest;
+
+    result
+}
@@ -0,0 +1,206 @@
+use naga::{
+    valid::{Capabilities, ValidationFlags, Validator},
+    Binding, Handle, Module, ScalarKind, ShaderStage, Type, TypeInner,
+};
+use wgpu::{VertexBufferLayout, VertexFormat};
+
+use crate::error::ShaderError;
+
+use super::preprocessor::{SourceLocation, SourceMap};
+
+/// Error message pointing to a line in the original shader source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub location: Option<SourceLocation>,
+}
+
+impl Diagnostic {
+    fn new(
+        message: String,
+        offset: Option<u32>,
+        source: &str,
+        source_map: Option<&SourceMap>,
+    ) -> Self {
+        let location = offset.and_then(|offset| {
+            let offset = offset as usize;
+            match source_map {
+                Some(map) => map.location_of_offset(source, offset).cloned(),
+                None => {
+                    let line = source.get(..offset)?.matches('\n').count() + 1;
+                    Some(SourceLocation::new("<wgsl>", line))
+                }
+            }
+        });
+
+        Diagnostic { message, location }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}", location, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Parses and validates WGSL `source` on the CPU.
+pub fn validate_wgsl(source: &str, source_map: Option<&SourceMap>) -> Result<Module, ShaderError> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|err| {
+        let offset = err.location(source).map(|it| it.offset);
+        ShaderError::Parse(Diagnostic::new(
+            err.message().to_string(),
+            offset,
+            source,
+            source_map,
+        ))
+    })?;
+
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|err| {
+            let offset = err.location(source).map(|it| it.offset);
+            let mut message = err.to_string();
+            let mut cause = std::error::Error::source(err.as_inner());
+            while let Some(inner) = cause {
+                message += &format!(": {}", inner);
+                cause = inner.source();
+            }
+            ShaderError::Validation(Diagnostic::new(message, offset, source, source_map))
+        })?;
+
+    Ok(module)
+}
+
+/// Checks that `module` declares an entry point called `name` for `stage`.
+pub fn check_entry_point(
+    module: &Module,
+    name: &str,
+    stage: ShaderStage,
+) -> Result<(), ShaderError> {
+    if module
+        .entry_points
+        .iter()
+        .any(|ep| ep.name == name && ep.stage == stage)
+    {
+        Ok(())
+    } else {
+        Err(ShaderError::MissingEntryPoint(name.to_string(), stage))
+    }
+}
+
+/// Checks that every `@location` input of vertex entry point `name` is
+/// provided by one of the `buffers` with a compatible format.
+pub fn check_vertex_inputs(
+    module: &Module,
+    name: &str,
+    buffers: &[VertexBufferLayout],
+) -> Result<(), ShaderError> {
+    check_entry_point(module, name, ShaderStage::Vertex)?;
+    let entry_point = module
+        .entry_points
+        .iter()
+        .find(|ep| ep.name == name && ep.stage == ShaderStage::Vertex)
+        .unwrap();
+
+    let mut inputs = Vec::new();
+    for argument in &entry_point.function.arguments {
+        collect_inputs(module, argument.ty, argument.binding.as_ref(), &mut inputs);
+    }
+
+    for (location, ty) in inputs {
+        let provided = buffers
+            .iter()
+            .flat_map(|buffer| buffer.attributes.iter())
+            .find(|attribute| attribute.shader_location == location)
+            .ok_or(ShaderError::MissingVertexAttribute(location))?
+            .format;
+
+        let expected = &module.types[ty].inner;
+        if input_signature(expected) != Some(format_signature(provided)) {
+            return Err(ShaderError::VertexFormatMismatch {
+                location,
+                expected: type_name(expected),
+                provided,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_inputs(
+    module: &Module,
+    ty: Handle<Type>,
+    binding: Option<&Binding>,
+    inputs: &mut Vec<(u32, Handle<Type>)>,
+) {
+    match binding {
+        Some(Binding::Location { location, .. }) => inputs.push((*location, ty)),
+        Some(Binding::BuiltIn(_)) => {}
+        None => {
+            if let TypeInner::Struct { members, .. } = &module.types[ty].inner {
+                for member in members {
+                    collect_inputs(module, member.ty, member.binding.as_ref(), inputs);
+                }
+            }
+        }
+    }
+}
+
+fn input_signature(ty: &TypeInner) -> Option<(ScalarKind, u32, u8)> {
+    match *ty {
+        TypeInner::Scalar { kind, width } => Some((kind, 1, width)),
+        TypeInner::Vector { size, kind, width } => Some((kind, size as u32, width)),
+        _ => None,
+    }
+}
+
+fn type_name(ty: &TypeInner) -> String {
+    let scalar = |kind, width| match (kind, width) {
+        (ScalarKind::Sint, _) => "i32",
+        (ScalarKind::Uint, _) => "u32",
+        (ScalarKind::Float, 8) => "f64",
+        (ScalarKind::Float, _) => "f32",
+        (ScalarKind::Bool, _) => "bool",
+    };
+
+    match *ty {
+        TypeInner::Scalar { kind, width } => scalar(kind, width).to_string(),
+        TypeInner::Vector { size, kind, width } => {
+            format!("vec{}<{}>", size as u32, scalar(kind, width))
+        }
+        ref other => format!("{:?}", other),
+    }
+}
+
+/// Returns the scalar kind, component count and scalar width a vertex format
+/// is seen as by shaders.
+fn format_signature(format: VertexFormat) -> (ScalarKind, u32, u8) {
+    use VertexFormat::*;
+
+    match format {
+        Uint8x2 | Uint16x2 | Uint32x2 => (ScalarKind::Uint, 2, 4),
+        Uint8x4 | Uint16x4 | Uint32x4 => (ScalarKind::Uint, 4, 4),
+        Sint8x2 | Sint16x2 | Sint32x2 => (ScalarKind::Sint, 2, 4),
+        Sint8x4 | Sint16x4 | Sint32x4 => (ScalarKind::Sint, 4, 4),
+        Unorm8x2 | Snorm8x2 | Unorm16x2 | Snorm16x2 | Float16x2 | Float32x2 => {
+            (ScalarKind::Float, 2, 4)
+        }
+        Unorm8x4 | Snorm8x4 | Unorm16x4 | Snorm16x4 | Float16x4 | Float32x4 => {
+            (ScalarKind::Float, 4, 4)
+        }
+        Float32 => (ScalarKind::Float, 1, 4),
+        Float32x3 => (ScalarKind::Float, 3, 4),
+        Uint32 => (ScalarKind::Uint, 1, 4),
+        Uint32x3 => (ScalarKind::Uint, 3, 4),
+        Sint32 => (ScalarKind::Sint, 1, 4),
+        Sint32x3 => (ScalarKind::Sint, 3, 4),
+        Float64 => (ScalarKind::Float, 1, 8),
+        Float64x2 => (ScalarKind::Float, 2, 8),
+        Float64x3 => (ScalarKind::Float, 3, 8),
+        Float64x4 => (ScalarKind::Float, 4, 8),
+    }
+}
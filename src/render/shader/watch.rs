@@ -0,0 +1,137 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::error::ShaderError;
+
+use super::{
+    preprocessor::{Directory, Preprocessor, VirtualFiles},
+    ShaderSource, WgslSource,
+};
+
+/// Directory shaders embedded in the renderer are loaded from in hot-reload
+/// builds.
+#[cfg(feature = "hot-reload")]
+const SHADER_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/render");
+
+/// Loads the renderer shader `file` that can include `files`.
+///
+/// Hot-reload builds read it from the source directory and reload it when it
+/// or files it includes from there change, other builds preprocess the
+/// `embedded` copy. The loaded source is validated so broken shaders are
+/// reported with naga diagnostics before they reach the device.
+pub fn load_source(
+    file: &str,
+    embedded: &WgslSource<'static>,
+    files: VirtualFiles,
+) -> Result<WgslSource<'static>, ShaderError> {
+    #[cfg(feature = "hot-reload")]
+    let source = {
+        let _ = embedded;
+        WatchedFile::new(Path::new(SHADER_DIRECTORY).join(file))
+            .with_files(files)
+            .load()?
+    };
+    #[cfg(not(feature = "hot-reload"))]
+    let source = embedded.preprocess(file, &Preprocessor::new().with_files(&files))?;
+
+    source.validate()?;
+    Ok(source)
+}
+
+/// Shader file on disk that's polled for changes during development.
+///
+/// Files it includes from its directory are watched as well.
+#[derive(Debug, Clone)]
+pub struct WatchedFile {
+    path: PathBuf,
+    files: VirtualFiles,
+    defines: HashMap<String, String>,
+    timestamps: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl WatchedFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        WatchedFile {
+            path: path.into(),
+            files: VirtualFiles::new(),
+            defines: HashMap::new(),
+            timestamps: Vec::new(),
+        }
+    }
+
+    /// Sets in-memory files the shader can include, files on disk next to
+    /// the shader are included instead if they exist.
+    pub fn with_files(mut self, files: VirtualFiles) -> Self {
+        self.files = files;
+        self
+    }
+
+    pub fn with_define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defines.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads and preprocesses the file, returned source keeps watching it.
+    pub fn load(mut self) -> Result<WgslSource<'static>, ShaderError> {
+        let source = std::fs::read_to_string(&self.path)
+            .map_err(|err| ShaderError::Io(self.path.clone(), err))?;
+
+        let directory = Directory::new(self.path.parent().unwrap_or_else(|| Path::new(".")));
+        let mut preprocessor = Preprocessor::new()
+            .with_files(&directory)
+            .with_files(&self.files);
+        for (name, value) in &self.defines {
+            preprocessor.define(name.as_str(), value.as_str());
+        }
+
+        let file = self.path.to_string_lossy().to_string();
+        let mut result = WgslSource::new(source).preprocess(&file, &preprocessor)?;
+
+        let mut watched = vec![self.path.clone()];
+        if let Some(source_map) = &result.source_map {
+            for path in source_map
+                .files()
+                .filter_map(|file| directory.resolve(file))
+            {
+                if !watched.contains(&path) {
+                    watched.push(path);
+                }
+            }
+        }
+        self.timestamps = watched
+            .into_iter()
+            .map(|path| {
+                let modified = modified(&path);
+                (path, modified)
+            })
+            .collect();
+
+        result.watch = Some(self);
+        Ok(result)
+    }
+
+    /// Returns `true` if any of the watched files changed since the last
+    /// call or [`load`](WatchedFile::load).
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for (path, timestamp) in self.timestamps.iter_mut() {
+            let current = modified(path);
+            if current != *timestamp {
+                *timestamp = current;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|it| it.modified()).ok()
+}
@@ -0,0 +1,417 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3, Vec4};
+use wgpu::*;
+
+use crate::math::Frustum;
+
+use super::{
+    camera::{Camera, CameraUniform},
+    graph::{AttachmentDescriptor, AttachmentSize, Attachments},
+    mesh::{MeshManager, PipelineId},
+    uniform::ShaderUniform,
+};
+
+pub const MAX_CASCADES: usize = 4;
+pub const SHADOW_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+/// Layered depth attachment cascades are rendered into, written by
+/// [`ShadowPass`](super::ShadowPass) and sampled by the main pass.
+pub const SHADOW_MAPS: &str = "shadow_maps";
+
+/// Cascade bindings and functions, included as `cascades.wgsl`.
+pub static CASCADES_WGSL: &str = include_str!("cascades.wgsl");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// Number of shadow maps the view is split into, at most
+    /// [`MAX_CASCADES`].
+    pub cascades: usize,
+    /// Width and height of every cascade shadow map.
+    pub resolution: u32,
+    /// Distance from the camera shadows end at.
+    pub distance: f32,
+    /// Blend between uniform (0) and logarithmic (1) cascade splits.
+    pub split_lambda: f32,
+    /// Constant depth bias of shadow casters, in depth buffer units.
+    pub depth_bias: i32,
+    /// Depth bias of shadow casters scaled by their slope.
+    pub slope_bias: f32,
+    /// Offset of shaded positions along their normal, in shadow map texels.
+    pub normal_bias: f32,
+    /// How far towards the sun geometry outside the view still casts
+    /// shadows into it.
+    pub caster_distance: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            enabled: true,
+            cascades: MAX_CASCADES,
+            resolution: 2048,
+            distance: 256.0,
+            split_lambda: 0.75,
+            depth_bias: 2,
+            slope_bias: 2.0,
+            normal_bias: 1.5,
+            caster_distance: 128.0,
+        }
+    }
+}
+
+impl ShadowSettings {
+    fn cascade_count(&self) -> usize {
+        self.cascades.clamp(1, MAX_CASCADES)
+    }
+
+    /// Attachment holding a layer per cascade.
+    pub fn attachment(&self) -> AttachmentDescriptor {
+        AttachmentDescriptor::new(SHADOW_FORMAT)
+            .with_size(AttachmentSize::Fixed(self.resolution, self.resolution))
+            .with_layers(self.cascade_count() as u32)
+    }
+
+    /// Depth state shadow casters are rendered with.
+    pub fn depth_stencil(&self) -> DepthStencilState {
+        DepthStencilState {
+            format: SHADOW_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState {
+                constant: self.depth_bias,
+                slope_scale: self.slope_bias,
+                clamp: 0.0,
+            },
+        }
+    }
+}
+
+/// View depths cascades end at, mixing uniform and logarithmic splits by
+/// `lambda`.
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let part = i as f32 / count as f32;
+            let uniform = near + (far - near) * part;
+            let logarithmic = near * (far / near).powf(part);
+            uniform + (logarithmic - uniform) * lambda
+        })
+        .collect()
+}
+
+/// Shadow map covering a slice of the view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cascade {
+    pub view_projection: Mat4,
+    /// View depth the cascade ends at, 0 if it isn't fitted to a view.
+    pub far: f32,
+    /// World space size of a shadow map texel.
+    pub texel_size: f32,
+}
+
+impl Cascade {
+    /// Fits an orthographic projection looking along `-sun` around world
+    /// space `corners` of a view slice.
+    ///
+    /// The projection is sized by the bounding sphere of the slice and
+    /// snapped to whole texels, so shadow edges don't shimmer as the camera
+    /// moves and turns.
+    pub fn fit(corners: &[Vec3; 8], sun: Vec3, resolution: u32, caster_distance: f32) -> Self {
+        let center = corners.iter().sum::<Vec3>() / 8.0;
+        let radius = corners
+            .iter()
+            .map(|it| it.distance(center))
+            .fold(0.0, f32::max);
+        let radius = (radius * 16.0).ceil() / 16.0;
+
+        let sun = sun.normalize();
+        let up = if sun.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y };
+        let eye = center + sun * (radius + caster_distance);
+        let view = Mat4::look_at_rh(eye, center, up);
+        let projection = Mat4::orthographic_rh(
+            -radius,
+            radius,
+            -radius,
+            radius,
+            0.0,
+            2.0 * radius + caster_distance,
+        );
+        let view_projection = projection * view;
+
+        let texels = resolution as f32 / 2.0;
+        let origin = view_projection.project_point3(Vec3::ZERO) * texels;
+        let snap = (origin.round() - origin) / texels;
+        let snap = Mat4::from_translation(Vec3::new(snap.x, snap.y, 0.0));
+
+        Cascade {
+            view_projection: snap * view_projection,
+            far: 0.0,
+            texel_size: 2.0 * radius / resolution as f32,
+        }
+    }
+
+    /// Fits cascades to the view of `camera`.
+    pub fn fit_view(camera: &Camera, sun: Vec3, settings: &ShadowSettings) -> Vec<Cascade> {
+        let inverse = camera.view_projection().inverse();
+        let corner = |x: f32, y: f32, z: f32| inverse.project_point3(Vec3::new(x, y, z));
+        // rays along the frustum edges, from the near to the far plane
+        let rays = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .map(|(x, y)| (corner(x, y, 0.0), corner(x, y, 1.0)));
+
+        let depth = |point: Vec3| -camera.view.transform_point3(point).z;
+        let near = depth(rays[0].0);
+        let range = depth(rays[0].1) - near;
+        let far = depth(rays[0].1).min(near + settings.distance);
+        // corners of the view at depth `at`
+        let slice =
+            |at: f32| rays.map(|(start, end)| start + (end - start) * ((at - near) / range));
+
+        let splits = cascade_splits(near, far, settings.cascade_count(), settings.split_lambda);
+        let mut start = near;
+        splits
+            .into_iter()
+            .map(|end| {
+                let [a, b, c, d] = slice(start);
+                let [e, f, g, h] = slice(end);
+                start = end;
+
+                Cascade {
+                    far: end,
+                    ..Cascade::fit(
+                        &[a, b, c, d, e, f, g, h],
+                        sun,
+                        settings.resolution,
+                        settings.caster_distance,
+                    )
+                }
+            })
+            .collect()
+    }
+}
+
+/// Shadow data available to shaders at `@group(2) @binding(0)`.
+#[derive(Debug, Copy, Clone, Pod, Zeroable, ShaderUniform)]
+#[repr(C)]
+pub struct ShadowUniform {
+    /// One per cascade, [`MAX_CASCADES`] long.
+    pub view_projections: [Mat4; 4],
+    /// View depth every cascade ends at.
+    pub splits: Vec4,
+    /// Direction towards the sun in `xyz`.
+    pub sun: Vec4,
+    pub texel_sizes: Vec4,
+    /// 0 disables shadows.
+    pub cascade_count: u32,
+    pub normal_bias: f32,
+    #[padding]
+    pub _padding: [u32; 2],
+}
+
+impl ShadowUniform {
+    pub fn new(cascades: &[Cascade], sun: Vec3, settings: &ShadowSettings) -> Self {
+        let mut uniform = ShadowUniform::zeroed();
+        for (i, cascade) in cascades.iter().take(MAX_CASCADES).enumerate() {
+            uniform.view_projections[i] = cascade.view_projection;
+            uniform.splits[i] = cascade.far;
+            uniform.texel_sizes[i] = cascade.texel_size;
+        }
+        uniform.sun = sun.normalize().extend(0.0);
+        uniform.cascade_count = cascades.len().min(MAX_CASCADES) as u32;
+        uniform.normal_bias = settings.normal_bias;
+        uniform
+    }
+}
+
+pub const SHADOW_BIND_GROUP_LAYOUT: BindGroupLayoutDescriptor<'static> =
+    BindGroupLayoutDescriptor {
+        label: Some("shadow_bind_group_layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX_FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: TextureViewDimension::D2Array,
+                    sample_type: TextureSampleType::Depth,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Comparison),
+                count: None,
+            },
+        ],
+    };
+
+/// Cascades shadow casters are rendered into [`SHADOW_MAPS`] with.
+#[derive(Debug)]
+pub struct ShadowMaps {
+    uniform_buffer: Buffer,
+    sampler: Sampler,
+    bind_group: Option<BindGroup>,
+    /// Cascade view projections shadow casters are rendered with.
+    cascade_buffers: Vec<Buffer>,
+    cascade_bind_groups: Vec<BindGroup>,
+    frustums: Vec<Frustum>,
+}
+
+impl ShadowMaps {
+    pub fn new(
+        device: &Device,
+        settings: &ShadowSettings,
+        camera_layout: &BindGroupLayout,
+    ) -> Self {
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("shadow_sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            compare: Some(CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let uniform_buffer = ShadowUniform::zeroed().create_init_wgpu_buff(device);
+
+        let cascade_buffers: Vec<_> = (0..settings.cascade_count())
+            .map(|_| CameraUniform::zeroed().create_init_wgpu_buff(device))
+            .collect();
+        let cascade_bind_groups = cascade_buffers
+            .iter()
+            .map(|buffer| {
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("cascade_bind_group"),
+                    layout: camera_layout,
+                    entries: &[BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                })
+            })
+            .collect();
+
+        ShadowMaps {
+            uniform_buffer,
+            sampler,
+            bind_group: None,
+            cascade_buffers,
+            cascade_bind_groups,
+            frustums: Vec::new(),
+        }
+    }
+
+    /// Binds the [`SHADOW_MAPS`] attachment array, it has to be bound again
+    /// whenever the attachment is recreated.
+    pub fn bind_maps(&mut self, device: &Device, layout: &BindGroupLayout, maps: &TextureView) {
+        self.bind_group = Some(device.create_bind_group(&BindGroupDescriptor {
+            label: Some("shadow_bind_group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(maps),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        }));
+    }
+
+    /// Bind group main pass pipelines sample shadows with, `None` until
+    /// [`ShadowMaps::bind_maps`] is called.
+    pub fn bind_group(&self) -> Option<&BindGroup> {
+        self.bind_group.as_ref()
+    }
+
+    /// Fits cascades to the camera view and uploads them.
+    pub fn prepare(
+        &mut self,
+        queue: &Queue,
+        camera: &Camera,
+        sun: Vec3,
+        settings: &ShadowSettings,
+    ) {
+        let cascades = match settings.enabled {
+            true => Cascade::fit_view(camera, sun, settings),
+            false => Vec::new(),
+        };
+        let cascades = &cascades[..cascades.len().min(self.cascade_buffers.len())];
+
+        let uniform = ShadowUniform::new(cascades, sun, settings);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+        for (cascade, buffer) in cascades.iter().zip(&self.cascade_buffers) {
+            let uniform = CameraUniform {
+                view_projection: cascade.view_projection,
+            };
+            queue.write_buffer(buffer, 0, bytemuck::bytes_of(&uniform));
+        }
+        self.frustums = cascades
+            .iter()
+            .map(|it| Frustum::from_view_projection(it.view_projection))
+            .collect();
+    }
+
+    /// Renders meshes of every caster pipeline into [`SHADOW_MAPS`] layers of
+    /// cascades they overlap.
+    ///
+    /// `casters` pair pipelines meshes are drawn with in the main pass with
+    /// depth-only pipelines they're drawn with here.
+    pub fn render(
+        &self,
+        encoder: &mut CommandEncoder,
+        attachments: &Attachments,
+        meshes: &MeshManager,
+        casters: &[(PipelineId, RenderPipeline)],
+        instances: u32,
+    ) {
+        for (i, (bind_group, frustum)) in self
+            .cascade_bind_groups
+            .iter()
+            .zip(&self.frustums)
+            .enumerate()
+        {
+            let layer = match attachments.layer(SHADOW_MAPS, i as u32) {
+                Some(it) => it,
+                None => break,
+            };
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: layer,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            for (pipeline, render_pipeline) in casters {
+                pass.set_pipeline(render_pipeline);
+                pass.set_bind_group(1, bind_group, &[]);
+                meshes.draw_in(&mut pass, *pipeline, instances, frustum);
+            }
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::f32::consts::TAU;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3, Vec4};
+use wgpu::*;
+
+use crate::content::world::{CHUNK_DISTANCE, CHUNK_SIZE};
+
+use super::{
+    camera::Camera,
+    pipeline::{FragmentInterface, Pipeline, VertexInterface},
+    shader::{self, preprocessor::VirtualFiles, WgslSource},
+};
+
+/// Sky bindings and functions, included as `sky.wgsl`.
+pub static SKY_WGSL: &str = include_str!("sky.wgsl");
+pub static SKY_SHADER: WgslSource<'static> = WgslSource::new_static(include_str!("sky_dome.wgsl"));
+
+/// Time of day, advancing as the game runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DayClock {
+    /// Fraction of the day, 0 is midnight, 0.25 sunrise, 0.5 noon and 0.75
+    /// sunset.
+    time: f32,
+    /// Real seconds a full day takes.
+    pub day_length: f32,
+    pub paused: bool,
+}
+
+impl Default for DayClock {
+    fn default() -> Self {
+        DayClock {
+            time: 0.35,
+            day_length: 1200.0,
+            paused: false,
+        }
+    }
+}
+
+impl DayClock {
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Sets the fraction of the day, wrapping it into `0..1`.
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time.rem_euclid(1.0);
+    }
+
+    /// Moves the clock `seconds` forward unless it's paused.
+    pub fn advance(&mut self, seconds: f32) {
+        if !self.paused && self.day_length > 0.0 {
+            self.set_time(self.time + seconds / self.day_length);
+        }
+    }
+}
+
+/// Distances at which geometry fades into the sky.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FogSettings {
+    /// Distance from the camera fog starts at, in blocks.
+    pub start: f32,
+    /// Distance from the camera geometry is fully hidden at, should be about
+    /// the view distance so chunks don't pop in at the horizon.
+    pub end: f32,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        FogSettings::for_view_distance(CHUNK_DISTANCE as f32)
+    }
+}
+
+impl FogSettings {
+    /// Fog ending at the edge of `view_distance` chunks.
+    pub fn for_view_distance(view_distance: f32) -> Self {
+        let end = view_distance * CHUNK_SIZE as f32;
+        FogSettings {
+            start: end * 0.6,
+            end,
+        }
+    }
+}
+
+const DAY_ZENITH: Vec3 = Vec3::new(0.22, 0.42, 0.85);
+const DAY_HORIZON: Vec3 = Vec3::new(0.62, 0.76, 0.95);
+const SUNSET_ZENITH: Vec3 = Vec3::new(0.2, 0.22, 0.45);
+const SUNSET_HORIZON: Vec3 = Vec3::new(1.0, 0.45, 0.2);
+const NIGHT_ZENITH: Vec3 = Vec3::new(0.004, 0.008, 0.025);
+const NIGHT_HORIZON: Vec3 = Vec3::new(0.02, 0.03, 0.06);
+
+const NOON_LIGHT: Vec3 = Vec3::new(0.45, 0.43, 0.4);
+const SUNSET_LIGHT: Vec3 = Vec3::new(0.45, 0.25, 0.12);
+const MOON_LIGHT: Vec3 = Vec3::new(0.06, 0.08, 0.13);
+const DAY_AMBIENT: Vec3 = Vec3::new(0.55, 0.58, 0.65);
+const NIGHT_AMBIENT: Vec3 = Vec3::new(0.05, 0.06, 0.1);
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Procedural sky, its colors and the light it casts follow the
+/// [`DayClock`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sky {
+    pub clock: DayClock,
+    /// Angle the path of the sun is tilted by towards `+Z`, so it isn't
+    /// straight overhead at noon.
+    pub tilt: f32,
+    pub fog: FogSettings,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky {
+            clock: DayClock::default(),
+            tilt: 0.4,
+            fog: FogSettings::default(),
+        }
+    }
+}
+
+impl Sky {
+    /// Direction towards the sun, it rises in `+X` and sets in `-X`.
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.clock.time() - 0.25) * TAU;
+        let (sin, cos) = angle.sin_cos();
+        Vec3::new(cos, sin * self.tilt.cos(), sin * self.tilt.sin())
+    }
+
+    /// Direction towards the moon, always opposite of the sun.
+    pub fn moon_direction(&self) -> Vec3 {
+        -self.sun_direction()
+    }
+
+    /// Direction shadows are cast from, the sun during the day and the moon
+    /// at night.
+    pub fn light_direction(&self) -> Vec3 {
+        let sun = self.sun_direction();
+        if sun.y >= 0.0 {
+            sun
+        } else {
+            -sun
+        }
+    }
+
+    /// 0 at night, 1 during the day.
+    fn daylight(&self) -> f32 {
+        smoothstep(-0.2, 0.2, self.sun_direction().y)
+    }
+
+    /// How close the sun is to the horizon, 1 at sunrise and sunset.
+    fn twilight(&self) -> f32 {
+        1.0 - smoothstep(0.0, 0.35, self.sun_direction().y.abs())
+    }
+
+    /// Color of light coming from [`Sky::light_direction`], it fades out
+    /// as the sun or moon reach the horizon so switching between them isn't
+    /// visible.
+    pub fn light_color(&self) -> Vec3 {
+        let height = self.light_direction().y;
+        let fade = smoothstep(0.0, 0.1, height);
+        if self.sun_direction().y >= 0.0 {
+            SUNSET_LIGHT.lerp(NOON_LIGHT, smoothstep(0.0, 0.4, height)) * fade
+        } else {
+            MOON_LIGHT * fade
+        }
+    }
+
+    /// Light scattered by the sky, reaching surfaces from every direction.
+    pub fn ambient(&self) -> Vec3 {
+        NIGHT_AMBIENT.lerp(DAY_AMBIENT, self.daylight())
+    }
+
+    /// Color of the sky straight up.
+    pub fn zenith(&self) -> Vec3 {
+        NIGHT_ZENITH
+            .lerp(DAY_ZENITH, self.daylight())
+            .lerp(SUNSET_ZENITH, self.twilight() * 0.5)
+    }
+
+    /// Color of the sky at the horizon, geometry fades into it.
+    pub fn horizon(&self) -> Vec3 {
+        NIGHT_HORIZON
+            .lerp(DAY_HORIZON, self.daylight())
+            .lerp(SUNSET_HORIZON, self.twilight() * 0.7)
+    }
+
+    /// Glow of light scattered around the sun.
+    pub fn glow(&self) -> Vec3 {
+        let visible = smoothstep(-0.1, 0.05, self.sun_direction().y);
+        (SUNSET_HORIZON * self.twilight() + NOON_LIGHT * 0.3) * visible
+    }
+}
+
+/// Sky data available to chunk shaders at `@group(3) @binding(0)`.
+#[derive(Debug, Copy, Clone, Pod, Zeroable, ShaderUniform)]
+#[repr(C)]
+pub struct SkyUniform {
+    pub inverse_view_projection: Mat4,
+    pub camera_position: Vec4,
+    pub sun: Vec4,
+    pub moon: Vec4,
+    pub zenith: Vec4,
+    pub horizon: Vec4,
+    pub glow: Vec4,
+    /// Color of the light shadows are cast from.
+    pub light: Vec4,
+    pub ambient: Vec4,
+    pub fog_start: f32,
+    pub fog_end: f32,
+    #[padding]
+    pub _padding: [u32; 2],
+}
+
+impl SkyUniform {
+    pub fn new(sky: &Sky, camera: &Camera) -> Self {
+        SkyUniform {
+            inverse_view_projection: camera.view_projection().inverse(),
+            camera_position: camera.position().extend(1.0),
+            sun: sky.sun_direction().extend(0.0),
+            moon: sky.moon_direction().extend(0.0),
+            zenith: sky.zenith().extend(1.0),
+            horizon: sky.horizon().extend(1.0),
+            glow: sky.glow().extend(1.0),
+            light: sky.light_color().extend(1.0),
+            ambient: sky.ambient().extend(1.0),
+            fog_start: sky.fog.start,
+            fog_end: sky.fog.end,
+            _padding: [0; 2],
+        }
+    }
+}
+
+pub const SKY_BIND_GROUP_LAYOUT: BindGroupLayoutDescriptor<'static> = BindGroupLayoutDescriptor {
+    label: Some("sky_bind_group_layout"),
+    entries: &[BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::VERTEX_FRAGMENT,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }],
+};
+
+/// Creates the pipeline the sky is drawn behind geometry with, its only bind
+/// group is the sky uniform.
+pub fn sky_pipeline(format: TextureFormat) -> Pipeline<'static, WgslSource<'static>> {
+    let files = VirtualFiles::new().with("sky.wgsl", SKY_WGSL);
+    let shader =
+        shader::load_source("sky_dome.wgsl", &SKY_SHADER, files).expect("invalid sky shader");
+
+    let mut pipeline = Pipeline::new(
+        shader,
+        VertexInterface::new("vs_sky"),
+        Some(FragmentInterface {
+            entry_point: "fs_sky".to_string(),
+            targets: vec![Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+    );
+    // drawn on the far plane, where the depth buffer is still clear
+    pipeline.depth_stencil = Some(DepthStencilState {
+        format: super::DEPTH_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: CompareFunction::LessEqual,
+        stencil: StencilState::default(),
+        bias: DepthBiasState::default(),
+    });
+    pipeline
+}
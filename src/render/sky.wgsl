@@ -0,0 +1,42 @@
+// Sky colors, sun and moon, bound by the renderer at group 3. Shaders that
+// don't bind anything else can `#define SKY_GROUP 0` before including it.
+
+#ifndef SKY_GROUP
+#define SKY_GROUP 3
+#endif
+
+struct SkyUniform {
+    inverse_view_projection: mat4x4<f32>,
+    camera_position: vec4<f32>,
+    sun: vec4<f32>,
+    moon: vec4<f32>,
+    zenith: vec4<f32>,
+    horizon: vec4<f32>,
+    glow: vec4<f32>,
+    light: vec4<f32>,
+    ambient: vec4<f32>,
+    fog_start: f32,
+    fog_end: f32,
+};
+
+@group(SKY_GROUP) @binding(0)
+var<uniform> sky: SkyUniform;
+
+// Color of the sky seen in `direction`, without the sun and moon discs.
+fn sky_color(direction: vec3<f32>) -> vec3<f32> {
+    let up = max(direction.y, 0.0);
+    var color = mix(sky.horizon.rgb, sky.zenith.rgb, sqrt(up));
+    // below the horizon the sky darkens towards the ground
+    color = color * mix(1.0, 0.4, smoothstep(0.0, -0.4, direction.y));
+    // light scattered around the sun, strongest when it's low
+    let towards_sun = max(dot(direction, sky.sun.xyz), 0.0);
+    return color + sky.glow.rgb * pow(towards_sun, 8.0);
+}
+
+// Blends `color` of a surface at `world_position` into the sky behind it.
+fn apply_fog(color: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+    let offset = world_position - sky.camera_position.xyz;
+    let distance = length(offset);
+    let fog = smoothstep(sky.fog_start, sky.fog_end, distance);
+    return mix(color, sky_color(offset / max(distance, 0.0001)), fog);
+}
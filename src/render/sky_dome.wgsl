@@ -0,0 +1,40 @@
+// Draws the sky behind everything rendered before it.
+
+#define SKY_GROUP 0
+#include "sky.wgsl"
+
+struct SkyOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+// Fullscreen triangle on the far plane, only visible where nothing else was
+// drawn.
+@vertex
+fn vs_sky(@builtin(vertex_index) index: u32) -> SkyOutput {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: SkyOutput;
+    out.ndc = corner * 2.0 - 1.0;
+    out.position = vec4<f32>(out.ndc, 1.0, 1.0);
+    return out;
+}
+
+// Sharp disc `radius` radians wide around `center`.
+fn disc(direction: vec3<f32>, center: vec3<f32>, radius: f32) -> f32 {
+    let edge = cos(radius);
+    return smoothstep(edge, mix(edge, 1.0, 0.3), dot(direction, center));
+}
+
+@fragment
+fn fs_sky(in: SkyOutput) -> @location(0) vec4<f32> {
+    let near = sky.inverse_view_projection * vec4<f32>(in.ndc, 0.0, 1.0);
+    let far = sky.inverse_view_projection * vec4<f32>(in.ndc, 1.0, 1.0);
+    let direction = normalize(far.xyz / far.w - near.xyz / near.w);
+
+    var color = sky_color(direction);
+    // the ground hides discs below the horizon
+    let above = smoothstep(-0.01, 0.01, direction.y);
+    color = color + vec3<f32>(40.0, 36.0, 30.0) * disc(direction, sky.sun.xyz, 0.02) * above;
+    color = color + vec3<f32>(1.6, 1.7, 2.0) * disc(direction, sky.moon.xyz, 0.025) * above;
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,34 @@
+// Maps HDR colors into the displayable range.
+
+#include "fullscreen.wgsl"
+
+struct TonemapUniform {
+    exposure: f32,
+    curve: u32,
+};
+
+@group(0) @binding(2)
+var<uniform> tonemap: TonemapUniform;
+
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let a = color * (2.51 * color + 0.03);
+    let b = color * (2.43 * color + 0.59) + 0.14;
+    return clamp(a / b, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_main(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = sample_source(in.uv).rgb * tonemap.exposure;
+    var mapped = color;
+    if (tonemap.curve == 1u) {
+        mapped = reinhard(color);
+    } else if (tonemap.curve == 2u) {
+        mapped = aces(color);
+    }
+    return vec4<f32>(mapped, 1.0);
+}
@@ -0,0 +1,484 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Vec2, Vec4};
+use image::{Rgba, RgbaImage};
+use wgpu::{util::DeviceExt, *};
+
+use crate::math::Rect;
+
+use super::{
+    font,
+    graph::{Attachments, GraphPass, SURFACE},
+    pipeline::{FragmentInterface, Pipeline, VertexInterface},
+    shader::{self, preprocessor::VirtualFiles, WgslSource},
+    texture::MENU_ICONS,
+    vertex::{VertexBuffer, VertexData},
+    Renderer,
+};
+
+pub static UI_SHADER: WgslSource<'static> = WgslSource::new_static(include_str!("ui.wgsl"));
+
+/// Side of the square atlas texture, in texels.
+pub const ATLAS_SIZE: u32 = 512;
+/// Side of every menu icon, in texels.
+pub const ICON_SIZE: u32 = 16;
+/// Top left corner of baked font glyphs, they're laid out in rows of
+/// [`GLYPHS_PER_ROW`] with a transparent texel between them.
+const FONT_ORIGIN: (u32, u32) = (0, 448);
+const GLYPHS_PER_ROW: u32 = 16;
+/// White block solid rectangles are drawn with.
+const WHITE_ORIGIN: (u32, u32) = (ATLAS_SIZE - WHITE_SIZE, ATLAS_SIZE - WHITE_SIZE);
+const WHITE_SIZE: u32 = 8;
+
+pub const UI_ATLAS_BIND_GROUP_LAYOUT: BindGroupLayoutDescriptor<'static> =
+    BindGroupLayoutDescriptor {
+        label: Some("ui_atlas_bind_group_layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: TextureViewDimension::D2,
+                    sample_type: TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    };
+
+#[derive(Debug, Copy, Clone, PartialEq, Pod, Zeroable, VertexData)]
+#[repr(C)]
+pub struct UiVertex {
+    /// Pixels from the top left corner of the screen, clip space once
+    /// uploaded.
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// Icons of `menu_icons.png`, laid out left to right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuIcon {
+    Check,
+    Settings,
+    Play,
+    Pause,
+}
+
+impl MenuIcon {
+    /// Part of the atlas the icon covers.
+    pub fn uv(self) -> Rect {
+        texel_rect(self as u32 * ICON_SIZE, 0, ICON_SIZE, ICON_SIZE)
+    }
+}
+
+/// Texture every UI quad samples, menu icons with font glyphs and a white
+/// block baked into their unused space.
+pub struct UiAtlas {
+    image: RgbaImage,
+}
+
+impl UiAtlas {
+    pub fn new(icons: &RgbaImage) -> Self {
+        let mut image = RgbaImage::new(ATLAS_SIZE, ATLAS_SIZE);
+        image::imageops::replace(&mut image, icons, 0, 0);
+
+        for index in 0..font::GLYPH_COUNT {
+            let c = (font::FIRST_CHAR as u8 + index as u8) as char;
+            let (left, top) = glyph_origin(index);
+            for y in 0..font::GLYPH_HEIGHT + 1 {
+                for x in 0..font::ADVANCE {
+                    let value = if font::is_set(c, x, y) { 255 } else { 0 };
+                    image.put_pixel(left + x, top + y, Rgba([value; 4]));
+                }
+            }
+        }
+        for y in 0..WHITE_SIZE {
+            for x in 0..WHITE_SIZE {
+                image.put_pixel(WHITE_ORIGIN.0 + x, WHITE_ORIGIN.1 + y, Rgba([255; 4]));
+            }
+        }
+        UiAtlas { image }
+    }
+
+    /// Atlas with the icons of [`MENU_ICONS`].
+    pub fn menu_icons() -> Self {
+        let icons = image::load_from_memory(MENU_ICONS)
+            .expect("invalid menu icons")
+            .to_rgba8();
+        UiAtlas::new(&icons)
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    /// Part of the atlas the glyph of `c` covers.
+    pub fn glyph_uv(c: char) -> Rect {
+        let (left, top) = glyph_origin(font::glyph_index(c));
+        texel_rect(left, top, font::GLYPH_WIDTH, font::GLYPH_HEIGHT)
+    }
+
+    /// Uniformly white part of the atlas, sampled by solid rectangles.
+    pub fn white_uv() -> Rect {
+        // away from the edges, so filtering never reaches other texels
+        texel_rect(WHITE_ORIGIN.0 + 2, WHITE_ORIGIN.1 + 2, 4, 4)
+    }
+
+    /// Uploads the atlas and binds it with a nearest sampler.
+    pub fn create_bind_group(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        layout: &BindGroupLayout,
+    ) -> BindGroup {
+        let texture = device.create_texture_with_data(
+            queue,
+            &TextureDescriptor {
+                label: Some("ui_atlas"),
+                size: Extent3d {
+                    width: ATLAS_SIZE,
+                    height: ATLAS_SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            },
+            self.image.as_raw(),
+        );
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("ui_atlas_sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("ui_atlas_bind_group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+        })
+    }
+}
+
+fn glyph_origin(index: usize) -> (u32, u32) {
+    let index = index as u32;
+    (
+        FONT_ORIGIN.0 + index % GLYPHS_PER_ROW * font::ADVANCE,
+        FONT_ORIGIN.1 + index / GLYPHS_PER_ROW * (font::GLYPH_HEIGHT + 1),
+    )
+}
+
+fn texel_rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+    let size = ATLAS_SIZE as f32;
+    Rect::new(
+        x as f32 / size,
+        y as f32 / size,
+        width as f32 / size,
+        height as f32 / size,
+    )
+}
+
+/// Quads drawn over the next frame, positions are in pixels from the top
+/// left corner of the screen.
+#[derive(Debug, Default, Clone)]
+pub struct UiBatch {
+    vertices: VertexBuffer<'static, UiVertex>,
+    size: Vec2,
+}
+
+impl UiBatch {
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut batch = UiBatch::default();
+        batch.resize(width, height);
+        batch
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.size = Vec2::new(width as f32, height as f32);
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    /// Rectangle covering the whole screen.
+    pub fn screen(&self) -> Rect {
+        Rect {
+            position: Vec2::ZERO,
+            size: self.size,
+        }
+    }
+
+    /// Every quad is two triangles of three vertices.
+    pub fn vertices(&self) -> &[UiVertex] {
+        &self.vertices
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Quad covering `rect` with the `uv` part of the atlas multiplied by
+    /// `color`.
+    pub fn quad(&mut self, rect: Rect, uv: Rect, color: Vec4) {
+        let color = color.to_array();
+        let (min, max) = (rect.min(), rect.max());
+        let (uv_min, uv_max) = (uv.min(), uv.max());
+        let top_left = ([min.x, min.y], [uv_min.x, uv_min.y]);
+        let bottom_left = ([min.x, max.y], [uv_min.x, uv_max.y]);
+        let bottom_right = ([max.x, max.y], [uv_max.x, uv_max.y]);
+        let top_right = ([max.x, min.y], [uv_max.x, uv_min.y]);
+        // counter-clockwise once Y points up in clip space
+        for (position, uv) in [
+            top_left,
+            bottom_left,
+            bottom_right,
+            top_left,
+            bottom_right,
+            top_right,
+        ] {
+            self.vertices.push(UiVertex {
+                position,
+                uv,
+                color,
+            });
+        }
+    }
+
+    pub fn rect(&mut self, rect: Rect, color: Vec4) {
+        self.quad(rect, UiAtlas::white_uv(), color);
+    }
+
+    /// Edges of `rect`, `thickness` pixels wide and inside of it.
+    pub fn border(&mut self, rect: Rect, thickness: f32, color: Vec4) {
+        let (min, max) = (rect.min(), rect.max());
+        let thickness = thickness.min(rect.size.x * 0.5).min(rect.size.y * 0.5);
+        let inner = rect.size.y - thickness * 2.0;
+        self.rect(Rect::new(min.x, min.y, rect.size.x, thickness), color);
+        self.rect(
+            Rect::new(min.x, max.y - thickness, rect.size.x, thickness),
+            color,
+        );
+        self.rect(Rect::new(min.x, min.y + thickness, thickness, inner), color);
+        self.rect(
+            Rect::new(max.x - thickness, min.y + thickness, thickness, inner),
+            color,
+        );
+    }
+
+    pub fn icon(&mut self, rect: Rect, icon: MenuIcon, color: Vec4) {
+        self.quad(rect, icon.uv(), color);
+    }
+
+    /// Draws `text` with its top left corner at `position`, every font pixel
+    /// covers `scale` pixels. Lines are split at `\n`.
+    pub fn text(&mut self, position: Vec2, text: &str, scale: f32, color: Vec4) {
+        let glyph_size = Vec2::new(font::GLYPH_WIDTH as f32, font::GLYPH_HEIGHT as f32) * scale;
+        for (row, line) in text.split('\n').enumerate() {
+            let top = position.y + (row as u32 * font::LINE_HEIGHT) as f32 * scale;
+            for (column, c) in line.chars().enumerate() {
+                if c == ' ' {
+                    continue;
+                }
+                let left = position.x + (column as u32 * font::ADVANCE) as f32 * scale;
+                self.quad(
+                    Rect {
+                        position: Vec2::new(left, top),
+                        size: glyph_size,
+                    },
+                    UiAtlas::glyph_uv(c),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Size `text` is drawn with at `scale`.
+    pub fn text_size(text: &str, scale: f32) -> Vec2 {
+        let (width, height) = font::text_size(text);
+        Vec2::new(width as f32, height as f32) * scale
+    }
+
+    /// Draws `text` centered in `rect`.
+    pub fn text_centered(&mut self, rect: Rect, text: &str, scale: f32, color: Vec4) {
+        let size = UiBatch::text_size(text, scale);
+        // whole pixels keep glyphs sharp
+        let position = rect.centered(size).position.round();
+        self.text(position, text, scale, color);
+    }
+}
+
+/// Creates the pipeline UI quads are drawn into `format` targets with, its
+/// only bind group is the atlas.
+pub fn ui_pipeline(format: TextureFormat) -> Pipeline<'static, WgslSource<'static>> {
+    let files = VirtualFiles::new().with("ui_vertex.wgsl", UiVertex::WGSL_STRUCT);
+    let shader = shader::load_source("ui.wgsl", &UI_SHADER, files).expect("invalid UI shader");
+
+    Pipeline::new(
+        shader,
+        VertexInterface::new("vs_main").with_buffer(UiVertex::LAYOUT),
+        Some(FragmentInterface {
+            entry_point: "fs_main".to_string(),
+            targets: vec![Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+    )
+}
+
+/// Uploads and draws [`UiBatch`]es.
+#[derive(Debug)]
+pub struct UiRenderer {
+    pipeline: Pipeline<'static, WgslSource<'static>>,
+    render_pipeline: Option<RenderPipeline>,
+    buffer: Option<Buffer>,
+    /// Vertices the buffer has room for.
+    capacity: usize,
+    vertex_count: u32,
+}
+
+impl UiRenderer {
+    pub fn new(format: TextureFormat) -> Self {
+        UiRenderer {
+            pipeline: ui_pipeline(format),
+            render_pipeline: None,
+            buffer: None,
+            capacity: 0,
+            vertex_count: 0,
+        }
+    }
+
+    /// Creates the render pipeline on first use and after its shader
+    /// changed, then uploads `batch` converted to clip space.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, batch: &UiBatch) {
+        if self.pipeline.reload_shader() || self.render_pipeline.is_none() {
+            let layout = device.create_bind_group_layout(&UI_ATLAS_BIND_GROUP_LAYOUT);
+            self.render_pipeline = Some(self.pipeline.create_render_pipeline(device, &[&layout]));
+        }
+
+        let size = batch.size().max(Vec2::ONE);
+        let vertices: Vec<UiVertex> = batch
+            .vertices()
+            .iter()
+            .map(|it| UiVertex {
+                position: [
+                    it.position[0] / size.x * 2.0 - 1.0,
+                    1.0 - it.position[1] / size.y * 2.0,
+                ],
+                ..*it
+            })
+            .collect();
+        self.vertex_count = vertices.len() as u32;
+        if vertices.is_empty() {
+            return;
+        }
+        if self.buffer.is_none() || self.capacity < vertices.len() {
+            self.capacity = vertices.len().next_power_of_two();
+            self.buffer = Some(device.create_buffer(&BufferDescriptor {
+                label: Some("ui_vertices"),
+                size: (self.capacity * UiVertex::SIZE) as BufferAddress,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        if let Some(buffer) = &self.buffer {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&vertices));
+        }
+    }
+
+    /// Draws the last prepared batch over `target`.
+    pub fn draw(&self, commands: &mut CommandEncoder, target: &TextureView, atlas: &BindGroup) {
+        let (pipeline, buffer) = match (&self.render_pipeline, &self.buffer) {
+            (Some(pipeline), Some(buffer)) if self.vertex_count > 0 => (pipeline, buffer),
+            _ => return,
+        };
+        let mut pass = commands.begin_render_pass(&RenderPassDescriptor {
+            label: Some("ui_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, atlas, &[]);
+        pass.set_vertex_buffer(0, buffer.slice(..));
+        pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+/// Draws [`Renderer::ui`] over the post-processed image.
+pub struct UiPass {
+    ui: UiRenderer,
+}
+
+impl UiPass {
+    pub fn new(format: TextureFormat) -> Self {
+        UiPass {
+            ui: UiRenderer::new(format),
+        }
+    }
+}
+
+impl GraphPass<Renderer<'static>> for UiPass {
+    fn writes(&self) -> &[&'static str] {
+        &[SURFACE]
+    }
+
+    fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        renderer: &mut Renderer<'static>,
+        _attachments: &Attachments,
+    ) {
+        self.ui.prepare(device, queue, &renderer.ui);
+        renderer.ui.clear();
+    }
+
+    fn execute(
+        &mut self,
+        renderer: &mut Renderer<'static>,
+        encoder: &mut CommandEncoder,
+        attachments: &Attachments,
+    ) {
+        if let (Some(atlas), Some(target)) =
+            (&renderer.ui_atlas_bind_group, attachments.view(SURFACE))
+        {
+            self.ui.draw(encoder, target, atlas);
+        }
+    }
+}
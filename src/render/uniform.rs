@@ -0,0 +1,18 @@
+use wgpu::util::DeviceExt;
+
+/// Data that can be bound as a uniform or storage buffer.
+///
+/// Implemented through `#[derive(ShaderUniform)]` which checks that the
+/// struct fields are placed where WGSL expects them.
+pub trait ShaderUniform: Copy + Clone + bytemuck::Pod + bytemuck::Zeroable {
+    const SIZE: usize;
+    const ALIGNMENT: usize;
+
+    fn create_init_wgpu_buff(&self, d: &wgpu::Device) -> wgpu::Buffer {
+        d.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::bytes_of(self),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+}
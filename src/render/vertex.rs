@@ -11,6 +11,36 @@ pub trait VertexData<'l>: Copy + Clone + bytemuck::Pod + bytemuck::Zeroable {
     const SIZE: usize;
     const ATTRIBUTES: &'l [wgpu::VertexAttribute];
     const LAYOUT: wgpu::VertexBufferLayout<'l>;
+    /// WGSL struct declaration with a `@location` for every attribute.
+    const WGSL_STRUCT: &'l str;
+
+    /// [`Self::WGSL_STRUCT`] with locations moved to start at
+    /// `first_location`.
+    ///
+    /// Used for buffers whose attributes were offset by
+    /// [`VertexInterface`](super::pipeline::VertexInterface).
+    fn wgsl_struct_at(first_location: wgpu::ShaderLocation) -> String {
+        let offset = Self::ATTRIBUTES
+            .iter()
+            .map(|it| it.shader_location)
+            .min()
+            .map(|min| first_location.saturating_sub(min))
+            .unwrap_or_default();
+
+        let mut result = String::with_capacity(Self::WGSL_STRUCT.len());
+        let mut rest = Self::WGSL_STRUCT;
+        while let Some(start) = rest.find("@location(") {
+            let (before, after) = rest.split_at(start + "@location(".len());
+            let end = after.find(')').unwrap_or(after.len());
+            let location: wgpu::ShaderLocation = after[..end].parse().unwrap_or_default();
+
+            result += before;
+            result += &(location + offset).to_string();
+            rest = &after[end..];
+        }
+        result += rest;
+        result
+    }
 }
 
 pub type IndexValue = u32;
@@ -108,10 +138,23 @@ impl<'l, D: VertexData<'l>> VertexBuffer<'l, D> {
         }
     }
 
+    pub fn push(&mut self, vertex: D) {
+        self.data.push(vertex)
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear()
+    }
+
     pub fn layout() -> wgpu::VertexBufferLayout<'l> {
         D::LAYOUT
     }
 
+    /// Whether the buffer holds per-instance instead of per-vertex data.
+    pub fn is_instanced() -> bool {
+        D::LAYOUT.step_mode == wgpu::VertexStepMode::Instance
+    }
+
     pub fn create_init_wgpu_buff(&self, d: &wgpu::Device) -> wgpu::Buffer {
         use wgpu::util::DeviceExt;
 
@@ -160,3 +203,15 @@ pub struct DevVertexData {
     pub position: [f32; 3],
     pub color: [f32; 3],
 }
+
+/// Per-instance placement of a mesh.
+///
+/// Its attributes are placed after per-vertex attributes by
+/// [`VertexInterface`](super::pipeline::VertexInterface).
+#[derive(Debug, Copy, Clone, Pod, Zeroable, VertexData)]
+#[repr(C)]
+#[step_mode(Instance)]
+pub struct InstanceData {
+    /// Translation in `xyz` and uniform scale in `w`.
+    pub offset: [f32; 4],
+}
@@ -0,0 +1,168 @@
+use glam::Vec3;
+use winit::event::WindowEvent;
+
+use crate::{
+    client::Client,
+    content::world::{ChunkCoord, Terrain, World, CHUNK_SIZE},
+    render::{
+        camera::Camera,
+        chunk::{ChunkMeshes, LodSettings},
+        sky::DayClock,
+    },
+};
+
+use super::{
+    is_escape,
+    loading::{request_chunks, GROUND_HEIGHT},
+    paused::Paused,
+    GameState, Transition,
+};
+
+const FOV_Y: f32 = 70.0 * std::f32::consts::PI / 180.0;
+
+/// Simulates and shows the loaded world, its chunks are meshed while the
+/// state is on the stack.
+pub struct InWorld {
+    world: World,
+    meshes: Option<ChunkMeshes>,
+    /// Time of day simulated by ticks, the sky shows it interpolated.
+    clock: DayClock,
+    /// Time of day before the last tick.
+    previous_time: f32,
+    pause: bool,
+}
+
+impl InWorld {
+    pub fn new(world: World) -> Self {
+        InWorld {
+            world,
+            meshes: None,
+            clock: DayClock::default(),
+            previous_time: DayClock::default().time(),
+            pause: false,
+        }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Gameplay plugins and systems are added through it.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    pub fn terrain(&self) -> &Terrain {
+        self.world.terrain()
+    }
+
+    /// Camera position, above the spawn.
+    fn eye() -> Vec3 {
+        Vec3::new(0.0, GROUND_HEIGHT as f32 + 24.0, 0.0)
+    }
+
+    /// Meshes every loaded chunk in view and removes meshes of ones that
+    /// aren't.
+    fn update_meshes(&mut self, client: &mut Client) {
+        self.world.take_changed_chunks();
+        let loaded: Vec<ChunkCoord> = self.terrain().loaded_chunks().collect();
+        self.mesh_chunks(client, loaded);
+    }
+
+    fn mesh_chunks(&mut self, client: &mut Client, chunks: impl IntoIterator<Item = ChunkCoord>) {
+        if let Some(meshes) = &mut self.meshes {
+            meshes.settings.view_distance = client.renderer.config().view_distance;
+            for coord in chunks {
+                meshes.update(
+                    &mut client.renderer.renderer.meshes,
+                    self.world.terrain(),
+                    &client.blocks,
+                    InWorld::eye(),
+                    coord,
+                );
+            }
+        }
+    }
+}
+
+impl GameState<Client> for InWorld {
+    fn name(&self) -> &'static str {
+        "in world"
+    }
+
+    fn enter(&mut self, client: &mut Client) {
+        self.clock = client.renderer.renderer.sky.clock;
+        self.previous_time = self.clock.time();
+        self.meshes = Some(ChunkMeshes::new(
+            client.chunk_pipelines,
+            LodSettings::default(),
+        ));
+        self.update_meshes(client);
+    }
+
+    fn exit(&mut self, client: &mut Client) {
+        if let Some(mut meshes) = self.meshes.take() {
+            for coord in self.world.terrain().loaded_chunks() {
+                meshes.remove(&mut client.renderer.renderer.meshes, coord);
+            }
+        }
+    }
+
+    /// The view distance might have been changed in the settings, chunks
+    /// that came into view are streamed in by the world.
+    fn resume(&mut self, client: &mut Client) {
+        let view_distance = client.renderer.config().view_distance;
+        request_chunks(&mut self.world.terrain_mut(), view_distance);
+        self.update_meshes(client);
+    }
+
+    fn input(&mut self, _client: &mut Client, event: &WindowEvent) -> bool {
+        self.pause |= is_escape(event);
+        is_escape(event)
+    }
+
+    fn update(&mut self, client: &mut Client, dt: f32) -> Transition<Client> {
+        self.previous_time = self.clock.time();
+        self.clock.advance(dt);
+        self.world.update(dt);
+        let changed = self.world.take_changed_chunks();
+        if let Some(meshes) = &mut self.meshes {
+            meshes.update_changed(
+                &mut client.renderer.renderer.meshes,
+                self.world.terrain(),
+                &client.blocks,
+                InWorld::eye(),
+                changed,
+            );
+        }
+        if std::mem::take(&mut self.pause) {
+            Transition::push(Paused::new())
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&mut self, client: &mut Client, alpha: f32) {
+        // time wraps around at midnight, interpolate forwards across it
+        let step = (self.clock.time() - self.previous_time).rem_euclid(1.0);
+        let mut clock = self.clock;
+        clock.set_time(self.previous_time + step * alpha);
+        client.renderer.renderer.sky.clock = clock;
+
+        let size = client.renderer.size;
+        let far = (client.renderer.config().view_distance + 2.0) * CHUNK_SIZE as f32;
+        let eye = InWorld::eye();
+        let renderer = &mut client.renderer.renderer;
+        renderer.camera = Camera::perspective(
+            eye,
+            eye + Vec3::new(1.0, -0.4, 1.0),
+            FOV_Y,
+            size.width as f32 / size.height.max(1) as f32,
+            0.1,
+            far,
+        );
+        if let Some(meshes) = &mut self.meshes {
+            meshes.sort_translucent(&mut renderer.meshes, eye);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use glam::Vec2;
+
+use crate::{
+    block::BlockID,
+    client::Client,
+    content::world::{BlockPos, ChunkCoord, ChunkGenerator, ChunkPos, Terrain, World},
+    gen,
+    math::Rect,
+};
+
+use super::{
+    in_world::InWorld,
+    menu::{SPACING, WIDGET_HEIGHT, WIDGET_WIDTH},
+    GameState, Transition,
+};
+
+/// Block the ground of the world is made of.
+pub const GROUND: BlockID = 1;
+/// Blocks below this height are [`GROUND`].
+pub const GROUND_HEIGHT: BlockPos = 8;
+/// Chunks streamed in every tick, more make loading faster but ticks
+/// longer.
+pub const CHUNKS_PER_TICK: usize = 4;
+
+/// Requests chunks up to `view_distance` chunks from the spawn that aren't
+/// loaded yet.
+pub fn request_chunks(terrain: &mut Terrain, view_distance: f32) {
+    let distance = view_distance.ceil() as ChunkPos;
+    // the world is flat, a single layer of chunks holds the ground
+    terrain.request_around(ChunkCoord::new(0, 0, 0), distance, 0);
+}
+
+/// Streams chunks around the spawn and shows how many are loaded, switches
+/// to [`InWorld`] once all of them are.
+pub struct Loading {
+    /// Created on enter, then handed over to [`InWorld`].
+    world: Option<World>,
+    view_distance: f32,
+    /// Chunks requested on enter.
+    total: usize,
+}
+
+impl Loading {
+    /// Loads chunks up to `view_distance` chunks from the spawn.
+    pub fn new(view_distance: f32) -> Self {
+        Loading {
+            world: None,
+            view_distance,
+            total: 0,
+        }
+    }
+
+    /// Fraction of requested chunks that are loaded, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        let pending = self
+            .world
+            .as_ref()
+            .map_or(0, |it| it.terrain().pending_chunks());
+        if self.total == 0 {
+            1.0
+        } else {
+            1.0 - pending as f32 / self.total as f32
+        }
+    }
+}
+
+impl GameState<Client> for Loading {
+    fn name(&self) -> &'static str {
+        "loading"
+    }
+
+    fn enter(&mut self, client: &mut Client) {
+        let mut terrain = Terrain::new();
+        request_chunks(&mut terrain, self.view_distance);
+        self.total = terrain.pending_chunks();
+
+        let mut world = World::new(terrain, client.blocks.clone());
+        world.insert_resource(ChunkGenerator::new(CHUNKS_PER_TICK, |coord| {
+            gen::flat(coord, GROUND_HEIGHT, GROUND)
+        }));
+        self.world = Some(world);
+    }
+
+    fn update(&mut self, _client: &mut Client, dt: f32) -> Transition<Client> {
+        let world = match &mut self.world {
+            Some(it) => it,
+            None => return Transition::None,
+        };
+        world.update(dt);
+        if world.terrain().pending_chunks() > 0 {
+            return Transition::None;
+        }
+        match self.world.take() {
+            Some(world) => Transition::switch(InWorld::new(world)),
+            None => Transition::None,
+        }
+    }
+
+    fn render(&mut self, client: &mut Client, _alpha: f32) {
+        let batch = &mut client.renderer.renderer.ui;
+        let theme = &client.theme;
+        let area = batch
+            .screen()
+            .centered(Vec2::new(WIDGET_WIDTH, WIDGET_HEIGHT * 2.0 + SPACING))
+            .round();
+        let (label, bar) = (
+            Rect::new(
+                area.position.x,
+                area.position.y,
+                WIDGET_WIDTH,
+                WIDGET_HEIGHT,
+            ),
+            Rect::new(
+                area.position.x,
+                area.position.y + WIDGET_HEIGHT + SPACING,
+                WIDGET_WIDTH,
+                WIDGET_HEIGHT,
+            ),
+        );
+        let text = format!("Loading world {:.0}%", self.progress() * 100.0);
+        batch.text_centered(label, &text, theme.text_scale, theme.text);
+        batch.rect(bar, theme.widget);
+        batch.rect(bar.split_left(bar.size.x * self.progress()).0, theme.active);
+        batch.border(bar, theme.border_width, theme.border);
+    }
+}
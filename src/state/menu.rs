@@ -0,0 +1,195 @@
+use glam::Vec2;
+use winit::event::WindowEvent;
+
+use crate::{
+    client::Client,
+    math::Rect,
+    render::{
+        config::{StateConfig, VsyncMode},
+        ui::{MenuIcon, UiBatch},
+    },
+    ui::{
+        widget::{Button, Checkbox, Slider, Widget},
+        Column, Theme,
+    },
+};
+
+use super::{is_escape, loading::Loading, GameState, Transition};
+
+pub(super) const WIDGET_WIDTH: f32 = 320.0;
+pub(super) const WIDGET_HEIGHT: f32 = 40.0;
+pub(super) const SPACING: f32 = 8.0;
+const TITLE_SCALE: f32 = 4.0;
+
+/// Column of `rows` widgets below a title, centered on the screen. Returns
+/// the title row along with the column.
+pub(super) fn menu_column(screen: Rect, rows: usize) -> (Rect, Column) {
+    let title = WIDGET_HEIGHT * 2.0;
+    let height = title + rows as f32 * (WIDGET_HEIGHT + SPACING);
+    let mut column = Column::centered(screen, Vec2::new(WIDGET_WIDTH, height), SPACING);
+    (column.row(title), column)
+}
+
+pub(super) fn draw_title(batch: &mut UiBatch, theme: &Theme, rect: Rect, title: &str) {
+    batch.text_centered(rect, title, TITLE_SCALE, theme.text);
+}
+
+/// Screen the game starts on.
+pub struct MainMenu {
+    title: Rect,
+    play: Button,
+    settings: Button,
+    quit: Button,
+}
+
+impl Default for MainMenu {
+    fn default() -> Self {
+        MainMenu {
+            title: Rect::default(),
+            play: Button::new("Play").with_icon(MenuIcon::Play),
+            settings: Button::new("Settings").with_icon(MenuIcon::Settings),
+            quit: Button::new("Quit"),
+        }
+    }
+}
+
+impl MainMenu {
+    pub fn new() -> Self {
+        MainMenu::default()
+    }
+
+    fn layout(&mut self, screen: Rect) {
+        let (title, mut column) = menu_column(screen, 3);
+        self.title = title;
+        for button in [&mut self.play, &mut self.settings, &mut self.quit] {
+            button.rect = column.row(WIDGET_HEIGHT);
+        }
+    }
+}
+
+impl GameState<Client> for MainMenu {
+    fn name(&self) -> &'static str {
+        "main menu"
+    }
+
+    fn update(&mut self, client: &mut Client, _dt: f32) -> Transition<Client> {
+        self.layout(client.renderer.renderer.ui.screen());
+        let play = self.play.update(&client.input);
+        let settings = self.settings.update(&client.input);
+        let quit = self.quit.update(&client.input);
+        if play {
+            Transition::switch(Loading::new(client.renderer.config().view_distance))
+        } else if settings {
+            Transition::push(Settings::new(client.renderer.config()))
+        } else if quit {
+            Transition::Quit
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&mut self, client: &mut Client, _alpha: f32) {
+        self.layout(client.renderer.renderer.ui.screen());
+        let batch = &mut client.renderer.renderer.ui;
+        let theme = &client.theme;
+        draw_title(batch, theme, self.title, "Earth Oxide");
+        self.play.draw(batch, theme);
+        self.settings.draw(batch, theme);
+        self.quit.draw(batch, theme);
+    }
+}
+
+/// Renderer settings, they're applied and saved once the screen is left.
+pub struct Settings {
+    title: Rect,
+    vsync: Checkbox,
+    multisampling: Checkbox,
+    view_distance: Slider,
+    done: Button,
+    /// Settings the screen was opened with, ones without widgets are kept.
+    config: StateConfig,
+    leave: bool,
+}
+
+impl Settings {
+    pub fn new(config: &StateConfig) -> Self {
+        Settings {
+            title: Rect::default(),
+            vsync: Checkbox::new("Vsync", config.vsync != VsyncMode::Off),
+            multisampling: Checkbox::new("Multisampling", config.msaa_samples > 1),
+            view_distance: Slider::new("View distance", config.view_distance, 2.0, 32.0)
+                .with_step(1.0),
+            done: Button::new("Done").with_icon(MenuIcon::Check),
+            config: config.clone(),
+            leave: false,
+        }
+    }
+
+    /// Settings picked with the widgets.
+    pub fn config(&self) -> StateConfig {
+        let mut config = self.config.clone();
+        config.vsync = match (self.vsync.checked, config.vsync) {
+            (true, VsyncMode::Off) => VsyncMode::On,
+            (true, mode) => mode,
+            (false, _) => VsyncMode::Off,
+        };
+        config.msaa_samples = match (self.multisampling.checked, config.msaa_samples) {
+            (true, 0..=1) => 4,
+            (true, samples) => samples,
+            (false, _) => 1,
+        };
+        config.view_distance = self.view_distance.value;
+        config
+    }
+
+    fn layout(&mut self, screen: Rect) {
+        let (title, mut column) = menu_column(screen, 4);
+        self.title = title;
+        self.vsync.rect = column.row(WIDGET_HEIGHT);
+        self.multisampling.rect = column.row(WIDGET_HEIGHT);
+        self.view_distance.rect = column.row(WIDGET_HEIGHT);
+        self.done.rect = column.row(WIDGET_HEIGHT);
+    }
+}
+
+impl GameState<Client> for Settings {
+    fn name(&self) -> &'static str {
+        "settings"
+    }
+
+    fn exit(&mut self, client: &mut Client) {
+        let config = self.config();
+        if config != *client.renderer.config() {
+            client.set_config(config);
+        }
+    }
+
+    fn input(&mut self, _client: &mut Client, event: &WindowEvent) -> bool {
+        self.leave |= is_escape(event);
+        is_escape(event)
+    }
+
+    fn update(&mut self, client: &mut Client, _dt: f32) -> Transition<Client> {
+        self.layout(client.renderer.renderer.ui.screen());
+        self.vsync.update(&client.input);
+        self.multisampling.update(&client.input);
+        self.view_distance.update(&client.input);
+        if self.done.update(&client.input) || self.leave {
+            Transition::Pop
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&mut self, client: &mut Client, _alpha: f32) {
+        self.layout(client.renderer.renderer.ui.screen());
+        let batch = &mut client.renderer.renderer.ui;
+        let theme = &client.theme;
+        batch.rect(batch.screen(), theme.background);
+        draw_title(batch, theme, self.title, "Settings");
+        self.vsync.draw(batch, theme);
+        self.multisampling.draw(batch, theme);
+        self.view_distance.draw(batch, theme);
+        self.done.draw(batch, theme);
+    }
+}
@@ -0,0 +1,204 @@
+pub mod in_world;
+pub mod loading;
+pub mod menu;
+pub mod paused;
+
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+/// Screen of the game, like a menu or the world, with its own input
+/// handling, updates and rendering.
+///
+/// `C` is the context states are run with, usually the
+/// [`Client`](crate::client::Client) owning the renderer and input.
+pub trait GameState<C> {
+    fn name(&self) -> &'static str;
+
+    /// Called once the state is added to the stack.
+    fn enter(&mut self, _context: &mut C) {}
+
+    /// Called once the state is removed from the stack.
+    fn exit(&mut self, _context: &mut C) {}
+
+    /// Called when another state is pushed over this one.
+    fn pause(&mut self, _context: &mut C) {}
+
+    /// Called when the state over this one is popped.
+    fn resume(&mut self, _context: &mut C) {}
+
+    /// Handles a window event, returns whether it was consumed. Only the top
+    /// state gets events.
+    fn input(&mut self, _context: &mut C, _event: &WindowEvent) -> bool {
+        false
+    }
+
+    /// Advances the state by a simulation tick of `dt` seconds, only the top
+    /// state is updated.
+    fn update(&mut self, context: &mut C, dt: f32) -> Transition<C>;
+
+    /// Draws the state, states below it are drawn first if it's an
+    /// overlay. `alpha` is how far the frame is between the last tick and
+    /// the next one, from 0 to 1.
+    fn render(&mut self, _context: &mut C, _alpha: f32) {}
+
+    /// Whether states below this one stay visible, like the world behind a
+    /// pause menu.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// Change of the state stack requested by the top state.
+pub enum Transition<C> {
+    None,
+    /// Pauses the top state and enters a new one over it.
+    Push(Box<dyn GameState<C>>),
+    /// Exits the top state and resumes the one below it.
+    Pop,
+    /// Exits the top state and enters a new one in its place.
+    Switch(Box<dyn GameState<C>>),
+    /// Exits every state and enters a new one.
+    Reset(Box<dyn GameState<C>>),
+    /// Exits every state.
+    Quit,
+}
+
+impl<C> Transition<C> {
+    pub fn push(state: impl GameState<C> + 'static) -> Self {
+        Transition::Push(Box::new(state))
+    }
+
+    pub fn switch(state: impl GameState<C> + 'static) -> Self {
+        Transition::Switch(Box::new(state))
+    }
+
+    pub fn reset(state: impl GameState<C> + 'static) -> Self {
+        Transition::Reset(Box::new(state))
+    }
+}
+
+/// Whether `event` is a press of the escape key, it leaves menus and pauses
+/// the game.
+fn is_escape(event: &WindowEvent) -> bool {
+    matches!(
+        event,
+        WindowEvent::KeyboardInput {
+            input: KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(VirtualKeyCode::Escape),
+                ..
+            },
+            ..
+        }
+    )
+}
+
+/// States of the game, the top one is active and the ones below it are
+/// paused.
+pub struct StateStack<C> {
+    states: Vec<Box<dyn GameState<C>>>,
+}
+
+impl<C> Default for StateStack<C> {
+    fn default() -> Self {
+        StateStack { states: Vec::new() }
+    }
+}
+
+impl<C> StateStack<C> {
+    pub fn new() -> Self {
+        StateStack::default()
+    }
+
+    /// Stack with `state` entered.
+    pub fn with_state(state: impl GameState<C> + 'static, context: &mut C) -> Self {
+        let mut stack = StateStack::new();
+        stack.apply(Transition::push(state), context);
+        stack
+    }
+
+    /// Once empty the game should quit.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Names of states from the bottom of the stack to the top.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.states.iter().map(|it| it.name()).collect()
+    }
+
+    pub fn input(&mut self, context: &mut C, event: &WindowEvent) -> bool {
+        match self.states.last_mut() {
+            Some(state) => state.input(context, event),
+            None => false,
+        }
+    }
+
+    /// Updates the top state and applies the transition it requested.
+    pub fn update(&mut self, context: &mut C, dt: f32) {
+        if let Some(state) = self.states.last_mut() {
+            let transition = state.update(context, dt);
+            self.apply(transition, context);
+        }
+    }
+
+    /// Renders the top state and every state it overlays, bottom first.
+    pub fn render(&mut self, context: &mut C, alpha: f32) {
+        let first = self
+            .states
+            .iter()
+            .rposition(|it| !it.is_overlay())
+            .unwrap_or(0);
+        for state in self.states.iter_mut().skip(first) {
+            state.render(context, alpha);
+        }
+    }
+
+    pub fn apply(&mut self, transition: Transition<C>, context: &mut C) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(mut state) => {
+                if let Some(top) = self.states.last_mut() {
+                    top.pause(context);
+                }
+                tracing::debug!("entering {}", state.name());
+                state.enter(context);
+                self.states.push(state);
+            }
+            Transition::Pop => {
+                self.pop(context);
+                if let Some(top) = self.states.last_mut() {
+                    top.resume(context);
+                }
+            }
+            Transition::Switch(mut state) => {
+                self.pop(context);
+                tracing::debug!("entering {}", state.name());
+                state.enter(context);
+                self.states.push(state);
+            }
+            Transition::Reset(state) => {
+                self.clear(context);
+                self.apply(Transition::Push(state), context);
+            }
+            Transition::Quit => self.clear(context),
+        }
+    }
+
+    fn pop(&mut self, context: &mut C) {
+        if let Some(mut state) = self.states.pop() {
+            tracing::debug!("exiting {}", state.name());
+            state.exit(context);
+        }
+    }
+
+    /// Exits every state, top first.
+    fn clear(&mut self, context: &mut C) {
+        while !self.states.is_empty() {
+            self.pop(context);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use winit::event::WindowEvent;
+
+use crate::{
+    client::Client,
+    math::Rect,
+    render::ui::MenuIcon,
+    ui::widget::{Button, Widget},
+};
+
+use super::{
+    is_escape,
+    menu::{draw_title, menu_column, MainMenu, Settings, WIDGET_HEIGHT},
+    GameState, Transition,
+};
+
+/// Menu over the world while the game is paused.
+pub struct Paused {
+    title: Rect,
+    resume: Button,
+    settings: Button,
+    main_menu: Button,
+    leave: bool,
+}
+
+impl Default for Paused {
+    fn default() -> Self {
+        Paused {
+            title: Rect::default(),
+            resume: Button::new("Resume").with_icon(MenuIcon::Play),
+            settings: Button::new("Settings").with_icon(MenuIcon::Settings),
+            main_menu: Button::new("Main menu"),
+            leave: false,
+        }
+    }
+}
+
+impl Paused {
+    pub fn new() -> Self {
+        Paused::default()
+    }
+
+    fn layout(&mut self, screen: Rect) {
+        let (title, mut column) = menu_column(screen, 3);
+        self.title = title;
+        for button in [&mut self.resume, &mut self.settings, &mut self.main_menu] {
+            button.rect = column.row(WIDGET_HEIGHT);
+        }
+    }
+}
+
+impl GameState<Client> for Paused {
+    fn name(&self) -> &'static str {
+        "paused"
+    }
+
+    fn input(&mut self, _client: &mut Client, event: &WindowEvent) -> bool {
+        self.leave |= is_escape(event);
+        is_escape(event)
+    }
+
+    fn update(&mut self, client: &mut Client, _dt: f32) -> Transition<Client> {
+        self.layout(client.renderer.renderer.ui.screen());
+        let resume = self.resume.update(&client.input);
+        let settings = self.settings.update(&client.input);
+        let main_menu = self.main_menu.update(&client.input);
+        if resume || std::mem::take(&mut self.leave) {
+            Transition::Pop
+        } else if settings {
+            Transition::push(Settings::new(client.renderer.config()))
+        } else if main_menu {
+            Transition::reset(MainMenu::new())
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&mut self, client: &mut Client, _alpha: f32) {
+        self.layout(client.renderer.renderer.ui.screen());
+        let batch = &mut client.renderer.renderer.ui;
+        let theme = &client.theme;
+        batch.rect(batch.screen(), theme.background);
+        draw_title(batch, theme, self.title, "Paused");
+        self.resume.draw(batch, theme);
+        self.settings.draw(batch, theme);
+        self.main_menu.draw(batch, theme);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
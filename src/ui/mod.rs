@@ -0,0 +1,218 @@
+pub mod widget;
+
+use glam::{Vec2, Vec4};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+
+use crate::math::Rect;
+
+/// Pixels of a pixel scroll delta counted as one line.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Mouse state widgets are updated with, built from window events.
+///
+/// Presses, releases and scrolling only last until [`UiInput::end_frame`].
+#[derive(Debug, Default, Clone)]
+pub struct UiInput {
+    cursor: Option<Vec2>,
+    down: bool,
+    pressed: bool,
+    released: bool,
+    /// Where the left button was last pressed.
+    press_position: Option<Vec2>,
+    scroll: f32,
+}
+
+impl UiInput {
+    pub fn new() -> Self {
+        UiInput::default()
+    }
+
+    /// Updates the state from `event`, returns whether it was a mouse event.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.move_cursor(Vec2::new(position.x as f32, position.y as f32));
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor = None,
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed => self.press(),
+                ElementState::Released => self.release(),
+            },
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(_, lines) => self.scroll(*lines),
+                MouseScrollDelta::PixelDelta(pixels) => {
+                    self.scroll(pixels.y as f32 / PIXELS_PER_LINE)
+                }
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn move_cursor(&mut self, position: Vec2) {
+        self.cursor = Some(position);
+    }
+
+    /// Presses the left button at the cursor.
+    pub fn press(&mut self) {
+        if !self.down {
+            self.down = true;
+            self.pressed = true;
+            self.press_position = self.cursor;
+        }
+    }
+
+    pub fn release(&mut self) {
+        if self.down {
+            self.down = false;
+            self.released = true;
+        }
+    }
+
+    /// Scrolls by `lines`, positive values scroll up.
+    pub fn scroll(&mut self, lines: f32) {
+        self.scroll += lines;
+    }
+
+    /// Forgets presses, releases and scrolling once every widget was
+    /// updated.
+    pub fn end_frame(&mut self) {
+        self.pressed = false;
+        self.released = false;
+        self.scroll = 0.0;
+    }
+
+    /// Cursor position in pixels from the top left corner of the window,
+    /// `None` while it's outside of it.
+    pub fn cursor(&self) -> Option<Vec2> {
+        self.cursor
+    }
+
+    /// Whether the left button is held.
+    pub fn is_down(&self) -> bool {
+        self.down
+    }
+
+    /// Whether the left button was pressed this frame.
+    pub fn pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Whether the left button was released this frame.
+    pub fn released(&self) -> bool {
+        self.released
+    }
+
+    /// Lines scrolled this frame.
+    pub fn scroll_delta(&self) -> f32 {
+        self.scroll
+    }
+
+    pub fn hovers(&self, rect: Rect) -> bool {
+        self.cursor.is_some_and(|it| rect.contains(it))
+    }
+
+    /// Whether the left button was pressed inside `rect` this frame.
+    pub fn pressed_in(&self, rect: Rect) -> bool {
+        self.pressed && self.hovers(rect)
+    }
+
+    /// Whether the left button was held since it was pressed inside `rect`.
+    pub fn held_from(&self, rect: Rect) -> bool {
+        self.down && self.press_position.is_some_and(|it| rect.contains(it))
+    }
+
+    /// Whether `rect` was clicked, the left button has to be both pressed
+    /// and released inside of it.
+    pub fn clicked(&self, rect: Rect) -> bool {
+        self.released
+            && self.hovers(rect)
+            && self.press_position.is_some_and(|it| rect.contains(it))
+    }
+}
+
+/// Colors and sizes widgets are drawn with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Backdrop behind menus.
+    pub background: Vec4,
+    pub widget: Vec4,
+    pub hovered: Vec4,
+    /// Held buttons, filled part of sliders and selected list items.
+    pub active: Vec4,
+    pub border: Vec4,
+    pub text: Vec4,
+    /// Screen pixels every font pixel covers.
+    pub text_scale: f32,
+    /// Space between widget edges and their content.
+    pub padding: f32,
+    pub border_width: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            background: Vec4::new(0.0, 0.0, 0.0, 0.6),
+            widget: Vec4::new(0.15, 0.15, 0.18, 0.9),
+            hovered: Vec4::new(0.25, 0.25, 0.3, 0.9),
+            active: Vec4::new(0.3, 0.5, 0.8, 1.0),
+            border: Vec4::new(0.6, 0.6, 0.65, 1.0),
+            text: Vec4::ONE,
+            text_scale: 2.0,
+            padding: 8.0,
+            border_width: 2.0,
+        }
+    }
+}
+
+impl Theme {
+    /// Color of a widget background that can be hovered and held.
+    pub fn fill(&self, hovered: bool, held: bool) -> Vec4 {
+        if held {
+            self.active
+        } else if hovered {
+            self.hovered
+        } else {
+            self.widget
+        }
+    }
+}
+
+/// Lays out rows top to bottom inside of a rectangle.
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    rect: Rect,
+    spacing: f32,
+    next: f32,
+}
+
+impl Column {
+    pub fn new(rect: Rect, spacing: f32) -> Self {
+        Column {
+            rect,
+            spacing,
+            next: rect.position.y,
+        }
+    }
+
+    /// Column of `size` centered in `screen`.
+    pub fn centered(screen: Rect, size: Vec2, spacing: f32) -> Self {
+        Column::new(screen.centered(size).round(), spacing)
+    }
+
+    /// Next row, as wide as the column and `height` tall.
+    pub fn row(&mut self, height: f32) -> Rect {
+        let row = Rect::new(self.rect.position.x, self.next, self.rect.size.x, height);
+        self.next += height + self.spacing;
+        row
+    }
+
+    /// Leaves `height` empty.
+    pub fn space(&mut self, height: f32) {
+        self.next += height;
+    }
+}
@@ -0,0 +1,351 @@
+use glam::Vec2;
+
+use crate::{
+    math::Rect,
+    render::ui::{MenuIcon, UiBatch},
+};
+
+use super::{Theme, UiInput};
+
+/// Element of a menu drawn inside of its rectangle.
+pub trait Widget {
+    fn rect(&self) -> Rect;
+
+    /// Handles `input`, returns whether the widget was activated or its value
+    /// changed.
+    fn update(&mut self, input: &UiInput) -> bool;
+
+    fn draw(&self, batch: &mut UiBatch, theme: &Theme);
+}
+
+/// Left aligned `text` vertically centered in `rect`.
+fn draw_label(batch: &mut UiBatch, theme: &Theme, rect: Rect, text: &str) {
+    let height = UiBatch::text_size(text, theme.text_scale).y;
+    let position = Vec2::new(rect.position.x, rect.center().y - height * 0.5);
+    batch.text(position.round(), text, theme.text_scale, theme.text);
+}
+
+/// Clickable rectangle with a centered label and an optional icon left of
+/// it.
+#[derive(Debug, Clone)]
+pub struct Button {
+    pub rect: Rect,
+    pub label: String,
+    pub icon: Option<MenuIcon>,
+    hovered: bool,
+    held: bool,
+}
+
+impl Button {
+    pub fn new(label: impl Into<String>) -> Self {
+        Button {
+            rect: Rect::default(),
+            label: label.into(),
+            icon: None,
+            hovered: false,
+            held: false,
+        }
+    }
+
+    pub fn with_icon(mut self, icon: MenuIcon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    pub fn is_hovered(&self) -> bool {
+        self.hovered
+    }
+}
+
+impl Widget for Button {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Returns `true` once the button is released over it.
+    fn update(&mut self, input: &UiInput) -> bool {
+        self.hovered = input.hovers(self.rect);
+        self.held = input.held_from(self.rect);
+        input.clicked(self.rect)
+    }
+
+    fn draw(&self, batch: &mut UiBatch, theme: &Theme) {
+        batch.rect(self.rect, theme.fill(self.hovered, self.held));
+        batch.border(self.rect, theme.border_width, theme.border);
+        batch.text_centered(self.rect, &self.label, theme.text_scale, theme.text);
+        if let Some(icon) = self.icon {
+            let size = self.rect.size.y - theme.padding * 2.0;
+            let icon_rect = Rect::new(
+                self.rect.position.x + theme.padding,
+                self.rect.position.y + theme.padding,
+                size,
+                size,
+            );
+            batch.icon(icon_rect, icon, theme.text);
+        }
+    }
+}
+
+/// Labeled box toggled by clicking anywhere on the widget.
+#[derive(Debug, Clone)]
+pub struct Checkbox {
+    pub rect: Rect,
+    pub label: String,
+    pub checked: bool,
+    hovered: bool,
+}
+
+impl Checkbox {
+    pub fn new(label: impl Into<String>, checked: bool) -> Self {
+        Checkbox {
+            rect: Rect::default(),
+            label: label.into(),
+            checked,
+            hovered: false,
+        }
+    }
+}
+
+impl Widget for Checkbox {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn update(&mut self, input: &UiInput) -> bool {
+        self.hovered = input.hovers(self.rect);
+        let clicked = input.clicked(self.rect);
+        if clicked {
+            self.checked = !self.checked;
+        }
+        clicked
+    }
+
+    fn draw(&self, batch: &mut UiBatch, theme: &Theme) {
+        let (check, label) = self.rect.split_left(self.rect.size.y);
+        batch.rect(check, theme.fill(self.hovered, false));
+        batch.border(check, theme.border_width, theme.border);
+        if self.checked {
+            batch.icon(
+                check.shrink(theme.padding * 0.5),
+                MenuIcon::Check,
+                theme.active,
+            );
+        }
+        draw_label(batch, theme, label.shrink(theme.padding), &self.label);
+    }
+}
+
+/// Value between `min` and `max` picked by dragging across the widget,
+/// snapped to multiples of `step` above `min` unless it's 0.
+#[derive(Debug, Clone)]
+pub struct Slider {
+    pub rect: Rect,
+    pub label: String,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    hovered: bool,
+    dragging: bool,
+}
+
+impl Slider {
+    pub fn new(label: impl Into<String>, value: f32, min: f32, max: f32) -> Self {
+        Slider {
+            rect: Rect::default(),
+            label: label.into(),
+            value: value.clamp(min, max),
+            min,
+            max,
+            step: 0.0,
+            hovered: false,
+            dragging: false,
+        }
+    }
+
+    pub fn with_step(mut self, step: f32) -> Self {
+        self.step = step;
+        self.value = self.snap(self.value);
+        self
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    /// How far the value is between `min` and `max`, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.max > self.min {
+            (self.value - self.min) / (self.max - self.min)
+        } else {
+            0.0
+        }
+    }
+
+    fn snap(&self, value: f32) -> f32 {
+        let value = if self.step > 0.0 {
+            self.min + ((value - self.min) / self.step).round() * self.step
+        } else {
+            value
+        };
+        value.clamp(self.min, self.max)
+    }
+
+    /// Digits after the decimal point values are shown with.
+    fn decimals(&self) -> usize {
+        if self.step >= 1.0 {
+            0
+        } else if self.step > 0.0 {
+            (-self.step.log10()).ceil() as usize
+        } else {
+            2
+        }
+    }
+}
+
+impl Widget for Slider {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn update(&mut self, input: &UiInput) -> bool {
+        self.hovered = input.hovers(self.rect);
+        if input.pressed_in(self.rect) {
+            self.dragging = true;
+        }
+        if !input.is_down() {
+            self.dragging = false;
+        }
+        match input.cursor() {
+            Some(cursor) if self.dragging => {
+                let fraction = if self.rect.size.x > 0.0 {
+                    ((cursor.x - self.rect.position.x) / self.rect.size.x).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let value = self.snap(self.min + fraction * (self.max - self.min));
+                let changed = value != self.value;
+                self.value = value;
+                changed
+            }
+            _ => false,
+        }
+    }
+
+    fn draw(&self, batch: &mut UiBatch, theme: &Theme) {
+        batch.rect(self.rect, theme.fill(self.hovered, false));
+        let filled = self.rect.split_left(self.rect.size.x * self.fraction()).0;
+        batch.rect(filled, theme.active);
+        batch.border(self.rect, theme.border_width, theme.border);
+        let text = format!("{}: {:.*}", self.label, self.decimals(), self.value);
+        batch.text_centered(self.rect, &text, theme.text_scale, theme.text);
+    }
+}
+
+/// Column of items of which one can be selected, scrolled with the mouse
+/// wheel while it's hovered.
+#[derive(Debug, Clone)]
+pub struct List {
+    pub rect: Rect,
+    pub items: Vec<String>,
+    pub selected: Option<usize>,
+    pub row_height: f32,
+    /// Index of the first visible item.
+    scroll: usize,
+    hovered: Option<usize>,
+}
+
+impl List {
+    pub fn new(items: Vec<String>, row_height: f32) -> Self {
+        List {
+            rect: Rect::default(),
+            items,
+            selected: None,
+            row_height,
+            scroll: 0,
+            hovered: None,
+        }
+    }
+
+    pub fn selected_item(&self) -> Option<&str> {
+        self.selected
+            .and_then(|it| self.items.get(it))
+            .map(String::as_str)
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Items that fit into the list at once.
+    pub fn visible_rows(&self) -> usize {
+        if self.row_height > 0.0 {
+            (self.rect.size.y / self.row_height).floor() as usize
+        } else {
+            0
+        }
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.items.len().saturating_sub(self.visible_rows())
+    }
+
+    /// Index of the item under `point`, if there is one.
+    pub fn item_at(&self, point: Vec2) -> Option<usize> {
+        if !self.rect.contains(point) || self.row_height <= 0.0 {
+            return None;
+        }
+        let row = ((point.y - self.rect.position.y) / self.row_height) as usize;
+        let index = self.scroll + row;
+        (row < self.visible_rows() && index < self.items.len()).then_some(index)
+    }
+
+    fn row_rect(&self, row: usize) -> Rect {
+        Rect::new(
+            self.rect.position.x,
+            self.rect.position.y + row as f32 * self.row_height,
+            self.rect.size.x,
+            self.row_height,
+        )
+    }
+}
+
+impl Widget for List {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Returns `true` if the selection changed.
+    fn update(&mut self, input: &UiInput) -> bool {
+        self.scroll = self.scroll.min(self.max_scroll());
+        if input.hovers(self.rect) && input.scroll_delta() != 0.0 {
+            // scrolling up shows earlier items
+            let rows = -input.scroll_delta().round() as isize;
+            self.scroll =
+                (self.scroll as isize + rows).clamp(0, self.max_scroll() as isize) as usize;
+        }
+        self.hovered = input.cursor().and_then(|it| self.item_at(it));
+        match self.hovered {
+            Some(index) if input.clicked(self.rect) && self.selected != Some(index) => {
+                self.selected = Some(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn draw(&self, batch: &mut UiBatch, theme: &Theme) {
+        batch.rect(self.rect, theme.widget);
+        let visible = self.scroll..(self.scroll + self.visible_rows()).min(self.items.len());
+        for (row, index) in visible.enumerate() {
+            let rect = self.row_rect(row);
+            if self.selected == Some(index) {
+                batch.rect(rect, theme.active);
+            } else if self.hovered == Some(index) {
+                batch.rect(rect, theme.hovered);
+            }
+            draw_label(batch, theme, rect.shrink(theme.padding), &self.items[index]);
+        }
+        batch.border(self.rect, theme.border_width, theme.border);
+    }
+}
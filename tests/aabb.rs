@@ -0,0 +1,124 @@
+use std::f32::consts::FRAC_PI_2;
+
+use earth_oxide::{
+    math::{Frustum, Intersection, Plane, AABB},
+    render::camera::Camera,
+};
+use glam::{IVec3, Mat4, Vec3};
+
+fn aabb(start: [f32; 3], end: [f32; 3]) -> AABB<f32, Vec3> {
+    AABB::new(Vec3::from(start), Vec3::from(end))
+}
+
+#[test]
+fn corners_are_ordered() {
+    let b = aabb([1.0, -1.0, 2.0], [-1.0, 1.0, 0.0]);
+    assert_eq!(*b.start(), Vec3::new(-1.0, -1.0, 0.0));
+    assert_eq!(*b.end(), Vec3::new(1.0, 1.0, 2.0));
+    assert_eq!(b.dimensions(), [2.0, 2.0, 2.0]);
+    assert_eq!(b.center(), Vec3::new(0.0, 0.0, 1.0));
+}
+
+#[test]
+fn containment() {
+    let outer = aabb([0.0; 3], [4.0; 3]);
+    let inner = aabb([1.0; 3], [2.0; 3]);
+
+    assert!(outer.contains(&inner));
+    assert!(!inner.contains(&outer));
+    assert!(outer.contains_point(&Vec3::splat(4.0)));
+    assert!(!outer.contains_point(&Vec3::new(4.0, 4.1, 0.0)));
+}
+
+#[test]
+fn intersection_and_union() {
+    let a = aabb([0.0; 3], [2.0; 3]);
+    let b = aabb([1.0; 3], [3.0; 3]);
+    let touching = aabb([2.0, 0.0, 0.0], [3.0, 2.0, 2.0]);
+
+    assert!(a.intersects(&b));
+    assert_eq!(a.intersection(&b), Some(aabb([1.0; 3], [2.0; 3])));
+    assert!(!a.intersects(&touching));
+    assert_eq!(a.intersection(&touching), None);
+
+    assert_eq!(a.union(&touching), aabb([0.0; 3], [3.0, 2.0, 2.0]));
+}
+
+#[test]
+fn expansion() {
+    let a = aabb([0.0; 3], [1.0; 3]);
+
+    assert_eq!(a.expand(1.0), aabb([-1.0; 3], [2.0; 3]));
+    assert_eq!(
+        a.include(&Vec3::new(-1.0, 0.5, 3.0)),
+        aabb([-1.0, 0.0, 0.0], [1.0, 1.0, 3.0])
+    );
+    assert_eq!(
+        AABB::from_points([Vec3::X, Vec3::Y, Vec3::NEG_Z]),
+        Some(aabb([0.0, 0.0, -1.0], [1.0, 1.0, 0.0]))
+    );
+}
+
+#[test]
+fn integer_boxes() {
+    let a = AABB::new(IVec3::ZERO, IVec3::splat(4));
+    let b = AABB::new(IVec3::splat(2), IVec3::splat(6));
+
+    assert_eq!(
+        a.intersection(&b),
+        Some(AABB::new(IVec3::splat(2), IVec3::splat(4)))
+    );
+    assert_eq!(a.expand(1).dimensions(), [6, 6, 6]);
+}
+
+#[test]
+fn plane_test() {
+    let plane = Plane::new(Vec3::Y, 0.0);
+
+    assert_eq!(
+        aabb([0.0, 1.0, 0.0], [1.0; 3]).test_plane(&plane),
+        Intersection::Inside
+    );
+    assert_eq!(
+        aabb([0.0, -1.0, 0.0], [1.0; 3]).test_plane(&plane),
+        Intersection::Intersecting
+    );
+    assert_eq!(
+        aabb([0.0, -2.0, 0.0], [1.0, -1.0, 1.0]).test_plane(&plane),
+        Intersection::Outside
+    );
+}
+
+#[test]
+fn frustum_test() {
+    let camera = Camera::perspective(Vec3::ZERO, Vec3::NEG_Z, FRAC_PI_2, 1.0, 0.1, 100.0);
+    let frustum = camera.frustum();
+
+    assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -10.0)));
+    assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 10.0)));
+
+    let ahead = aabb([-1.0, -1.0, -11.0], [1.0, 1.0, -9.0]);
+    let behind = aabb([-1.0, -1.0, 9.0], [1.0, 1.0, 11.0]);
+    let too_far = aabb([-1.0, -1.0, -300.0], [1.0, 1.0, -200.0]);
+    let crossing_left = aabb([-20.0, -1.0, -11.0], [-9.0, 1.0, -9.0]);
+
+    assert_eq!(ahead.test_frustum(&frustum), Intersection::Inside);
+    assert_eq!(behind.test_frustum(&frustum), Intersection::Outside);
+    assert_eq!(too_far.test_frustum(&frustum), Intersection::Outside);
+    assert_eq!(
+        crossing_left.test_frustum(&frustum),
+        Intersection::Intersecting
+    );
+}
+
+#[test]
+fn transformed_bounds() {
+    let a = aabb([0.0; 3], [1.0; 3]);
+    let moved = a.transformed(Mat4::from_translation(Vec3::new(0.0, 0.0, -5.0)));
+
+    assert_eq!(moved, aabb([0.0, 0.0, -5.0], [1.0, 1.0, -4.0]));
+
+    let identity = Frustum::from_view_projection(Mat4::IDENTITY);
+    assert!(a.intersects_frustum(&identity));
+    assert!(!moved.intersects_frustum(&identity));
+}
@@ -0,0 +1,91 @@
+use earth_oxide::render::pool::{Move, RangeAllocator};
+
+#[test]
+fn allocations_are_aligned() {
+    let mut alloc = RangeAllocator::new(64, 4);
+
+    let a = alloc.allocate(3).unwrap();
+    let b = alloc.allocate(8).unwrap();
+
+    assert_eq!(alloc.range(a), Some(0..4));
+    assert_eq!(alloc.range(b), Some(4..12));
+    assert_eq!(alloc.free_space(), 52);
+}
+
+#[test]
+fn allocation_fails_when_full() {
+    let mut alloc = RangeAllocator::new(16, 4);
+
+    assert!(alloc.allocate(12).is_some());
+    assert!(alloc.allocate(8).is_none());
+    assert!(alloc.allocate(4).is_some());
+    assert!(alloc.allocate(1).is_none());
+}
+
+#[test]
+fn freed_ranges_are_reused_and_coalesced() {
+    let mut alloc = RangeAllocator::new(32, 4);
+
+    let a = alloc.allocate(8).unwrap();
+    let b = alloc.allocate(8).unwrap();
+    let c = alloc.allocate(8).unwrap();
+    let _d = alloc.allocate(8).unwrap();
+    assert_eq!(alloc.free_space(), 0);
+
+    assert!(alloc.free(a));
+    assert!(alloc.free(c));
+    assert!(!alloc.free(c));
+    assert_eq!(alloc.largest_free(), 8);
+
+    assert!(alloc.free(b));
+    assert_eq!(alloc.largest_free(), 24);
+
+    let e = alloc.allocate(20).unwrap();
+    assert_eq!(alloc.range(e), Some(0..20));
+}
+
+#[test]
+fn grow_extends_trailing_free_range() {
+    let mut alloc = RangeAllocator::new(16, 4);
+
+    let _a = alloc.allocate(8).unwrap();
+    alloc.grow(32);
+
+    assert_eq!(alloc.size(), 32);
+    assert_eq!(alloc.largest_free(), 24);
+}
+
+#[test]
+fn defragment_packs_allocations() {
+    let mut alloc = RangeAllocator::new(32, 4);
+
+    let a = alloc.allocate(8).unwrap();
+    let b = alloc.allocate(8).unwrap();
+    let c = alloc.allocate(8).unwrap();
+    let d = alloc.allocate(8).unwrap();
+    alloc.free(a);
+    alloc.free(c);
+    assert!(alloc.fragmentation() > 0.0);
+    assert!(alloc.allocate(16).is_none());
+
+    let moves = alloc.defragment();
+
+    assert_eq!(
+        moves,
+        vec![
+            Move {
+                id: b,
+                from: 8..16,
+                to: 0..8,
+            },
+            Move {
+                id: d,
+                from: 24..32,
+                to: 8..16,
+            },
+        ]
+    );
+    assert_eq!(alloc.range(d), Some(8..16));
+    assert_eq!(alloc.fragmentation(), 0.0);
+    assert!(alloc.allocate(16).is_some());
+}
@@ -0,0 +1,408 @@
+use earth_oxide::{
+    block::{Block, BlockID, BlockRegistry, RenderLayer},
+    content::{
+        chunk::ArrayChunk,
+        light::{Light, MAX_LIGHT},
+        world::{BlockCoord, BlockPos, ChunkCoord, Terrain, CHUNK_SIZE},
+    },
+    render::{
+        chunk::{
+            build_chunk_mesh, sort_faces, ChunkMesh, ChunkMeshes, ChunkPipelines, Face, Lod,
+            LodChunk, LodSettings,
+        },
+        mesh::{MeshManager, PipelineId},
+    },
+};
+
+use glam::{Vec3, Vec4};
+
+const NO_NEIGHBORS: [Option<&LodChunk>; 6] = [None; 6];
+
+const STONE: BlockID = 1;
+const LEAVES: BlockID = 10;
+const GLASS: BlockID = 11;
+const WATER: BlockID = 12;
+
+fn blocks() -> BlockRegistry {
+    let mut blocks = BlockRegistry::new();
+    blocks.register(STONE, Block::new(Vec4::new(0.5, 0.5, 0.5, 1.0)));
+    blocks.register(
+        LEAVES,
+        Block::new(Vec4::new(0.2, 0.6, 0.1, 0.8))
+            .with_layer(RenderLayer::Cutout)
+            .transparent(),
+    );
+    blocks.register(
+        GLASS,
+        Block::new(Vec4::new(0.9, 0.9, 1.0, 0.3))
+            .with_layer(RenderLayer::Translucent)
+            .transparent(),
+    );
+    blocks.register(
+        WATER,
+        Block::new(Vec4::new(0.1, 0.3, 0.8, 0.6))
+            .with_layer(RenderLayer::Translucent)
+            .transparent(),
+    );
+    blocks
+}
+
+fn mesh(chunk: &ArrayChunk) -> ChunkMesh {
+    build_chunk_mesh(
+        &LodChunk::new(chunk, Lod::Full),
+        &NO_NEIGHBORS,
+        &blocks(),
+        unlit,
+    )
+}
+
+fn unlit(_: [BlockPos; 3]) -> Light {
+    Light::default()
+}
+
+fn filled(lod: Lod) -> LodChunk {
+    let mut chunk = ArrayChunk::default();
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                chunk.set(x, y, z, 1);
+            }
+        }
+    }
+    LodChunk::new(&chunk, lod)
+}
+
+#[test]
+fn single_block_has_six_faces() {
+    let mut chunk = ArrayChunk::default();
+    chunk.set(4, 5, 6, 3);
+    let mesh = mesh(&chunk).opaque;
+
+    assert_eq!(mesh.vertices.len(), 6 * 4);
+    assert_eq!(mesh.indices.len(), 6 * 6);
+    for vertex in mesh.vertices.iter() {
+        assert_eq!(vertex.block, [3]);
+        for (axis, value) in vertex.position.into_iter().enumerate() {
+            let min = [4.0, 5.0, 6.0][axis];
+            assert!(value == min || value == min + 1.0);
+        }
+    }
+}
+
+#[test]
+fn faces_wind_counter_clockwise_from_outside() {
+    let mut chunk = ArrayChunk::default();
+    chunk.set(0, 0, 0, 1);
+    let mesh = mesh(&chunk).opaque;
+
+    for triangle in mesh.indices.chunks(3) {
+        let [a, b, c] =
+            [0, 1, 2].map(|i| glam::Vec3::from(mesh.vertices[triangle[i] as usize].position));
+        let normal = glam::Vec3::from(mesh.vertices[triangle[0] as usize].normal);
+        assert!((b - a).cross(c - a).dot(normal) > 0.0);
+    }
+}
+
+#[test]
+fn adjacent_blocks_hide_shared_faces() {
+    let mut chunk = ArrayChunk::default();
+    chunk.set(1, 1, 1, 1);
+    chunk.set(2, 1, 1, 1);
+    let mesh = mesh(&chunk).opaque;
+
+    assert_eq!(mesh.vertices.len(), 10 * 4);
+}
+
+#[test]
+fn downsampling_keeps_majority_block() {
+    let mut chunk = ArrayChunk::default();
+    // 5 of 8 blocks in the first half-LOD cell are solid, 3 of them stone
+    for (i, (x, y, z)) in [(0, 0, 0), (1, 0, 0), (0, 1, 0), (1, 1, 0), (0, 0, 1)]
+        .into_iter()
+        .enumerate()
+    {
+        chunk.set(x, y, z, if i < 3 { 2 } else { 5 });
+    }
+    // 3 of 8 blocks in the next cell are solid
+    for (x, y, z) in [(2, 0, 0), (3, 0, 0), (2, 1, 0)] {
+        chunk.set(x, y, z, 7);
+    }
+
+    let half = LodChunk::new(&chunk, Lod::Half);
+    assert_eq!(half.size(), CHUNK_SIZE / 2);
+    assert_eq!(half.get(0, 0, 0), 2);
+    assert_eq!(half.get(1, 0, 0), 0);
+
+    let eighth = LodChunk::new(&chunk, Lod::Eighth);
+    assert_eq!(eighth.size(), CHUNK_SIZE / 8);
+    assert_eq!(eighth.get(0, 0, 0), 0);
+}
+
+#[test]
+fn lod_faces_are_scaled() {
+    let mesh = build_chunk_mesh(&filled(Lod::Quarter), &NO_NEIGHBORS, &blocks(), unlit).opaque;
+    let cells = CHUNK_SIZE / 4;
+
+    // only the chunk surface is left
+    assert_eq!(mesh.vertices.len(), 6 * cells * cells * 4);
+    for vertex in mesh.vertices.iter() {
+        for value in vertex.position {
+            assert_eq!(value % 4.0, 0.0);
+            assert!((0.0..=CHUNK_SIZE as f32).contains(&value));
+        }
+    }
+}
+
+#[test]
+fn seams_are_closed_between_lods() {
+    let chunk = filled(Lod::Half);
+    let cells = CHUNK_SIZE / 2;
+    let face = cells * cells * 4;
+
+    let same = filled(Lod::Half);
+    let mut neighbors = NO_NEIGHBORS;
+    neighbors[Face::PosX as usize] = Some(&same);
+    let mesh = build_chunk_mesh(&chunk, &neighbors, &blocks(), unlit).opaque;
+    assert_eq!(mesh.vertices.len(), 5 * face);
+
+    let coarser = filled(Lod::Quarter);
+    neighbors[Face::PosX as usize] = Some(&coarser);
+    let mesh = build_chunk_mesh(&chunk, &neighbors, &blocks(), unlit).opaque;
+    assert_eq!(mesh.vertices.len(), 6 * face);
+}
+
+#[test]
+fn lod_is_selected_by_distance() {
+    let settings = LodSettings::default();
+    let [half, quarter, eighth] = settings.distances;
+
+    assert_eq!(Lod::select(0.0, &settings), Lod::Full);
+    assert_eq!(Lod::select(half, &settings), Lod::Half);
+    assert_eq!(Lod::select(quarter + 0.1, &settings), Lod::Quarter);
+    assert_eq!(Lod::select(eighth * 2.0, &settings), Lod::Eighth);
+    assert_eq!(Lod::ALL.map(Lod::scale), [1, 2, 4, 8]);
+}
+
+#[test]
+fn lod_changes_have_hysteresis() {
+    let settings = LodSettings::default();
+    let [half, _, _] = settings.distances;
+    let inside = half - settings.hysteresis / 2.0;
+
+    // coarser LODs are used right away
+    assert_eq!(Lod::update(Some(Lod::Full), half, &settings), Lod::Half);
+    // but finer ones only once the chunk is clearly past the boundary
+    assert_eq!(Lod::update(Some(Lod::Half), inside, &settings), Lod::Half);
+    assert_eq!(Lod::update(None, inside, &settings), Lod::Full);
+    assert_eq!(
+        Lod::update(Some(Lod::Half), half - settings.hysteresis * 2.0, &settings),
+        Lod::Full
+    );
+}
+
+#[test]
+fn faces_are_lit_from_the_front() {
+    let mut chunk = ArrayChunk::default();
+    chunk.set(4, 4, 4, 1);
+    let blocks = blocks();
+    let mesh = build_chunk_mesh(
+        &LodChunk::new(&chunk, Lod::Full),
+        &NO_NEIGHBORS,
+        &blocks,
+        |pos| {
+            match pos {
+                // only the block above is lit
+                [4, 5, 4] => Light {
+                    sky: MAX_LIGHT,
+                    block: 3,
+                },
+                _ => Light::default(),
+            }
+        },
+    )
+    .opaque;
+
+    for vertex in mesh.vertices.iter() {
+        let expected = if vertex.normal == [0.0, 1.0, 0.0] {
+            [1.0, 3.0 / MAX_LIGHT as f32]
+        } else {
+            [0.0, 0.0]
+        };
+        assert_eq!(vertex.light, expected);
+    }
+}
+
+#[test]
+fn corners_next_to_blocks_are_occluded() {
+    let mut chunk = ArrayChunk::default();
+    chunk.set(4, 4, 4, STONE);
+    chunk.set(5, 5, 4, STONE);
+    chunk.set(4, 5, 5, STONE);
+    // leaves don't occlude
+    chunk.set(3, 5, 4, LEAVES);
+    let mesh = mesh(&chunk).opaque;
+
+    let first = mesh
+        .vertices
+        .iter()
+        .position(|it| it.normal == [0.0, 1.0, 0.0] && it.position[1] == 5.0)
+        .unwrap();
+    for vertex in &mesh.vertices[first..first + 4] {
+        let expected = match [vertex.position[0], vertex.position[2]] {
+            [5.0, 5.0] => 0.0,
+            [5.0, _] | [_, 5.0] => 2.0 / 3.0,
+            _ => 1.0,
+        };
+        assert_eq!(vertex.occlusion, [expected], "{:?}", vertex.position);
+    }
+
+    // the face is split along the diagonal between the lighter corners
+    let dark = first + 2;
+    assert_eq!(mesh.vertices[dark].occlusion, [0.0]);
+    let triangles = mesh.indices.chunks(3).filter(|it| {
+        it.iter()
+            .all(|i| (first..first + 4).contains(&(*i as usize)))
+    });
+    let with_dark = triangles
+        .filter(|it| it.iter().any(|i| *i as usize == dark))
+        .count();
+    assert_eq!(with_dark, 1);
+}
+
+#[test]
+fn faces_are_split_by_layer() {
+    let mut chunk = ArrayChunk::default();
+    chunk.set(1, 1, 1, STONE);
+    chunk.set(5, 1, 1, LEAVES);
+    chunk.set(9, 1, 1, GLASS);
+    let mesh = mesh(&chunk);
+
+    assert_eq!(mesh.opaque.vertices.len(), 6 * 4);
+    assert_eq!(mesh.cutout.vertices.len(), 6 * 4);
+    assert_eq!(mesh.translucent.vertices.len(), 6 * 4);
+    assert!(mesh
+        .translucent
+        .vertices
+        .iter()
+        .all(|it| it.color == [0.9, 0.9, 1.0, 0.3]));
+}
+
+#[test]
+fn only_opaque_or_matching_blocks_hide_faces() {
+    let mut chunk = ArrayChunk::default();
+    // stone stays visible through glass, glass against stone is hidden
+    chunk.set(1, 1, 1, STONE);
+    chunk.set(2, 1, 1, GLASS);
+    // water doesn't draw faces between water blocks, but does against glass
+    chunk.set(1, 5, 1, WATER);
+    chunk.set(2, 5, 1, WATER);
+    chunk.set(3, 5, 1, GLASS);
+    // leaves are seen through each other
+    chunk.set(1, 9, 1, LEAVES);
+    chunk.set(2, 9, 1, LEAVES);
+    let mesh = mesh(&chunk);
+
+    assert_eq!(mesh.opaque.vertices.len(), 6 * 4);
+    assert_eq!(mesh.translucent.vertices.len(), (5 + 10 + 6) * 4);
+    assert_eq!(mesh.cutout.vertices.len(), 12 * 4);
+}
+
+#[test]
+fn translucent_faces_sort_back_to_front() {
+    let mut chunk = ArrayChunk::default();
+    chunk.set(1, 1, 1, GLASS);
+    chunk.set(20, 1, 1, GLASS);
+    let mut mesh = mesh(&chunk).translucent;
+
+    let eye = Vec3::new(0.0, 1.5, 1.5);
+    sort_faces(&mut mesh, eye);
+
+    assert_eq!(mesh.indices.len(), 12 * 6);
+    let distances: Vec<f32> = mesh
+        .indices
+        .chunks(6)
+        .map(|face| {
+            // the two triangles share corners 0 and 2
+            let center = [0, 1, 2, 5]
+                .map(|i| Vec3::from(mesh.vertices[face[i] as usize].position))
+                .into_iter()
+                .sum::<Vec3>()
+                / 4.0;
+            center.distance(eye)
+        })
+        .collect();
+    assert!(
+        distances.windows(2).all(|it| it[0] >= it[1]),
+        "{distances:?}"
+    );
+}
+
+fn chunk_meshes() -> ChunkMeshes {
+    let pipelines = ChunkPipelines {
+        opaque: PipelineId(0),
+        cutout: PipelineId(1),
+        translucent: PipelineId(2),
+    };
+    ChunkMeshes::new(pipelines, LodSettings::default())
+}
+
+#[test]
+fn edited_chunks_are_meshed_again() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    let coord = ChunkCoord::new(0, 0, 0);
+    let mut chunk = ArrayChunk::default();
+    chunk.set(4, 4, 4, STONE);
+    terrain.insert_chunk(coord, chunk, &blocks);
+
+    let mut meshes = MeshManager::new();
+    let mut chunks = chunk_meshes();
+    let camera = Vec3::splat(CHUNK_SIZE as f32 / 2.0);
+    chunks.update(&mut meshes, &terrain, &blocks, camera, coord);
+    let opaque = chunks.mesh(coord, RenderLayer::Opaque).unwrap();
+    assert_eq!(meshes.index_count(opaque), Some(6 * 6));
+
+    let changed = terrain.set_block(BlockCoord::new(10, 4, 4), STONE, &blocks);
+    chunks.update_changed(&mut meshes, &terrain, &blocks, camera, changed);
+    assert_eq!(chunks.mesh(coord, RenderLayer::Opaque), Some(opaque));
+    assert_eq!(meshes.index_count(opaque), Some(2 * 6 * 6));
+}
+
+#[test]
+fn neighbors_are_rebuilt_when_lod_changes() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    let near = ChunkCoord::new(1, 0, 0);
+    let far = ChunkCoord::new(0, 0, 0);
+    for coord in [near, far] {
+        let mut chunk = ArrayChunk::default();
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, STONE);
+                }
+            }
+        }
+        terrain.insert_chunk(coord, chunk, &blocks);
+    }
+
+    let mut meshes = MeshManager::new();
+    let mut chunks = chunk_meshes();
+    let half = CHUNK_SIZE as f32 / 2.0;
+    let camera = Vec3::splat(half);
+    // the near chunk hides its border behind the far one
+    for coord in [far, near] {
+        chunks.update(&mut meshes, &terrain, &blocks, camera, coord);
+    }
+    let opaque = chunks.mesh(near, RenderLayer::Opaque).unwrap();
+    let face = CHUNK_SIZE * CHUNK_SIZE * 6;
+    assert_eq!(meshes.index_count(opaque), Some(5 * face));
+
+    // the far chunk drops to a coarser LOD, the near one stays
+    let camera = Vec3::new(half + 3.2 * CHUNK_SIZE as f32, half, half);
+    chunks.update(&mut meshes, &terrain, &blocks, camera, far);
+    chunks.update(&mut meshes, &terrain, &blocks, camera, near);
+    assert_eq!(chunks.lod(far), Some(Lod::Half));
+    assert_eq!(chunks.lod(near), Some(Lod::Full));
+    assert_eq!(meshes.index_count(opaque), Some(6 * face));
+}
@@ -0,0 +1,86 @@
+use earth_oxide::{
+    block::{Block, BlockRegistry},
+    content::world::{BlockCoord, ChunkCoord, Terrain},
+    gen,
+    state::loading::request_chunks,
+};
+use glam::Vec4;
+
+const STONE: u32 = 1;
+
+fn blocks() -> BlockRegistry {
+    let mut blocks = BlockRegistry::new();
+    blocks.register(STONE, Block::new(Vec4::ONE));
+    blocks
+}
+
+#[test]
+fn closest_chunks_are_streamed_first() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    let center = ChunkCoord::new(4, 0, -2);
+    terrain.request_around(center, 2, 0);
+    // a circle of radius 2
+    assert_eq!(terrain.pending_chunks(), 13);
+
+    let mut generated = Vec::new();
+    terrain.stream(1, &blocks, |coord| {
+        generated.push(coord);
+        gen::flat(coord, 0, STONE)
+    });
+    assert_eq!(generated, [center]);
+    assert_eq!(terrain.pending_chunks(), 12);
+
+    terrain.stream(4, &blocks, |coord| {
+        generated.push(coord);
+        gen::flat(coord, 0, STONE)
+    });
+    assert!(generated[1..].iter().all(|it| {
+        [
+            it.offset(1, 0, 0),
+            it.offset(-1, 0, 0),
+            it.offset(0, 0, 1),
+            it.offset(0, 0, -1),
+        ]
+        .contains(&center)
+    }));
+
+    terrain.stream(100, &blocks, |coord| gen::flat(coord, 0, STONE));
+    assert_eq!(terrain.pending_chunks(), 0);
+    assert_eq!(terrain.loaded_chunks().count(), 13);
+}
+
+#[test]
+fn loaded_chunks_are_not_requested_again() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    terrain.request_around(ChunkCoord::new(0, 0, 0), 1, 1);
+    assert_eq!(terrain.pending_chunks(), 5 * 3);
+    terrain.stream(5, &blocks, |coord| gen::flat(coord, 0, STONE));
+    terrain.request_around(ChunkCoord::new(0, 0, 0), 1, 1);
+    assert_eq!(terrain.pending_chunks(), 10);
+}
+
+#[test]
+fn flat_chunks_are_filled_below_the_height() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    terrain.request_around(ChunkCoord::new(0, 0, 0), 0, 1);
+    terrain.stream(3, &blocks, |coord| gen::flat(coord, 8, STONE));
+    assert_eq!(terrain.block(BlockCoord::new(3, 7, 3)), Some(STONE));
+    assert_eq!(terrain.block(BlockCoord::new(3, 8, 3)), Some(0));
+    assert_eq!(terrain.block(BlockCoord::new(3, -20, 3)), Some(STONE));
+    assert_eq!(terrain.block(BlockCoord::new(3, 40, 3)), Some(0));
+}
+
+#[test]
+fn raising_view_distance_requests_new_chunks() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    request_chunks(&mut terrain, 1.0);
+    terrain.stream(100, &blocks, |coord| gen::flat(coord, 0, STONE));
+    assert_eq!(terrain.loaded_chunks().count(), 5);
+
+    request_chunks(&mut terrain, 1.5);
+    assert_eq!(terrain.pending_chunks(), 13 - 5);
+}
@@ -0,0 +1,66 @@
+use earth_oxide::{
+    math::AABB,
+    render::debug::{DebugLines, DebugView},
+};
+use glam::{Vec3, Vec4};
+use wgpu::Features;
+
+#[test]
+fn views_cycle_back_to_shaded() {
+    let features = Features::POLYGON_MODE_LINE;
+    let mut view = DebugView::Shaded;
+    let mut seen = Vec::new();
+    loop {
+        view = view.next(features);
+        seen.push(view);
+        if view == DebugView::Shaded {
+            break;
+        }
+    }
+    assert_eq!(seen.len(), DebugView::ALL.len());
+    assert_eq!(seen[0], DebugView::Wireframe);
+}
+
+#[test]
+fn unsupported_views_are_skipped() {
+    assert!(!DebugView::Wireframe.is_supported(Features::empty()));
+    assert_eq!(
+        DebugView::Shaded.next(Features::empty()),
+        DebugView::Normals
+    );
+    assert_eq!(
+        DebugView::ChunkHeatmap.next(Features::empty()),
+        DebugView::Shaded
+    );
+}
+
+#[test]
+fn boxes_have_twelve_edges() {
+    let mut lines = DebugLines::new();
+    let bounds = AABB::new(Vec3::ZERO, Vec3::new(1.0, 2.0, 3.0));
+    lines.aabb(&bounds, Vec4::ONE);
+    assert_eq!(lines.len(), 12);
+
+    for edge in lines.vertices().chunks(2) {
+        let start = Vec3::from(edge[0].position);
+        let end = Vec3::from(edge[1].position);
+        // every edge runs along a single axis
+        let delta = (end - start).abs();
+        assert_eq!(delta.cmpgt(Vec3::ZERO).bitmask().count_ones(), 1);
+    }
+}
+
+#[test]
+fn rays_and_axes_start_at_origin() {
+    let mut lines = DebugLines::new();
+    let origin = Vec3::new(1.0, 2.0, 3.0);
+    lines.ray(origin, Vec3::new(0.0, 2.0, 0.0), 5.0, Vec4::ONE);
+    assert_eq!(lines.vertices()[1].position, [1.0, 7.0, 3.0]);
+
+    lines.clear();
+    lines.axes(origin, 2.0);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines.vertices()[1].position, [3.0, 2.0, 3.0]);
+    assert_eq!(lines.vertices()[1].color, [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(lines.vertices()[5].position, [1.0, 2.0, 5.0]);
+}
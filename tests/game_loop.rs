@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use earth_oxide::game_loop::{GameLoop, LoopSettings, ManualClock};
+
+const TICK: Duration = Duration::from_millis(10);
+
+fn game_loop(max_ticks_per_frame: u32) -> (ManualClock, GameLoop<ManualClock>) {
+    let clock = ManualClock::new();
+    let settings = LoopSettings {
+        tick_rate: 100,
+        max_ticks_per_frame,
+    };
+    let mut game_loop = GameLoop::new(clock.clone(), settings);
+    assert_eq!(game_loop.advance().ticks, 0);
+    (clock, game_loop)
+}
+
+#[test]
+fn ticks_at_a_fixed_rate() {
+    let (clock, mut game_loop) = game_loop(8);
+    let mut ticks = 0;
+    // frames at 250 Hz tick every 2.5 frames
+    for _ in 0..10 {
+        clock.advance(Duration::from_millis(4));
+        ticks += game_loop.advance().ticks;
+    }
+    assert_eq!(ticks, 4);
+    assert_eq!(game_loop.ticks(), 4);
+}
+
+#[test]
+fn slow_frames_catch_up() {
+    let (clock, mut game_loop) = game_loop(8);
+    clock.advance(TICK * 3 + TICK / 2);
+    let mut lengths = Vec::new();
+    let alpha = game_loop.run(|dt| lengths.push(dt));
+    assert_eq!(lengths, [TICK; 3]);
+    assert!((alpha - 0.5).abs() < 1e-4, "{}", alpha);
+}
+
+#[test]
+fn spiral_of_death_is_capped() {
+    let (clock, mut game_loop) = game_loop(5);
+    clock.advance(TICK * 100 + TICK / 4);
+    let frame = game_loop.advance();
+    assert_eq!(frame.ticks, 5);
+    assert_eq!(frame.dropped, TICK * 95);
+    assert!((frame.alpha - 0.25).abs() < 1e-4, "{}", frame.alpha);
+
+    // dropped time isn't simulated later
+    clock.advance(TICK);
+    assert_eq!(game_loop.advance().ticks, 1);
+}
+
+#[test]
+fn alpha_stays_between_ticks() {
+    let (clock, mut game_loop) = game_loop(8);
+    for millis in [1, 3, 7, 16, 2, 25, 9] {
+        clock.advance(Duration::from_millis(millis));
+        let alpha = game_loop.advance().alpha;
+        assert!((0.0..1.0).contains(&alpha), "{}", alpha);
+    }
+}
@@ -0,0 +1,24 @@
+use earth_oxide::{
+    error::RenderError,
+    render::headless::{HeadlessConfig, HeadlessState},
+};
+
+#[test]
+fn renders_dev_triangle() {
+    let state = pollster::block_on(HeadlessState::new(HeadlessConfig {
+        width: 64,
+        height: 64,
+        force_fallback_adapter: true,
+        ..Default::default()
+    }));
+    let mut state = match state {
+        Ok(state) => state,
+        // machines without any (software) adapter can't run this test
+        Err(RenderError::NoAdapter) => return,
+        Err(err) => panic!("{}", err),
+    };
+
+    let frame = state.render().unwrap();
+    assert_eq!(frame.dimensions(), (64, 64));
+    assert_ne!(frame.get_pixel(32, 32), frame.get_pixel(0, 0));
+}
@@ -0,0 +1,155 @@
+use earth_oxide::{
+    block::{Block, BlockID, BlockRegistry, AIR},
+    content::{
+        chunk::ArrayChunk,
+        light::MAX_LIGHT,
+        world::{BlockCoord, ChunkCoord, Terrain, CHUNK_SIZE},
+    },
+};
+use glam::Vec4;
+
+const STONE: BlockID = 1;
+const TORCH: BlockID = 2;
+const GLASS: BlockID = 3;
+
+fn blocks() -> BlockRegistry {
+    let mut blocks = BlockRegistry::new();
+    blocks.register(STONE, Block::new(Vec4::ONE));
+    blocks.register(TORCH, Block::new(Vec4::ONE).with_emission(14).transparent());
+    blocks.register(GLASS, Block::new(Vec4::ONE).transparent());
+    blocks
+}
+
+fn layer(y: usize, block: BlockID) -> ArrayChunk {
+    let mut chunk = ArrayChunk::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            chunk.set(x, y, z, block);
+        }
+    }
+    chunk
+}
+
+fn pos(x: i64, y: i64, z: i64) -> BlockCoord {
+    BlockCoord::new(x, y, z)
+}
+
+#[test]
+fn open_sky_is_fully_lit() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    terrain.insert_chunk(ChunkCoord::new(0, 0, 0), layer(0, STONE), &blocks);
+
+    assert_eq!(terrain.light().get(pos(5, 1, 5)).sky, MAX_LIGHT);
+    assert_eq!(terrain.light().get(pos(5, 31, 5)).sky, MAX_LIGHT);
+    assert_eq!(terrain.light().get(pos(5, 0, 5)).sky, 0);
+}
+
+#[test]
+fn roof_shades_light_falls_off_under_it() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    terrain.insert_chunk(ChunkCoord::new(0, 0, 0), ArrayChunk::default(), &blocks);
+
+    for x in 0..8 {
+        for z in 0..8 {
+            terrain.set_block(pos(x, 10, z), STONE, &blocks);
+        }
+    }
+
+    // light comes in around the edges of the roof
+    assert_eq!(terrain.light().get(pos(8, 5, 3)).sky, MAX_LIGHT);
+    assert_eq!(terrain.light().get(pos(7, 5, 3)).sky, MAX_LIGHT - 1);
+    assert_eq!(terrain.light().get(pos(3, 9, 3)).sky, MAX_LIGHT - 5);
+
+    // and fills the space again once it's removed
+    for x in 0..8 {
+        for z in 0..8 {
+            terrain.set_block(pos(x, 10, z), AIR, &blocks);
+        }
+    }
+    assert_eq!(terrain.light().get(pos(3, 5, 3)).sky, MAX_LIGHT);
+}
+
+#[test]
+fn transparent_blocks_let_sunlight_through() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    terrain.insert_chunk(ChunkCoord::new(0, 0, 0), layer(20, GLASS), &blocks);
+
+    assert_eq!(terrain.light().get(pos(3, 20, 3)).sky, MAX_LIGHT);
+    assert_eq!(terrain.light().get(pos(3, 4, 3)).sky, MAX_LIGHT);
+}
+
+#[test]
+fn torch_light_spreads_across_chunks() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    let roof = layer(CHUNK_SIZE - 1, STONE);
+    terrain.insert_chunk(ChunkCoord::new(0, 0, 0), roof.clone(), &blocks);
+    terrain.insert_chunk(ChunkCoord::new(1, 0, 0), roof, &blocks);
+
+    let changed = terrain.set_block(pos(30, 5, 5), TORCH, &blocks);
+    assert!(changed.contains(&ChunkCoord::new(0, 0, 0)));
+    assert!(changed.contains(&ChunkCoord::new(1, 0, 0)));
+
+    assert_eq!(terrain.light().get(pos(30, 5, 5)).block, 14);
+    assert_eq!(terrain.light().get(pos(33, 5, 5)).block, 11);
+    assert_eq!(terrain.light().get(pos(30, 5, 9)).block, 10);
+
+    // removing the torch darkens everything it lit
+    terrain.set_block(pos(30, 5, 5), AIR, &blocks);
+    assert_eq!(terrain.light().get(pos(30, 5, 5)).block, 0);
+    assert_eq!(terrain.light().get(pos(33, 5, 5)).block, 0);
+}
+
+#[test]
+fn overlapping_torches_keep_remaining_light() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    terrain.insert_chunk(ChunkCoord::new(0, 0, 0), ArrayChunk::default(), &blocks);
+
+    terrain.set_block(pos(5, 5, 5), TORCH, &blocks);
+    terrain.set_block(pos(9, 5, 5), TORCH, &blocks);
+    assert_eq!(terrain.light().get(pos(7, 5, 5)).block, 12);
+
+    terrain.set_block(pos(5, 5, 5), AIR, &blocks);
+    assert_eq!(terrain.light().get(pos(7, 5, 5)).block, 12);
+    assert_eq!(terrain.light().get(pos(5, 5, 5)).block, 10);
+    assert_eq!(terrain.light().get(pos(2, 5, 5)).block, 7);
+}
+
+#[test]
+fn walls_block_light() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    terrain.insert_chunk(ChunkCoord::new(0, 0, 0), ArrayChunk::default(), &blocks);
+
+    terrain.set_block(pos(5, 5, 5), TORCH, &blocks);
+    assert_eq!(terrain.light().get(pos(7, 5, 5)).block, 12);
+
+    for y in 0..CHUNK_SIZE as i64 {
+        for z in 0..CHUNK_SIZE as i64 {
+            terrain.set_block(pos(6, y, z), STONE, &blocks);
+        }
+    }
+    assert_eq!(terrain.light().get(pos(6, 5, 5)).block, 0);
+    assert_eq!(terrain.light().get(pos(7, 5, 5)).block, 0);
+    assert_eq!(terrain.light().get(pos(5, 5, 5)).block, 14);
+}
+
+#[test]
+fn loading_chunk_above_shades_chunk_below() {
+    let blocks = blocks();
+    let mut terrain = Terrain::new();
+    terrain.insert_chunk(ChunkCoord::new(0, 0, 0), ArrayChunk::default(), &blocks);
+    assert_eq!(terrain.light().get(pos(5, 5, 5)).sky, MAX_LIGHT);
+
+    let changed = terrain.insert_chunk(ChunkCoord::new(0, 1, 0), layer(0, STONE), &blocks);
+    assert!(changed.contains(&ChunkCoord::new(0, 0, 0)));
+    assert_eq!(terrain.light().get(pos(5, 5, 5)).sky, 0);
+    assert_eq!(terrain.light().get(pos(5, 40, 5)).sky, MAX_LIGHT);
+
+    terrain.remove_chunk(ChunkCoord::new(0, 1, 0), &blocks);
+    assert_eq!(terrain.light().get(pos(5, 5, 5)).sky, MAX_LIGHT);
+}
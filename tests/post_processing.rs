@@ -0,0 +1,120 @@
+use earth_oxide::{
+    error::RenderError,
+    render::{
+        post::{
+            Lut, PostEffect, PostSettings, TonemapSettings, TonemapUniform, Tonemapper,
+            MAX_POST_EFFECTS,
+        },
+        shadow::ShadowSettings,
+        Renderer,
+    },
+};
+
+fn order(post: &PostSettings) -> Vec<&'static str> {
+    let mut graph = Renderer::graph(
+        wgpu::TextureFormat::Bgra8UnormSrgb,
+        post,
+        &ShadowSettings::default(),
+        1,
+    );
+    graph.compile().unwrap();
+    graph.order().unwrap().collect()
+}
+
+fn position(order: &[&str], pass: &str) -> usize {
+    order.iter().position(|it| *it == pass).unwrap()
+}
+
+#[test]
+fn effects_run_in_configured_order() {
+    let order = order(&PostSettings::default());
+    let main = position(&order, "main");
+    let translucent = position(&order, "translucent");
+    let bloom = position(&order, "bloom");
+    let tonemap = position(&order, "tonemap");
+    let fxaa = position(&order, "fxaa");
+    assert!(main < translucent && translucent < bloom);
+    assert!(bloom < tonemap && tonemap < fxaa);
+
+    let reversed = PostSettings {
+        effects: vec![
+            PostEffect::Fxaa(Default::default()),
+            PostEffect::Tonemap(Default::default()),
+        ],
+    };
+    let order = self::order(&reversed);
+    assert!(position(&order, "fxaa") < position(&order, "tonemap"));
+    assert!(!order.contains(&"bloom"));
+}
+
+#[test]
+fn empty_chain_presents_the_image() {
+    let order = order(&PostSettings {
+        effects: Vec::new(),
+    });
+    assert!(position(&order, "translucent") < position(&order, "present"));
+}
+
+#[test]
+fn multisampled_scene_is_resolved_before_post_processing() {
+    let mut graph = Renderer::graph(
+        wgpu::TextureFormat::Bgra8UnormSrgb,
+        &PostSettings::default(),
+        &ShadowSettings::default(),
+        4,
+    );
+    graph.compile().unwrap();
+    let order: Vec<_> = graph.order().unwrap().collect();
+    assert!(position(&order, "main") < position(&order, "translucent"));
+    assert!(position(&order, "translucent") < position(&order, "debug"));
+    assert!(position(&order, "debug") < position(&order, "bloom"));
+    assert!(position(&order, "shadow") < position(&order, "main"));
+    assert!(position(&order, "cull") < position(&order, "main"));
+    assert!(position(&order, "main") < position(&order, "hi_z"));
+}
+
+#[test]
+fn long_chains_are_truncated() {
+    let post = PostSettings {
+        effects: vec![PostEffect::Fxaa(Default::default()); MAX_POST_EFFECTS + 2],
+    };
+    let order = order(&post);
+    assert_eq!(
+        order.iter().filter(|it| **it == "fxaa").count(),
+        MAX_POST_EFFECTS
+    );
+}
+
+#[test]
+fn tonemapper_selects_shader_curve() {
+    let settings = TonemapSettings {
+        tonemapper: Tonemapper::Reinhard,
+        exposure: 2.0,
+    };
+    let uniform = TonemapUniform::from(&settings);
+    assert_eq!(uniform.curve, 1);
+    assert_eq!(uniform.exposure, 2.0);
+    assert_eq!(TonemapUniform::from(&TonemapSettings::default()).curve, 2);
+}
+
+#[test]
+fn identity_lut_maps_colors_to_themselves() {
+    let lut = Lut::identity(4);
+    assert_eq!(lut.size(), 4);
+    assert_eq!(lut.get(0, 0, 0), [0, 0, 0, 255]);
+    assert_eq!(lut.get(3, 0, 1), [255, 0, 85, 255]);
+    assert_eq!(lut.get(3, 3, 3), [255, 255, 255, 255]);
+}
+
+#[test]
+fn lut_strips_are_read_square_by_square() {
+    let identity = Lut::identity(4);
+    let strip = image::RgbaImage::from_fn(16, 4, |x, y| image::Rgba(identity.get(x % 4, y, x / 4)));
+    assert_eq!(Lut::from_image(&strip).unwrap(), identity);
+
+    let invalid = image::RgbaImage::new(16, 3);
+    assert!(matches!(
+        Lut::from_image(&invalid),
+        Err(RenderError::InvalidLut(16, 3))
+    ));
+}
@@ -0,0 +1,80 @@
+use earth_oxide::{
+    error::GraphError,
+    render::graph::{AttachmentDescriptor, Attachments, GraphPass, RenderGraph, SURFACE},
+};
+
+struct TestPass {
+    reads: &'static [&'static str],
+    writes: &'static [&'static str],
+}
+
+impl GraphPass<()> for TestPass {
+    fn reads(&self) -> &[&'static str] {
+        self.reads
+    }
+
+    fn writes(&self) -> &[&'static str] {
+        self.writes
+    }
+
+    fn execute(&mut self, _: &mut (), _: &mut wgpu::CommandEncoder, _: &Attachments) {}
+}
+
+fn pass(reads: &'static [&'static str], writes: &'static [&'static str]) -> TestPass {
+    TestPass { reads, writes }
+}
+
+fn attachment() -> AttachmentDescriptor {
+    AttachmentDescriptor::new(wgpu::TextureFormat::Rgba16Float)
+}
+
+#[test]
+fn orders_passes_by_attachments() {
+    let mut graph = RenderGraph::new()
+        .with_attachment("shadow_map", attachment())
+        .with_attachment("hdr", attachment())
+        .with_attachment("depth", attachment())
+        .with_pass("post", pass(&["hdr"], &[SURFACE]))
+        .with_pass("opaque", pass(&["shadow_map"], &["hdr", "depth"]))
+        .with_pass("ui", pass(&[], &[SURFACE]))
+        .with_pass("shadow", pass(&[], &["shadow_map"]))
+        .with_pass("transparent", pass(&["depth"], &["hdr"]));
+
+    graph.compile().unwrap();
+    let order: Vec<_> = graph.order().unwrap().collect();
+    assert_eq!(order, ["shadow", "opaque", "transparent", "post", "ui"]);
+}
+
+#[test]
+fn orders_passes_by_buffers() {
+    let mut graph = RenderGraph::new()
+        .with_attachment("hdr", attachment())
+        .with_buffer("draws")
+        .with_pass("opaque", pass(&["draws"], &["hdr"]))
+        .with_pass("cull", pass(&[], &["draws"]));
+
+    graph.compile().unwrap();
+    let order: Vec<_> = graph.order().unwrap().collect();
+    assert_eq!(order, ["cull", "opaque"]);
+}
+
+#[test]
+fn detects_cycles() {
+    let mut graph = RenderGraph::new()
+        .with_attachment("a", attachment())
+        .with_attachment("b", attachment())
+        .with_pass("first", pass(&["a"], &["b"]))
+        .with_pass("second", pass(&["b"], &["a"]));
+
+    assert!(matches!(graph.compile(), Err(GraphError::Cycle(_))));
+}
+
+#[test]
+fn rejects_unknown_attachments() {
+    let mut graph = RenderGraph::new().with_pass("post", pass(&["hdr"], &[SURFACE]));
+
+    assert!(matches!(
+        graph.compile(),
+        Err(GraphError::UnknownAttachment("post", "hdr"))
+    ));
+}
@@ -0,0 +1,130 @@
+use std::time::{Duration, Instant};
+
+use earth_oxide::render::config::{
+    BackendPreference, ConfigChanges, FrameLimiter, StateConfig, VsyncMode,
+};
+use wgpu::{Features, PresentMode};
+
+#[test]
+fn missing_settings_keep_defaults() {
+    let config = StateConfig::from_toml(
+        r#"
+        vsync = "mailbox"
+        frame_cap = 60
+
+        [features]
+        wireframe = false
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.vsync, VsyncMode::Mailbox);
+    assert_eq!(config.frame_cap, Some(60));
+    assert_eq!(config.features.requested(), Features::empty());
+    assert_eq!(config.msaa_samples, StateConfig::default().msaa_samples);
+    assert_eq!(config.backend, BackendPreference::Auto);
+}
+
+#[test]
+fn settings_survive_a_round_trip() {
+    let config = StateConfig {
+        vsync: VsyncMode::Off,
+        msaa_samples: 4,
+        backend: BackendPreference::Vulkan,
+        view_distance: 12.0,
+        frame_cap: Some(144),
+        ..Default::default()
+    };
+    let text = config.to_toml().unwrap();
+    assert_eq!(StateConfig::from_toml(&text).unwrap(), config);
+    assert_eq!(
+        StateConfig::from_toml(&StateConfig::default().to_toml().unwrap()).unwrap(),
+        StateConfig::default()
+    );
+}
+
+#[test]
+fn invalid_settings_are_errors() {
+    assert!(StateConfig::from_toml("vsync = \"sometimes\"").is_err());
+    assert!(StateConfig::from_toml("msaa_samples = \"4\"").is_err());
+}
+
+#[test]
+fn unsupported_present_modes_fall_back() {
+    let fifo = [PresentMode::Fifo];
+    assert_eq!(VsyncMode::On.present_mode(&fifo), PresentMode::Fifo);
+    assert_eq!(VsyncMode::Adaptive.present_mode(&fifo), PresentMode::Fifo);
+    assert_eq!(
+        VsyncMode::Mailbox.present_mode(&fifo),
+        PresentMode::AutoNoVsync
+    );
+    assert_eq!(
+        VsyncMode::Mailbox.present_mode(&[PresentMode::Fifo, PresentMode::Mailbox]),
+        PresentMode::Mailbox
+    );
+}
+
+#[test]
+fn changes_are_classified() {
+    let config = StateConfig::default();
+    assert!(config.changes(&config.clone()).is_empty());
+    assert_eq!(
+        config.changes(&StateConfig {
+            vsync: VsyncMode::Off,
+            msaa_samples: 4,
+            ..Default::default()
+        }),
+        ConfigChanges {
+            surface: true,
+            pipelines: true,
+            ..Default::default()
+        }
+    );
+    assert!(
+        config
+            .changes(&StateConfig {
+                backend: BackendPreference::Gl,
+                ..Default::default()
+            })
+            .restart
+    );
+    // the frame cap is read every frame, nothing has to be reconfigured
+    assert!(config
+        .changes(&StateConfig {
+            frame_cap: Some(30),
+            ..Default::default()
+        })
+        .is_empty());
+}
+
+#[test]
+fn frame_limiter_paces_frames() {
+    let start = Instant::now();
+    let interval = Duration::from_millis(10);
+    let mut limiter = FrameLimiter::default();
+
+    assert_eq!(limiter.wait(None, start), None);
+    assert_eq!(limiter.wait(Some(100), start), None);
+    assert_eq!(limiter.wait(Some(100), start), Some(start + interval));
+    assert_eq!(limiter.wait(Some(100), start + interval), None);
+    // a long frame doesn't make the following ones come sooner
+    let late = start + interval * 10;
+    assert_eq!(limiter.wait(Some(100), late), None);
+    assert_eq!(limiter.wait(Some(100), late), Some(late + interval));
+}
+
+#[test]
+fn sample_count_is_clamped_to_supported_counts() {
+    let supported = [1, 4];
+    let samples = |msaa_samples| {
+        StateConfig {
+            msaa_samples,
+            ..Default::default()
+        }
+        .sample_count(&supported)
+    };
+    assert_eq!(samples(1), 1);
+    assert_eq!(samples(4), 4);
+    assert_eq!(samples(8), 4);
+    assert_eq!(samples(2), 1);
+    assert_eq!(samples(0), 1);
+}
@@ -0,0 +1,165 @@
+use earth_oxide::{
+    error::PreprocessError,
+    render::shader::preprocessor::{Preprocessor, SourceLocation, VirtualFiles},
+};
+
+fn process(files: &VirtualFiles, source: &str) -> Result<String, PreprocessError> {
+    Preprocessor::new()
+        .with_files(files)
+        .process("main.wgsl", source)
+        .map(|(output, _)| output)
+}
+
+fn error(files: &VirtualFiles, source: &str) -> PreprocessError {
+    process(files, source).expect_err("source should be rejected")
+}
+
+#[test]
+fn defines_are_substituted() {
+    let source = "#define SIZE 4\nvar<private> a: array<f32, SIZE>;\nlet SIZE_2 = SIZE;\n";
+    assert_eq!(
+        process(&VirtualFiles::new(), source).unwrap(),
+        "var<private> a: array<f32, 4>;\nlet SIZE_2 = 4;\n"
+    );
+
+    let undefined = "#define A 1\n#undef A\nlet x = A;\n";
+    assert_eq!(
+        process(&VirtualFiles::new(), undefined).unwrap(),
+        "let x = A;\n"
+    );
+
+    let (output, _) = Preprocessor::new()
+        .with_define("SHADOWS", "true")
+        .process("main.wgsl", "let shadows = SHADOWS;")
+        .unwrap();
+    assert_eq!(output, "let shadows = true;\n");
+}
+
+#[test]
+fn nested_conditionals() {
+    let source = "\
+#define A
+#ifdef A
+a
+#ifndef B
+not b
+#ifdef B
+b
+#else
+nested else
+#endif
+#else
+not a
+#endif
+#endif
+#ifdef C
+c
+#else
+not c
+#endif
+";
+    assert_eq!(
+        process(&VirtualFiles::new(), source).unwrap(),
+        "a\nnot b\nnested else\nnot c\n"
+    );
+}
+
+#[test]
+fn inactive_branches_are_ignored() {
+    // defines and includes in skipped branches have no effect
+    let source = "#ifdef MISSING\n#define X 1\n#include \"missing.wgsl\"\n#endif\nX\n";
+    assert_eq!(process(&VirtualFiles::new(), source).unwrap(), "X\n");
+}
+
+#[test]
+fn files_are_included_once() {
+    let files = VirtualFiles::new()
+        .with("common.wgsl", "common\n")
+        .with("a.wgsl", "#include \"common.wgsl\"\na\n");
+    let source = "#include \"a.wgsl\"\n#include \"common.wgsl\"\nmain\n";
+    assert_eq!(process(&files, source).unwrap(), "common\na\nmain\n");
+}
+
+#[test]
+fn include_cycles_are_rejected() {
+    let files = VirtualFiles::new()
+        .with("a.wgsl", "#include \"b.wgsl\"\n")
+        .with("b.wgsl", "\n#include \"a.wgsl\"\n");
+    match error(&files, "#include \"a.wgsl\"\n") {
+        PreprocessError::IncludeCycle(location, path) => {
+            assert_eq!(location, SourceLocation::new("b.wgsl", 2));
+            assert_eq!(path, "a.wgsl");
+        }
+        other => panic!("unexpected error: {}", other),
+    }
+
+    assert!(matches!(
+        error(&VirtualFiles::new(), "#include \"missing.wgsl\"\n"),
+        PreprocessError::IncludeNotFound(_, path) if path == "missing.wgsl"
+    ));
+}
+
+#[test]
+fn malformed_directives_are_rejected() {
+    let files = VirtualFiles::new();
+    assert!(matches!(
+        error(&files, "fn a() {}\n#pragma once\n"),
+        PreprocessError::UnknownDirective(location, name)
+            if location == SourceLocation::new("main.wgsl", 2) && name == "pragma"
+    ));
+    assert!(matches!(
+        error(&files, "#endif\n"),
+        PreprocessError::UnmatchedDirective(_, name) if name == "endif"
+    ));
+    assert!(matches!(
+        error(&files, "#ifdef A\n#else\n#else\n#endif\n"),
+        PreprocessError::UnmatchedDirective(location, name)
+            if location.line == 3 && name == "else"
+    ));
+    assert!(matches!(
+        error(&files, "#ifdef A\n#ifndef B\n#endif\n"),
+        PreprocessError::UnterminatedConditional(location) if location.line == 1
+    ));
+    assert!(matches!(
+        error(&files, "#define 1A 2\n"),
+        PreprocessError::MalformedDirective(..)
+    ));
+    assert!(matches!(
+        error(&files, "#include common.wgsl\n"),
+        PreprocessError::MalformedDirective(..)
+    ));
+}
+
+#[test]
+fn source_map_points_at_original_lines() {
+    let files = VirtualFiles::new().with("common.wgsl", "// common\nlet a = 1;\n");
+    let source = "#define B 2\n#include \"common.wgsl\"\n\nlet b = B;\n";
+    let (output, map) = Preprocessor::new()
+        .with_files(&files)
+        .process("main.wgsl", source)
+        .unwrap();
+    assert_eq!(output, "// common\nlet a = 1;\n\nlet b = 2;\n");
+
+    assert_eq!(
+        map.location(1),
+        Some(&SourceLocation::new("common.wgsl", 1))
+    );
+    assert_eq!(
+        map.location(2),
+        Some(&SourceLocation::new("common.wgsl", 2))
+    );
+    assert_eq!(map.location(3), Some(&SourceLocation::new("main.wgsl", 3)));
+    assert_eq!(map.location(4), Some(&SourceLocation::new("main.wgsl", 4)));
+    assert_eq!(map.location(0), None);
+    assert_eq!(map.location(5), None);
+    assert_eq!(
+        map.files().collect::<Vec<_>>(),
+        ["common.wgsl", "main.wgsl"]
+    );
+
+    let offset = output.find("let b").unwrap();
+    assert_eq!(
+        map.location_of_offset(&output, offset),
+        Some(&SourceLocation::new("main.wgsl", 4))
+    );
+}
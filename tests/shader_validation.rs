@@ -0,0 +1,203 @@
+use earth_oxide::{
+    error::ShaderError,
+    render::{
+        chunk::{
+            chunk_cutout_pipeline, chunk_debug_pipeline, chunk_pipeline, chunk_shadow_pipeline,
+            chunk_translucent_pipeline,
+        },
+        debug::{debug_lines_pipeline, DebugView},
+        occlusion::{HI_Z_SHADER, OCCLUSION_SHADER},
+        pipeline::{Pipeline, VertexInterface},
+        post::{bloom_pipeline, color_grade_pipeline, fxaa_pipeline, tonemap_pipeline, BloomStage},
+        shader::{
+            load_source,
+            preprocessor::{Preprocessor, SourceLocation, VirtualFiles},
+            ShaderSource, WgslSource, COMMON_WGSL, DEV_SHADER,
+        },
+        sky::sky_pipeline,
+        ui::ui_pipeline,
+        vertex::{DevVertexData, InstanceData, VertexData},
+    },
+};
+
+fn dev_pipeline(
+    vertex_struct: &'static str,
+) -> Result<Pipeline<'static, WgslSource<'static>>, ShaderError> {
+    let files = VirtualFiles::new()
+        .with("common.wgsl", COMMON_WGSL)
+        .with("dev_vertex.wgsl", vertex_struct);
+    let shader = DEV_SHADER.preprocess("shader.wgsl", &Preprocessor::new().with_files(&files))?;
+
+    Ok(Pipeline::new(
+        shader,
+        VertexInterface {
+            entry_point: "vs_main".to_string(),
+            buffers: vec![DevVertexData::LAYOUT],
+        },
+        None,
+    ))
+}
+
+#[test]
+fn dev_shader_is_valid() {
+    dev_pipeline(DevVertexData::WGSL_STRUCT)
+        .unwrap()
+        .validate()
+        .unwrap();
+}
+
+#[test]
+fn chunk_shader_is_valid() {
+    chunk_pipeline(wgpu::TextureFormat::Bgra8UnormSrgb)
+        .validate()
+        .unwrap();
+    chunk_cutout_pipeline(wgpu::TextureFormat::Bgra8UnormSrgb)
+        .validate()
+        .unwrap();
+    chunk_translucent_pipeline(wgpu::TextureFormat::Bgra8UnormSrgb)
+        .validate()
+        .unwrap();
+    chunk_shadow_pipeline().validate().unwrap();
+}
+
+#[test]
+fn post_shaders_are_valid() {
+    let format = wgpu::TextureFormat::Bgra8UnormSrgb;
+    tonemap_pipeline(format).validate().unwrap();
+    fxaa_pipeline(format).validate().unwrap();
+    color_grade_pipeline(format).validate().unwrap();
+    for stage in [
+        BloomStage::Prefilter,
+        BloomStage::Downsample,
+        BloomStage::Upsample,
+        BloomStage::Composite,
+    ] {
+        bloom_pipeline(stage, format).validate().unwrap();
+    }
+}
+
+#[test]
+fn debug_shaders_are_valid() {
+    let format = wgpu::TextureFormat::Rgba16Float;
+    debug_lines_pipeline(format).validate().unwrap();
+    for view in DebugView::ALL {
+        if let Some(pipeline) = chunk_debug_pipeline(format, view) {
+            pipeline.validate().unwrap();
+        }
+    }
+}
+
+#[test]
+fn sky_shader_is_valid() {
+    sky_pipeline(wgpu::TextureFormat::Rgba16Float)
+        .validate()
+        .unwrap();
+}
+
+#[test]
+fn ui_shader_is_valid() {
+    ui_pipeline(wgpu::TextureFormat::Bgra8UnormSrgb)
+        .validate()
+        .unwrap();
+}
+
+#[test]
+fn occlusion_shaders_are_valid() {
+    HI_Z_SHADER.validate().unwrap();
+    OCCLUSION_SHADER.validate().unwrap();
+}
+
+#[test]
+fn parse_errors_point_to_included_file() {
+    let err = dev_pipeline("struct DevVertexData {\n    @location(0) position: vec3<f32>\n    @location(1) color: vec3<f32>,\n};\n")
+        .unwrap()
+        .validate()
+        .unwrap_err();
+
+    match err {
+        ShaderError::Parse(diagnostic) => assert_eq!(
+            diagnostic.location,
+            Some(SourceLocation::new("dev_vertex.wgsl", 3))
+        ),
+        other => panic!("expected a parse error, got: {}", other),
+    }
+}
+
+#[test]
+fn invalid_shader() {
+    let err = WgslSource::new("fn f() -> f32 { return 1u; }")
+        .validate()
+        .unwrap_err();
+    assert!(matches!(err, ShaderError::Validation(_)));
+}
+
+// hot-reload builds load the file from the source directory instead
+#[cfg(not(feature = "hot-reload"))]
+#[test]
+fn invalid_shader_fails_to_load() {
+    let embedded = WgslSource::new_static("fn f() -> f32 { return 1u; }");
+    let err = load_source("invalid.wgsl", &embedded, VirtualFiles::new()).unwrap_err();
+    assert!(matches!(err, ShaderError::Validation(_)));
+}
+
+/// Reads `color` as `vec4<u32>` while [`DevVertexData`] provides `vec3<f32>`,
+/// the shader itself is valid.
+const MISMATCHED_SHADER: &str = "\
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<u32>,
+};
+
+@vertex
+fn vs_main(vertex: Vertex) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(vertex.position * vec3<f32>(vertex.color.xyz), 1.0);
+}
+";
+
+#[test]
+fn vertex_input_mismatch() {
+    let shader = WgslSource::new(MISMATCHED_SHADER);
+    assert!(shader.validate().is_ok());
+
+    let err = Pipeline::new(
+        shader,
+        VertexInterface::new("vs_main").with_buffer(DevVertexData::LAYOUT),
+        None,
+    )
+    .validate()
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ShaderError::VertexFormatMismatch { location: 1, .. }
+    ));
+}
+
+#[test]
+fn missing_vertex_input() {
+    let err = dev_pipeline("struct DevVertexData {\n    @location(0) position: vec3<f32>,\n    @location(5) color: vec3<f32>,\n};\n")
+        .unwrap()
+        .validate()
+        .unwrap_err();
+    assert!(matches!(err, ShaderError::MissingVertexAttribute(5)));
+}
+
+#[test]
+fn instance_attributes_follow_vertex_attributes() {
+    let interface = VertexInterface::new("vs_main")
+        .with_buffer(DevVertexData::LAYOUT)
+        .with_buffer(InstanceData::LAYOUT);
+    let locations = interface.first_locations();
+    assert_eq!(locations, vec![0, 2]);
+
+    let source = format!(
+        "{}{}{}",
+        DevVertexData::wgsl_struct_at(locations[0]),
+        InstanceData::wgsl_struct_at(locations[1]),
+        "@vertex\nfn vs_main(v: DevVertexData, i: InstanceData) -> @builtin(position) vec4<f32> {\n    return vec4<f32>(v.position * i.offset.w + i.offset.xyz, 1.0);\n}\n"
+    );
+    assert!(source.contains("@location(2) offset: vec4<f32>"));
+
+    Pipeline::new(WgslSource::new(source), interface, None)
+        .validate()
+        .unwrap();
+}
@@ -0,0 +1,155 @@
+use std::f32::consts::FRAC_PI_3;
+
+use earth_oxide::{
+    error::RenderError,
+    render::{
+        camera::Camera,
+        headless::{HeadlessConfig, HeadlessState, HEADLESS_FORMAT},
+        shadow::{cascade_splits, Cascade, ShadowSettings, ShadowUniform},
+        Renderer,
+    },
+};
+use glam::Vec3;
+
+fn camera(eye: Vec3) -> Camera {
+    Camera::perspective(
+        eye,
+        eye + Vec3::new(1.0, -0.2, 0.3),
+        FRAC_PI_3,
+        16.0 / 9.0,
+        0.1,
+        500.0,
+    )
+}
+
+fn sun() -> Vec3 {
+    Vec3::new(0.4, 1.0, -0.3).normalize()
+}
+
+#[test]
+fn splits_blend_uniform_and_logarithmic() {
+    let uniform = cascade_splits(1.0, 100.0, 4, 0.0);
+    assert_eq!(uniform, vec![25.75, 50.5, 75.25, 100.0]);
+
+    let logarithmic = cascade_splits(1.0, 100.0, 2, 1.0);
+    assert!((logarithmic[0] - 10.0).abs() < 1e-4);
+    assert!((logarithmic[1] - 100.0).abs() < 1e-3);
+
+    let mixed = cascade_splits(0.1, 256.0, 4, 0.75);
+    assert!(mixed.windows(2).all(|it| it[0] < it[1]));
+    assert!((mixed[3] - 256.0).abs() < 1e-3);
+}
+
+#[test]
+fn cascades_end_at_shadow_distance() {
+    let settings = ShadowSettings {
+        distance: 64.0,
+        cascades: 3,
+        ..Default::default()
+    };
+    let cascades = Cascade::fit_view(&camera(Vec3::ZERO), sun(), &settings);
+
+    assert_eq!(cascades.len(), 3);
+    assert!((cascades[2].far - 64.1).abs() < 1e-3);
+    // closer cascades cover less, so their texels are smaller
+    assert!(cascades
+        .windows(2)
+        .all(|it| it[0].texel_size < it[1].texel_size));
+}
+
+#[test]
+fn cascade_contains_view_slice() {
+    let corners = [
+        Vec3::new(-1.0, -1.0, -1.0),
+        Vec3::new(1.0, -1.0, -1.0),
+        Vec3::new(1.0, 1.0, -1.0),
+        Vec3::new(-1.0, 1.0, -1.0),
+        Vec3::new(-8.0, -8.0, -20.0),
+        Vec3::new(8.0, -8.0, -20.0),
+        Vec3::new(8.0, 8.0, -20.0),
+        Vec3::new(-8.0, 8.0, -20.0),
+    ];
+    let cascade = Cascade::fit(&corners, sun(), 1024, 32.0);
+
+    for corner in corners {
+        let ndc = cascade.view_projection.project_point3(corner);
+        assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0, "{ndc}");
+        assert!((0.0..=1.0).contains(&ndc.z), "{ndc}");
+    }
+
+    // casters between the slice and the sun are still in the depth range
+    let caster = corners[0] + sun() * 30.0;
+    assert!(cascade.view_projection.project_point3(caster).z >= 0.0);
+}
+
+#[test]
+fn cascades_snap_to_texels() {
+    let settings = ShadowSettings::default();
+    let point = Vec3::new(12.3, 4.5, -6.7);
+    let texel_offset = |eye: Vec3| {
+        let cascade = Cascade::fit_view(&camera(eye), sun(), &settings)[0];
+        let texels =
+            cascade.view_projection.project_point3(point) * settings.resolution as f32 / 2.0;
+        texels.truncate().fract()
+    };
+
+    // moving the camera only moves shadow maps by whole texels
+    let still = texel_offset(Vec3::ZERO);
+    let moved = texel_offset(Vec3::new(0.37, 0.0, -0.21));
+    let difference = (still - moved).abs();
+    assert!(difference.x.min(1.0 - difference.x) < 1e-2, "{difference}");
+    assert!(difference.y.min(1.0 - difference.y) < 1e-2, "{difference}");
+}
+
+#[test]
+fn disabled_shadows_have_no_cascades() {
+    let settings = ShadowSettings::default();
+    let uniform = ShadowUniform::new(&[], sun(), &settings);
+    assert_eq!(uniform.cascade_count, 0);
+    assert_eq!(uniform.sun.truncate(), sun());
+
+    let cascades = Cascade::fit_view(&camera(Vec3::ZERO), sun(), &settings);
+    let uniform = ShadowUniform::new(&cascades, sun(), &settings);
+    assert_eq!(uniform.cascade_count, settings.cascades as u32);
+    assert_eq!(uniform.splits[0], cascades[0].far);
+}
+
+#[test]
+fn single_cascade_is_an_array() {
+    let settings = ShadowSettings {
+        cascades: 1,
+        ..Default::default()
+    };
+    let cascades = Cascade::fit_view(&camera(Vec3::ZERO), sun(), &settings);
+    assert_eq!(cascades.len(), 1);
+    assert!((cascades[0].far - (settings.distance + 0.1)).abs() < 1e-3);
+
+    let attachment = settings.attachment();
+    assert_eq!(attachment.layers, 1);
+    assert_eq!(
+        attachment.view_dimension,
+        wgpu::TextureViewDimension::D2Array
+    );
+
+    let state = pollster::block_on(HeadlessState::new(HeadlessConfig {
+        width: 64,
+        height: 64,
+        force_fallback_adapter: true,
+        ..Default::default()
+    }));
+    let mut state = match state {
+        Ok(state) => state,
+        // machines without any (software) adapter can't run this part
+        Err(RenderError::NoAdapter) => return,
+        Err(err) => panic!("{}", err),
+    };
+    state.renderer.shadows = settings;
+    state.graph = Renderer::graph(
+        HEADLESS_FORMAT,
+        &state.renderer.post,
+        &state.renderer.shadows,
+        state.renderer.sample_count,
+    );
+    state.graph.resize(&state.device, 64, 64);
+    state.render().unwrap();
+}
@@ -0,0 +1,83 @@
+use earth_oxide::render::{
+    camera::Camera,
+    sky::{DayClock, Sky, SkyUniform},
+};
+use glam::Vec3;
+
+fn sky_at(time: f32) -> Sky {
+    let mut sky = Sky::default();
+    sky.clock.set_time(time);
+    sky
+}
+
+#[test]
+fn clock_wraps_around_days() {
+    let mut clock = DayClock::default();
+    clock.day_length = 100.0;
+    clock.set_time(0.9);
+    clock.advance(30.0);
+    assert!((clock.time() - 0.2).abs() < 1e-5);
+
+    clock.set_time(-0.25);
+    assert!((clock.time() - 0.75).abs() < 1e-5);
+
+    clock.paused = true;
+    clock.advance(30.0);
+    assert!((clock.time() - 0.75).abs() < 1e-5);
+}
+
+#[test]
+fn sun_rises_in_the_east_and_peaks_at_noon() {
+    let sunrise = sky_at(0.25).sun_direction();
+    assert!((sunrise - Vec3::X).length() < 1e-5, "{sunrise}");
+
+    let noon = sky_at(0.5).sun_direction();
+    assert!(noon.y > 0.9, "{noon}");
+    assert!((noon.length() - 1.0).abs() < 1e-5);
+
+    let sunset = sky_at(0.75).sun_direction();
+    assert!((sunset + Vec3::X).length() < 1e-5, "{sunset}");
+}
+
+#[test]
+fn moon_casts_light_at_night() {
+    let night = sky_at(0.0);
+    assert!(night.sun_direction().y < 0.0);
+    assert_eq!(night.light_direction(), night.moon_direction());
+    assert!(night.light_direction().y > 0.0);
+
+    let day = sky_at(0.5);
+    assert_eq!(day.light_direction(), day.sun_direction());
+}
+
+#[test]
+fn nights_are_darker_than_days() {
+    let day = sky_at(0.5);
+    let night = sky_at(0.0);
+    assert!(night.light_color().length() < day.light_color().length());
+    assert!(night.ambient().length() < day.ambient().length());
+    assert!(night.zenith().length() < day.zenith().length());
+    assert!(night.horizon().length() < day.horizon().length());
+
+    // light fades out while switching between the sun and moon
+    assert!(sky_at(0.25).light_color().length() < 1e-5);
+}
+
+#[test]
+fn uniform_holds_fog_and_camera() {
+    let eye = Vec3::new(4.0, 70.0, -3.0);
+    let camera = Camera::perspective(eye, eye + Vec3::Z, 1.0, 1.0, 0.1, 500.0);
+    let sky = Sky::default();
+    let uniform = SkyUniform::new(&sky, &camera);
+
+    assert!((uniform.camera_position.truncate() - eye).length() < 1e-3);
+    assert_eq!(uniform.fog_start, sky.fog.start);
+    assert_eq!(uniform.fog_end, sky.fog.end);
+    assert!(sky.fog.start < sky.fog.end);
+
+    // rays through the far plane point where the camera looks
+    let far = uniform
+        .inverse_view_projection
+        .project_point3(Vec3::new(0.0, 0.0, 1.0));
+    assert!((far - eye).normalize().dot(Vec3::Z) > 0.99);
+}
@@ -0,0 +1,139 @@
+use earth_oxide::state::{GameState, StateStack, Transition};
+
+/// Hook calls of every state, in order.
+#[derive(Default)]
+struct Log {
+    calls: Vec<String>,
+    /// Transition the next updated state requests.
+    next: Option<Transition<Log>>,
+}
+
+struct Recorder {
+    name: &'static str,
+    overlay: bool,
+}
+
+fn state(name: &'static str) -> Recorder {
+    Recorder {
+        name,
+        overlay: false,
+    }
+}
+
+fn overlay(name: &'static str) -> Recorder {
+    Recorder {
+        name,
+        overlay: true,
+    }
+}
+
+impl GameState<Log> for Recorder {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn enter(&mut self, log: &mut Log) {
+        log.calls.push(format!("enter {}", self.name));
+    }
+
+    fn exit(&mut self, log: &mut Log) {
+        log.calls.push(format!("exit {}", self.name));
+    }
+
+    fn pause(&mut self, log: &mut Log) {
+        log.calls.push(format!("pause {}", self.name));
+    }
+
+    fn resume(&mut self, log: &mut Log) {
+        log.calls.push(format!("resume {}", self.name));
+    }
+
+    fn update(&mut self, log: &mut Log, _dt: f32) -> Transition<Log> {
+        log.calls.push(format!("update {}", self.name));
+        log.next.take().unwrap_or(Transition::None)
+    }
+
+    fn render(&mut self, log: &mut Log, _alpha: f32) {
+        log.calls.push(format!("render {}", self.name));
+    }
+
+    fn is_overlay(&self) -> bool {
+        self.overlay
+    }
+}
+
+fn update(stack: &mut StateStack<Log>, log: &mut Log, transition: Transition<Log>) -> Vec<String> {
+    log.calls.clear();
+    log.next = Some(transition);
+    stack.update(log, 1.0 / 60.0);
+    std::mem::take(&mut log.calls)
+}
+
+#[test]
+fn hooks_run_on_transitions() {
+    let mut log = Log::default();
+    let mut stack = StateStack::with_state(state("menu"), &mut log);
+    assert_eq!(log.calls, ["enter menu"]);
+
+    assert_eq!(
+        update(&mut stack, &mut log, Transition::switch(state("world"))),
+        ["update menu", "exit menu", "enter world"]
+    );
+    assert_eq!(
+        update(&mut stack, &mut log, Transition::push(overlay("paused"))),
+        ["update world", "pause world", "enter paused"]
+    );
+    assert_eq!(stack.names(), ["world", "paused"]);
+    assert_eq!(
+        update(&mut stack, &mut log, Transition::Pop),
+        ["update paused", "exit paused", "resume world"]
+    );
+    assert_eq!(stack.names(), ["world"]);
+}
+
+#[test]
+fn only_the_top_state_is_updated() {
+    let mut log = Log::default();
+    let mut stack = StateStack::with_state(state("world"), &mut log);
+    stack.apply(Transition::push(overlay("paused")), &mut log);
+    assert_eq!(
+        update(&mut stack, &mut log, Transition::None),
+        ["update paused"]
+    );
+}
+
+#[test]
+fn overlays_render_over_states_below_them() {
+    let mut log = Log::default();
+    let mut stack = StateStack::with_state(state("menu"), &mut log);
+    stack.apply(Transition::push(state("world")), &mut log);
+    stack.apply(Transition::push(overlay("paused")), &mut log);
+    log.calls.clear();
+    stack.render(&mut log, 0.0);
+    assert_eq!(log.calls, ["render world", "render paused"]);
+
+    stack.apply(Transition::push(state("settings")), &mut log);
+    log.calls.clear();
+    stack.render(&mut log, 0.0);
+    assert_eq!(log.calls, ["render settings"]);
+}
+
+#[test]
+fn reset_and_quit_exit_every_state() {
+    let mut log = Log::default();
+    let mut stack = StateStack::with_state(state("world"), &mut log);
+    stack.apply(Transition::push(overlay("paused")), &mut log);
+    assert_eq!(
+        update(&mut stack, &mut log, Transition::reset(state("menu"))),
+        ["update paused", "exit paused", "exit world", "enter menu"]
+    );
+    assert_eq!(stack.names(), ["menu"]);
+
+    assert_eq!(
+        update(&mut stack, &mut log, Transition::Quit),
+        ["update menu", "exit menu"]
+    );
+    assert!(stack.is_empty());
+    // nothing left to update
+    assert!(update(&mut stack, &mut log, Transition::None).is_empty());
+}
@@ -0,0 +1,190 @@
+use earth_oxide::{
+    math::Rect,
+    render::{
+        font,
+        ui::{MenuIcon, UiAtlas, UiBatch},
+    },
+    ui::{
+        widget::{Button, Checkbox, List, Slider, Widget},
+        Column, UiInput,
+    },
+};
+use glam::{Vec2, Vec4};
+
+fn click(input: &mut UiInput, widget: &mut impl Widget, at: Vec2) -> bool {
+    input.move_cursor(at);
+    input.press();
+    let pressed = widget.update(input);
+    input.end_frame();
+    input.release();
+    let released = widget.update(input);
+    input.end_frame();
+    pressed || released
+}
+
+#[test]
+fn rect_edges() {
+    let rect = Rect::new(10.0, 20.0, 30.0, 40.0);
+    assert!(rect.contains(Vec2::new(10.0, 20.0)));
+    assert!(!rect.contains(Vec2::new(40.0, 30.0)));
+    assert!(!rect.contains(Vec2::new(25.0, 60.0)));
+    assert_eq!(rect.center(), Vec2::new(25.0, 40.0));
+    assert_eq!(rect.shrink(5.0), Rect::new(15.0, 25.0, 20.0, 30.0));
+    let (left, right) = rect.split_left(10.0);
+    assert_eq!(left, Rect::new(10.0, 20.0, 10.0, 40.0));
+    assert_eq!(right, Rect::new(20.0, 20.0, 20.0, 40.0));
+}
+
+#[test]
+fn text_is_measured_in_font_pixels() {
+    assert_eq!(
+        font::text_size("A"),
+        (font::GLYPH_WIDTH, font::GLYPH_HEIGHT)
+    );
+    assert_eq!(
+        font::text_size("ab\nc"),
+        (
+            2 * font::ADVANCE - 1,
+            font::LINE_HEIGHT + font::GLYPH_HEIGHT
+        )
+    );
+    assert_eq!(font::glyph_index('é'), font::glyph_index('?'));
+    // the top of 'T' is a full row, its stem is in the middle column
+    assert!((0..font::GLYPH_WIDTH).all(|x| font::is_set('T', x, 0)));
+    assert!(font::is_set('T', 2, 6) && !font::is_set('T', 0, 6));
+    assert!(!font::is_set(' ', 2, 3));
+}
+
+#[test]
+fn batch_draws_a_quad_per_visible_glyph() {
+    let mut batch = UiBatch::new(800, 600);
+    batch.rect(Rect::new(0.0, 0.0, 10.0, 10.0), Vec4::ONE);
+    assert_eq!(batch.vertices().len(), 6);
+    batch.text(Vec2::ZERO, "a b\nc", 2.0, Vec4::ONE);
+    assert_eq!(batch.vertices().len(), 6 * 4);
+    batch.border(Rect::new(0.0, 0.0, 10.0, 10.0), 1.0, Vec4::ONE);
+    assert_eq!(batch.vertices().len(), 6 * 8);
+    batch.clear();
+    assert!(batch.is_empty());
+
+    assert_eq!(
+        UiBatch::text_size("ab", 2.0),
+        Vec2::new(
+            (2 * font::ADVANCE - 1) as f32 * 2.0,
+            font::GLYPH_HEIGHT as f32 * 2.0
+        )
+    );
+}
+
+#[test]
+fn atlas_has_icons_glyphs_and_white() {
+    let atlas = UiAtlas::menu_icons();
+    let image = atlas.image();
+    let texel = |uv: Rect| {
+        let center = uv.center() * image.width() as f32;
+        image.get_pixel(center.x as u32, center.y as u32).0
+    };
+    assert_eq!(texel(UiAtlas::white_uv()), [255; 4]);
+    // icons are drawn on a transparent background
+    let icon = MenuIcon::Play.uv();
+    let size = image.width() as f32;
+    let min = (icon.min() * size).as_uvec2();
+    let max = (icon.max() * size).as_uvec2();
+    assert!((min.y..max.y).any(|y| (min.x..max.x).any(|x| image.get_pixel(x, y).0[3] > 0)));
+
+    let glyph = UiAtlas::glyph_uv('T');
+    let top_left = (glyph.min() * size).as_uvec2();
+    assert_eq!(image.get_pixel(top_left.x, top_left.y).0, [255; 4]);
+    assert_eq!(image.get_pixel(top_left.x, top_left.y + 6).0[3], 0);
+}
+
+#[test]
+fn buttons_click_on_release_inside() {
+    let mut input = UiInput::new();
+    let mut button = Button::new("Play");
+    button.rect = Rect::new(0.0, 0.0, 100.0, 40.0);
+
+    assert!(click(&mut input, &mut button, Vec2::new(50.0, 20.0)));
+    assert!(!click(&mut input, &mut button, Vec2::new(150.0, 20.0)));
+
+    // pressed inside but released outside
+    input.move_cursor(Vec2::new(50.0, 20.0));
+    input.press();
+    assert!(!button.update(&input));
+    input.end_frame();
+    input.move_cursor(Vec2::new(150.0, 20.0));
+    input.release();
+    assert!(!button.update(&input));
+    assert!(!button.is_hovered());
+}
+
+#[test]
+fn checkboxes_toggle() {
+    let mut input = UiInput::new();
+    let mut checkbox = Checkbox::new("Fog", false);
+    checkbox.rect = Rect::new(0.0, 0.0, 200.0, 30.0);
+    assert!(click(&mut input, &mut checkbox, Vec2::new(10.0, 10.0)));
+    assert!(checkbox.checked);
+    assert!(click(&mut input, &mut checkbox, Vec2::new(150.0, 10.0)));
+    assert!(!checkbox.checked);
+}
+
+#[test]
+fn sliders_follow_the_cursor_while_dragged() {
+    let mut input = UiInput::new();
+    let mut slider = Slider::new("Distance", 8.0, 2.0, 12.0).with_step(1.0);
+    slider.rect = Rect::new(100.0, 0.0, 100.0, 30.0);
+
+    input.move_cursor(Vec2::new(151.0, 10.0));
+    input.press();
+    assert!(slider.update(&input));
+    assert_eq!(slider.value, 7.0);
+    input.end_frame();
+
+    // dragging continues outside of the slider and is clamped
+    input.move_cursor(Vec2::new(400.0, 300.0));
+    assert!(slider.update(&input));
+    assert_eq!(slider.value, 12.0);
+    input.release();
+    slider.update(&input);
+    assert!(!slider.is_dragging());
+
+    input.end_frame();
+    input.move_cursor(Vec2::new(100.0, 10.0));
+    assert!(!slider.update(&input));
+    assert_eq!(slider.value, 12.0);
+}
+
+#[test]
+fn lists_select_and_scroll() {
+    let mut input = UiInput::new();
+    let items = (0..10).map(|it| format!("world {it}")).collect();
+    let mut list = List::new(items, 20.0);
+    list.rect = Rect::new(0.0, 0.0, 100.0, 60.0);
+    assert_eq!(list.visible_rows(), 3);
+
+    assert!(click(&mut input, &mut list, Vec2::new(10.0, 25.0)));
+    assert_eq!(list.selected, Some(1));
+    assert!(!click(&mut input, &mut list, Vec2::new(10.0, 25.0)));
+
+    input.move_cursor(Vec2::new(10.0, 10.0));
+    input.scroll(-2.0);
+    list.update(&input);
+    input.end_frame();
+    assert_eq!(list.scroll(), 2);
+    assert!(click(&mut input, &mut list, Vec2::new(10.0, 5.0)));
+    assert_eq!(list.selected_item(), Some("world 2"));
+
+    input.scroll(-100.0);
+    list.update(&input);
+    input.end_frame();
+    assert_eq!(list.scroll(), 7);
+}
+
+#[test]
+fn columns_stack_rows() {
+    let mut column = Column::new(Rect::new(10.0, 10.0, 200.0, 400.0), 5.0);
+    assert_eq!(column.row(40.0), Rect::new(10.0, 10.0, 200.0, 40.0));
+    column.space(10.0);
+    assert_eq!(column.row(20.0), Rect::new(10.0, 65.0, 200.0, 20.0));
+}
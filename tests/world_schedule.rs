@@ -0,0 +1,101 @@
+use bevy_ecs::prelude::*;
+use earth_oxide::{
+    block::{Block, BlockRegistry},
+    content::world::{BlockCoord, ChunkCoord, ChunkGenerator, Terrain, Tick, World, WorldStage},
+    gen,
+};
+use glam::Vec4;
+
+const STONE: u32 = 1;
+
+fn world() -> World {
+    let mut blocks = BlockRegistry::new();
+    blocks.register(STONE, Block::new(Vec4::ONE));
+    World::new(Terrain::new(), blocks)
+}
+
+#[derive(Default, Resource)]
+struct Ran(Vec<WorldStage>);
+
+fn record(stage: WorldStage) -> impl FnMut(ResMut<Ran>) {
+    move |mut ran| ran.0.push(stage)
+}
+
+#[test]
+fn stages_run_in_order() {
+    let mut world = world();
+    world.insert_resource(Ran::default());
+    // added out of order
+    for stage in WorldStage::ALL.into_iter().rev() {
+        world.add_system(stage, record(stage));
+    }
+    world.update(0.5);
+    assert_eq!(world.ecs().resource::<Ran>().0, WorldStage::ALL);
+    assert_eq!(world.tick(), Tick { dt: 0.5, count: 1 });
+}
+
+#[derive(Component)]
+struct Position(f32);
+
+#[derive(Component)]
+struct Velocity(f32);
+
+fn movement(world: &mut World) {
+    world
+        .register_component::<Position>()
+        .register_component::<Velocity>()
+        .add_system(
+            WorldStage::Physics,
+            |tick: Res<Tick>, mut query: Query<(&mut Position, &Velocity)>| {
+                for (mut position, velocity) in &mut query {
+                    position.0 += velocity.0 * tick.dt;
+                }
+            },
+        );
+}
+
+#[test]
+fn plugins_add_systems() {
+    let mut world = world();
+    world.add_plugin(movement);
+    let entity = world.spawn((Position(1.0), Velocity(2.0)));
+    world.update(0.25);
+    world.update(0.25);
+    let position = world.ecs().get::<Position>(entity).unwrap();
+    assert_eq!(position.0, 2.0);
+}
+
+#[test]
+fn terrain_is_a_resource() {
+    let mut world = world();
+    world.add_system(WorldStage::Simulation, |terrain: Res<Terrain>| {
+        assert_eq!(terrain.pending_chunks(), 1);
+    });
+    world
+        .terrain_mut()
+        .request_around(ChunkCoord::new(0, 0, 0), 0, 0);
+    world.update(0.1);
+    assert_eq!(world.terrain().loaded_chunks().count(), 0);
+}
+
+#[test]
+fn requested_chunks_are_streamed() {
+    let mut world = world();
+    world.insert_resource(ChunkGenerator::new(2, |coord| gen::flat(coord, 4, STONE)));
+    world
+        .terrain_mut()
+        .request_around(ChunkCoord::new(0, 0, 0), 1, 0);
+    assert_eq!(world.terrain().pending_chunks(), 5);
+
+    world.update(0.1);
+    assert_eq!(world.terrain().pending_chunks(), 3);
+    world.update(0.1);
+    world.update(0.1);
+    assert_eq!(world.terrain().pending_chunks(), 0);
+    assert_eq!(world.terrain().loaded_chunks().count(), 5);
+    assert_eq!(world.terrain().block(BlockCoord::new(0, 0, 0)), Some(STONE));
+
+    let changed = world.take_changed_chunks();
+    assert!(changed.contains(&ChunkCoord::new(0, 0, 0)));
+    assert!(world.take_changed_chunks().is_empty());
+}